@@ -1,9 +1,22 @@
 use mlua::{Result, Lua};
 use std::rc::Rc;
+use std::path::Path;
 use std::cell::RefCell;
-use remux_core::editor::editor::{KeyMap, Editor, Modifiers, PhysicalModifiers, EditorEvent, InputMode, MiniBufferAction};
-use remux_core::minibuffer::MiniBufferMode;
+use remux_core::editor::editor::{KeyMap, Editor, Modifiers, PhysicalModifiers};
+use remux_core::editor::events::EditorEvent;
 use remux_core::config::{config_path, UserConfig};
+use remux_core::timer::TimerHandle;
+use remux_core::process::{ProcessSpec, ProcessSink};
+use remux_core::grep::GrepProgram;
+use remux_core::lsp::LspServer;
+use remux_core::editor::layout::LineNumberStyle;
+use remux_core::diagnostics::{Diagnostic, Severity};
+use remux_core::indent::{IndentRule, IndentStyle};
+use remux_core::kmacro;
+use remux_core::log::LogLevel;
+use remux_core::registers::Register;
+use remux_core::status::{Face, ModeLineFormat, Segment, SegmentKind, BUILTIN_SEGMENTS};
+use std::time::Duration;
 
 
 pub fn parse_modifiers(s: &str) -> Modifiers {
@@ -26,6 +39,43 @@ pub fn parse_modifiers(s: &str) -> Modifiers {
 }
 
 
+/// `"name"`, `{ "name", fg = ... }` or `{ text = "...", fg = ... }`.
+fn parse_segment(value: mlua::Value) -> Result<Segment> {
+		match value {
+				mlua::Value::String(name) => Ok(Segment::named(name.to_str()?)),
+				mlua::Value::Table(t) => {
+						let kind = match (t.get::<_, Option<String>>(1)?, t.get::<_, Option<String>>("text")?) {
+								(_, Some(text)) => SegmentKind::Text(text),
+								(Some(name), None) => SegmentKind::Named(name),
+								(None, None) => return Err(mlua::Error::RuntimeError(
+										"set_mode_line: segment needs a name or text".into()
+								)),
+						};
+						let face = Face {
+								fg: t.get("fg")?,
+								bg: t.get("bg")?,
+								bold: t.get::<_, Option<bool>>("bold")?.unwrap_or(false),
+								italic: t.get::<_, Option<bool>>("italic")?.unwrap_or(false),
+								underline: t.get::<_, Option<bool>>("underline")?.unwrap_or(false),
+						};
+						Ok(Segment { kind, face })
+				}
+				_ => Err(mlua::Error::RuntimeError("set_mode_line: segments are strings or tables".into())),
+		}
+}
+
+/// Key names for `bind`: a single character, or `RET`, `TAB`, `SPC`, `DEL`.
+pub fn parse_key(s: &str) -> Option<char> {
+    match s {
+        "RET" | "enter" => Some('\n'),
+        "TAB" | "tab" => Some('\t'),
+        "SPC" | "space" => Some(' '),
+        "DEL" | "backspace" => Some('\x7f'),
+        _ => s.chars().next(),
+    }
+}
+
+
 pub fn parse_mod_mask(s: &str) -> (PhysicalModifiers, Option<char>) {
     let mut phys = PhysicalModifiers::empty();
     let mut key = None;
@@ -46,27 +96,45 @@ pub fn parse_mod_mask(s: &str) -> (PhysicalModifiers, Option<char>) {
     (phys, key)
 }
 
+/// Install the Lua API, then run `init` (`init.lua`, or the file given
+/// with `--config`); `None` runs no init file.
 pub fn load_lua(
     lua: &Lua,
     editor: Rc<RefCell<Editor>>,
     keymap: Rc<RefCell<KeyMap>>,
     lua_events: Rc<RefCell<Vec<EditorEvent>>>,
     config: Rc<RefCell<UserConfig>>,
+    init: Option<&Path>,
 ) -> Result<()> {
     let editor_hooks = editor.clone();
     let border_config = config.clone();
     let events = lua_events.clone();
 		let highlight_config = config.clone();
+		let compile_config = config.clone();
+		let grep_config = config.clone();
+		let grep_context_config = config.clone();
 		
     lua.globals().set(
 				"bind",
 				lua.create_function(move |_, (mod_str, key, cmd): (String, String, String)| {
-						let key = key.chars().next().unwrap();
+						let key = parse_key(&key).ok_or_else(|| mlua::Error::RuntimeError("bind: empty key".into()))?;
             let mods = parse_modifiers(&mod_str);
 						keymap.borrow_mut().bind(mods, key, cmd);
             Ok(())
 				})?,
     )?;
+
+		// bind_mode("compilation", "", "RET", "compile-goto-error")
+		let ed = editor.clone();
+    lua.globals().set(
+				"bind_mode",
+				lua.create_function(move |_, (mode, mod_str, key, cmd): (String, String, String, String)| {
+						let key = parse_key(&key).ok_or_else(|| mlua::Error::RuntimeError("bind_mode: empty key".into()))?;
+            let mods = parse_modifiers(&mod_str);
+						ed.borrow_mut().mode_keymaps.entry(mode).or_insert_with(KeyMap::new).bind(mods, key, cmd);
+            Ok(())
+				})?,
+    )?;
     
 		lua.globals().set(
 				"bind_mod",
@@ -110,11 +178,20 @@ pub fn load_lua(
     lua.globals().set(
 				"add_hook",
 				lua.create_function(move |lua, (name, func): (String, mlua::Function)| {
-            editor_hooks.borrow_mut().hooks.add(lua, &name, func)?;
+            editor_hooks.borrow_mut().bus.add_hook(lua, &name, func)?;
             Ok(())
 				})?,
     )?;
 
+    let events = lua_events.clone();
+    lua.globals().set(
+				"emit",
+				lua.create_function(move |_, (name, arg): (String, Option<String>)| {
+						events.borrow_mut().push(EditorEvent::Custom { name, arg: arg.unwrap_or_default() });
+						Ok(())
+				})?,
+    )?;
+
     let events = lua_events.clone();
     lua.globals().set(
 				"message",
@@ -124,6 +201,21 @@ pub fn load_lua(
 				})?,
     )?;
 
+		// log.info("config loaded")      -- also log.debug, log.warn, log.error
+		// Goes to *Messages* (and the log file), not to the echo area.
+		let log = lua.create_table()?;
+		for level in [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error] {
+				let shared = editor.borrow().log.clone();
+				log.set(
+						level.as_str().to_lowercase(),
+						lua.create_function(move |_, msg: String| {
+								shared.borrow_mut().log(level, &msg);
+								Ok(())
+						})?,
+				)?;
+		}
+		lua.globals().set("log", log)?;
+
     lua.globals().set(
 				"set_buffer_borders",
 				lua.create_function(move |_, enabled: bool| {
@@ -132,6 +224,70 @@ pub fn load_lua(
 				})?,
     )?;
 
+		// set_line_numbers("absolute" | "relative" | "hybrid" | "off")
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_line_numbers",
+				lua.create_function(move |_, style: String| {
+						let ed = ed.borrow();
+						let mut config = ed.user_config.borrow_mut();
+						if style == "off" {
+								config.line_numbers = false;
+								return Ok(());
+						}
+						config.line_number_style = LineNumberStyle::parse(&style).ok_or_else(|| mlua::Error::RuntimeError(
+								format!("set_line_numbers: expected absolute, relative, hybrid or off, got {style}")
+						))?;
+						config.line_numbers = true;
+						Ok(())
+				})?,
+		)?;
+
+		// set_tab_width(8) / set_indent_tabs_mode(true) change the defaults;
+		// set_buffer_tab_width(2) / set_buffer_indent_tabs_mode(false) only
+		// the current buffer (for example from a major mode hook).
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_tab_width",
+				lua.create_function(move |_, width: usize| {
+						if width == 0 {
+								return Err(mlua::Error::RuntimeError("set_tab_width: width must be positive".into()));
+						}
+						ed.borrow().user_config.borrow_mut().tab_width = width;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_indent_tabs_mode",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow().user_config.borrow_mut().indent_tabs_mode = on;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_buffer_tab_width",
+				lua.create_function(move |_, width: usize| {
+						if width == 0 {
+								return Err(mlua::Error::RuntimeError("set_buffer_tab_width: width must be positive".into()));
+						}
+						ed.borrow_mut().buffer.tab_width = Some(width);
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_buffer_indent_tabs_mode",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow_mut().buffer.indent_tabs_mode = Some(on);
+						Ok(())
+				})?,
+		)?;
+
 		lua.globals().set(
 				"set_isearch_highlight",
 				lua.create_function(move |_, enabled: bool| {
@@ -140,6 +296,370 @@ pub fn load_lua(
 				})?,
 		)?;
 
+		lua.globals().set(
+				"set_compile_command",
+				lua.create_function(move |_, cmd: String| {
+						compile_config.borrow_mut().compile_command = cmd;
+						Ok(())
+				})?,
+		)?;
+
+		lua.globals().set(
+				"set_grep_program",
+				lua.create_function(move |_, name: String| {
+						let program = GrepProgram::parse(&name).ok_or_else(|| {
+								mlua::Error::RuntimeError(format!("set_grep_program: expected auto, builtin or rg, got {name}"))
+						})?;
+						grep_config.borrow_mut().grep_program = program;
+						Ok(())
+				})?,
+		)?;
+
+		lua.globals().set(
+				"set_grep_context",
+				lua.create_function(move |_, lines: usize| {
+						grep_context_config.borrow_mut().grep_context = lines;
+						Ok(())
+				})?,
+		)?;
+
+		// set_bookmark_file("~/.local/share/remux/bookmarks.json")
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_bookmark_file",
+				lua.create_function(move |_, path: String| {
+						let mut ed = ed.borrow_mut();
+						let path = ed.buffer.expand_tilde(path);
+						ed.user_config.borrow_mut().bookmark_file = path;
+						Ok(())
+				})?,
+		)?;
+
+		// set_desktop_save_mode(true)     -- save the session on exit, restore it on start
+		// set_save_place(true)           -- reopen files where the cursor was left
+		// set_desktop_file("~/.local/share/remux/desktop.json")
+		// set_save_place_file("~/.local/share/remux/places.json")
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_desktop_save_mode",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow().user_config.borrow_mut().desktop_save_mode = on;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_save_place",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow().user_config.borrow_mut().save_place = on;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_desktop_file",
+				lua.create_function(move |_, path: String| {
+						let mut ed = ed.borrow_mut();
+						let path = ed.buffer.expand_tilde(path);
+						ed.user_config.borrow_mut().desktop_file = path;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_save_place_file",
+				lua.create_function(move |_, path: String| {
+						let mut ed = ed.borrow_mut();
+						let path = ed.buffer.expand_tilde(path);
+						ed.user_config.borrow_mut().places_file = path;
+						Ok(())
+				})?,
+		)?;
+
+		// set_log_file("~/.local/state/remux/remux.log")   -- nil stops writing one
+		// set_log_level("warn")                             -- least level written to the log file
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_log_file",
+				lua.create_function(move |_, path: Option<String>| {
+						let mut ed = ed.borrow_mut();
+						let path = path.map(|p| ed.buffer.expand_tilde(p));
+						ed.log.borrow_mut().file = path;
+						Ok(())
+				})?,
+		)?;
+
+		let log = editor.borrow().log.clone();
+		lua.globals().set(
+				"set_log_level",
+				lua.create_function(move |_, name: String| {
+						let level = LogLevel::parse(&name).ok_or_else(|| mlua::Error::RuntimeError(
+								format!("set_log_level: expected debug, info, warn or error, got {name}")
+						))?;
+						log.borrow_mut().level = level;
+						Ok(())
+				})?,
+		)?;
+
+		// set_project_markers({ ".git", "Cargo.toml", "go.mod" })
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_project_markers",
+				lua.create_function(move |_, markers: Vec<String>| {
+						let ed = ed.borrow();
+						ed.user_config.borrow_mut().project_markers = markers;
+						ed.project_roots.borrow_mut().clear();
+						Ok(())
+				})?,
+		)?;
+
+		// set_lsp_server("rust", { "rust-analyzer" })
+		// set_lsp_server("python", { "pylsp", "--check-parent-process" })
+		// set_lsp_server("rust", { script = "/tmp/fake-server.json" })
+		// set_lsp_server("rust", nil)       -- no server for rust
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_lsp_server",
+				lua.create_function(move |_, (language, spec): (String, Option<mlua::Table>)| {
+						let server = match spec {
+								None => None,
+								Some(t) => match t.get::<_, Option<String>>("script")? {
+										Some(script) => Some(LspServer::Scripted(script.into())),
+										None => {
+												let mut argv: Vec<String> = t.sequence_values().collect::<Result<_>>()?;
+												if argv.is_empty() {
+														return Err(mlua::Error::RuntimeError(
+																"set_lsp_server: expected { command, args... } or { script = path }".into(),
+														));
+												}
+												let command = argv.remove(0);
+												Some(LspServer::Stdio { command, args: argv })
+										}
+								},
+						};
+						let mut ed = ed.borrow_mut();
+						let mut config = ed.user_config.borrow_mut();
+						match server {
+								Some(server) => config.lsp_servers.insert(language.clone(), server),
+								None => config.lsp_servers.remove(&language),
+						};
+						drop(config);
+						ed.lsp_failed.retain(|(lang, _)| *lang != language);
+						Ok(())
+				})?,
+		)?;
+
+		// set_indent_rule("nix", { style = "block", open = { "{", "[", "(", "let" },
+		//                         close = { "}", "]", ")", "in" }, comment = "#", offset = 2 })
+		// set_indent_rule("toml", { style = "copy" })
+		// set_indent_rule("python", nil)    -- back to copying the previous line
+		// Fields: style ("block" | "offside" | "copy"), open, close, middle,
+		// dedent_after, comment, offset, char_literals (`'` quotes one char,
+		// as in Rust, rather than strings).
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_indent_rule",
+				lua.create_function(move |_, (mode, spec): (String, Option<mlua::Table>)| {
+						let ed = ed.borrow();
+						let mut config = ed.user_config.borrow_mut();
+						let Some(t) = spec else {
+								config.indent_rules.remove(&mode);
+								return Ok(());
+						};
+						let style: String = t.get::<_, Option<String>>("style")?.unwrap_or_else(|| "block".to_string());
+						let style = IndentStyle::parse(&style).ok_or_else(|| mlua::Error::RuntimeError(
+								format!("set_indent_rule: expected style block, offside or copy, got {style}")
+						))?;
+						let list = |key: &str| -> Result<Vec<String>> {
+								Ok(t.get::<_, Option<Vec<String>>>(key)?.unwrap_or_default())
+						};
+						let rule = IndentRule {
+								style,
+								open: list("open")?,
+								close: list("close")?,
+								middle: list("middle")?,
+								dedent_after: list("dedent_after")?,
+								comment: t.get("comment")?,
+								offset: t.get("offset")?,
+								char_literals: t.get::<_, Option<bool>>("char_literals")?.unwrap_or(false),
+						};
+						config.indent_rules.insert(mode, rule);
+						Ok(())
+				})?,
+		)?;
+
+		// set_electric_indent(false)        -- RET no longer indents, closers stay put
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_electric_indent",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow().user_config.borrow_mut().electric_indent = on;
+						Ok(())
+				})?,
+		)?;
+
+		// set_electric_pair(true)          -- electric-pair-mode
+		// set_show_paren(false)            -- show-paren-mode
+		// set_pairs({ "()", "[]", "{}", "\"\"", "''" })
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_electric_pair",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow().user_config.borrow_mut().electric_pair = on;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_show_paren",
+				lua.create_function(move |_, on: bool| {
+						ed.borrow().user_config.borrow_mut().show_paren = on;
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_pairs",
+				lua.create_function(move |_, specs: Vec<String>| {
+						let pairs = specs.iter()
+								.map(|spec| {
+										let mut chars = spec.chars();
+										match (chars.next(), chars.next(), chars.next()) {
+												(Some(open), Some(close), None) => Ok((open, close)),
+												_ => Err(mlua::Error::RuntimeError(
+														format!("set_pairs: expected two characters, got {spec:?}")
+												)),
+										}
+								})
+								.collect::<Result<Vec<_>>>()?;
+						ed.borrow().user_config.borrow_mut().pairs = pairs;
+						Ok(())
+				})?,
+		)?;
+
+		// define_macro("comment-line", { "C-a", "-", "-", "SPC", "<down>" })
+		// define_macro("comment-line", { ... }, "Comment out the line and move down")  -- with a doc
+		// bind("mod2", "m", "comment-line")
+		// `insert-kbd-macro` writes this for a recorded macro.
+		let ed = editor.clone();
+		lua.globals().set(
+				"define_macro",
+				lua.create_function(move |_, (name, keys, doc): (String, Vec<String>, Option<String>)| {
+						let keys = keys.iter()
+								.map(|k| kmacro::parse_key(k).ok_or_else(|| mlua::Error::RuntimeError(
+										format!("define_macro: unknown key {k:?}")
+								)))
+								.collect::<Result<Vec<_>>>()?;
+						let mut ed = ed.borrow_mut();
+						match doc {
+								Some(doc) => ed.command_docs.insert(name.clone(), doc),
+								None => ed.command_docs.remove(&name),
+						};
+						ed.named_macros.insert(name, keys);
+						Ok(())
+				})?,
+		)?;
+
+		// set_register("a", "some text")
+		// set_register("n", 42)
+		// set_register("p", { line = 10, col = 1, buffer = "init.lua" })  -- buffer defaults to the current one
+		// set_register("a", nil)
+		// get_register("a") -> { type = "text", text = ... } | { type = "number", value = ... }
+		//                    | { type = "position", buffer = ..., file = ..., line = ..., col = ... } | nil
+		// Lines and columns are 1-based.
+		let ed = editor.clone();
+		lua.globals().set(
+				"get_register",
+				lua.create_function(move |lua, name: String| {
+						let Some(c) = name.chars().next() else { return Ok(None) };
+						let Some(reg) = ed.borrow().register(c) else { return Ok(None) };
+						let t = lua.create_table()?;
+						match reg {
+								Register::Text(text) => {
+										t.set("type", "text")?;
+										t.set("text", text)?;
+								}
+								Register::Number(n) => {
+										t.set("type", "number")?;
+										t.set("value", n)?;
+								}
+								Register::Position { buffer, file, x, y } => {
+										t.set("type", "position")?;
+										t.set("buffer", buffer)?;
+										t.set("file", file.map(|p| p.display().to_string()))?;
+										t.set("line", y + 1)?;
+										t.set("col", x + 1)?;
+								}
+						}
+						Ok(Some(t))
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_register",
+				lua.create_function(move |_, (name, value): (String, mlua::Value)| {
+						let c = name.chars().next().ok_or_else(|| mlua::Error::RuntimeError(
+								"set_register: empty register name".to_string()
+						))?;
+						let mut ed = ed.borrow_mut();
+						let reg = match value {
+								mlua::Value::Nil => {
+										ed.clear_register(c);
+										return Ok(());
+								}
+								mlua::Value::String(s) => Register::Text(s.to_str()?.to_string()),
+								mlua::Value::Integer(n) => Register::Number(n),
+								mlua::Value::Number(n) => Register::Number(n as i64),
+								mlua::Value::Table(t) => {
+										let buffer = t.get::<_, Option<String>>("buffer")?.unwrap_or_else(|| ed.buffer.file_name());
+										let file = ed.find_buffer(&buffer).and_then(|b| b.absolute_path());
+										Register::Position {
+												buffer,
+												file,
+												x: t.get::<_, Option<usize>>("col")?.unwrap_or(1).max(1) - 1,
+												y: t.get::<_, usize>("line")?.max(1) - 1,
+										}
+								}
+								other => return Err(mlua::Error::RuntimeError(
+										format!("set_register: expected a string, number or position table, got {}", other.type_name())
+								)),
+						};
+						ed.set_register(c, reg);
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"project_root",
+				lua.create_function(move |_, ()| {
+						Ok(ed.borrow().project_root().map(|p| p.display().to_string()))
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"major_mode",
+				lua.create_function(move |_, ()| {
+						Ok(ed.borrow().buffer.major_mode.clone())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_major_mode",
+				lua.create_function(move |_, mode: String| {
+						ed.borrow_mut().buffer.major_mode = mode;
+						Ok(())
+				})?,
+		)?;
+
 		let ed = editor.clone();
 		lua.globals().set(
 				"buffer_modified",
@@ -184,25 +704,232 @@ pub fn load_lua(
 		)?;
 
 
-		let ed = editor.clone();
+		let events = lua_events.clone();
 		lua.globals().set(
 				"minibuffer_prompt",
-				lua.create_function(move |lua, (prompt, cmd): (String, String)| {
+				lua.create_function(move |_, (prompt, command): (String, String)| {
+						events.borrow_mut().push(EditorEvent::MiniBufferPrompt { prompt, command });
+						Ok(())
+				})?,
+		)?;
+
+		// ---- Timers ----
+		let timers = editor.borrow().timers.clone();
+		lua.globals().set(
+				"set_timeout",
+				lua.create_function(move |lua, (ms, func): (u64, mlua::Function)| {
+						let key = lua.create_registry_value(func)?;
+						let id = timers.borrow_mut().set_timeout(Duration::from_millis(ms), key);
+						Ok(TimerHandle { id, timers: timers.clone() })
+				})?,
+		)?;
+
+		let timers = editor.borrow().timers.clone();
+		lua.globals().set(
+				"set_interval",
+				lua.create_function(move |lua, (ms, func): (u64, mlua::Function)| {
+						let key = lua.create_registry_value(func)?;
+						let id = timers.borrow_mut().set_interval(Duration::from_millis(ms), key);
+						Ok(TimerHandle { id, timers: timers.clone() })
+				})?,
+		)?;
+
+		let timers = editor.borrow().timers.clone();
+		lua.globals().set(
+				"run_when_idle",
+				lua.create_function(move |lua, (secs, func): (f64, mlua::Function)| {
+						let key = lua.create_registry_value(func)?;
+						let id = timers.borrow_mut().run_when_idle(Duration::from_secs_f64(secs.max(0.0)), key);
+						Ok(TimerHandle { id, timers: timers.clone() })
+				})?,
+		)?;
+
+		let timers = editor.borrow().timers.clone();
+		lua.globals().set(
+				"cancel_timer",
+				lua.create_function(move |_, handle: mlua::AnyUserData| {
+						let id = handle.borrow::<TimerHandle>()?.id;
+						Ok(timers.borrow_mut().cancel(id))
+				})?,
+		)?;
+
+		// ---- Mode line ----
+		// set_mode_line{
+		//   left  = { "undo", "buffer-id", { "project", fg = "cyan" }, "diagnostics" },
+		//   right = { "git-branch", { text = "|" }, "major-mode", "position" },
+		// }
+		// A segment is a name, or a table holding a name (or `text = "..."`)
+		// with `fg`, `bg`, `bold`, `italic`, `underline`.
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_mode_line",
+				lua.create_function(move |_, format: mlua::Table| {
+						let parse_side = |key: &str| -> Result<Vec<Segment>> {
+								let items: Option<Vec<mlua::Value>> = format.get(key)?;
+								items.unwrap_or_default().into_iter().map(parse_segment).collect()
+						};
+						let mode_line = ModeLineFormat { left: parse_side("left")?, right: parse_side("right")? };
+						ed.borrow().user_config.borrow_mut().mode_line = mode_line;
+						Ok(())
+				})?,
+		)?;
+
+		// define_mode_line_segment("clock", function() return os.date("%H:%M") end)
+		let segments = editor.borrow().mode_line_segments.clone();
+		lua.globals().set(
+				"define_mode_line_segment",
+				lua.create_function(move |lua, (name, func): (String, mlua::Function)| {
+						if BUILTIN_SEGMENTS.contains(&name.as_str()) {
+								return Err(mlua::Error::RuntimeError(
+										format!("define_mode_line_segment: {name} is a built-in segment")
+								));
+						}
+						segments.borrow_mut().insert(name, lua.create_registry_value(func)?);
+						Ok(())
+				})?,
+		)?;
+
+		// ---- Diagnostics ----
+		// set_diagnostics("luacheck", {
+		//   { line = 3, col = 5, severity = "warning", message = "unused variable", source = "luacheck" },
+		// }, "/path/to/file.lua")     -- the path defaults to the current buffer
+		// Lines and columns are 1-based; end_line / end_col (exclusive) are optional.
+		let ed = editor.clone();
+		lua.globals().set(
+				"set_diagnostics",
+				lua.create_function(move |_, (provider, items, path): (String, Vec<mlua::Table>, Option<String>)| {
+						let mut list = Vec::new();
+						for item in items {
+								let line = item.get::<_, usize>("line")?.max(1) - 1;
+								let col = item.get::<_, Option<usize>>("col")?.unwrap_or(1).max(1) - 1;
+								let end_line = item.get::<_, Option<usize>>("end_line")?.map_or(line, |l| l.max(1) - 1);
+								let end_col = item.get::<_, Option<usize>>("end_col")?.map_or(col + 1, |c| c.max(1) - 1);
+								let severity = match item.get::<_, Option<String>>("severity")? {
+										None => Severity::Error,
+										Some(name) => Severity::parse(&name).ok_or_else(|| mlua::Error::RuntimeError(
+												format!("set_diagnostics: expected error, warning, info or hint, got {name}")
+										))?,
+								};
+								list.push(Diagnostic {
+										line,
+										col,
+										end_line,
+										end_col,
+										severity,
+										message: item.get::<_, Option<String>>("message")?.unwrap_or_default(),
+										source: item.get("source")?,
+								});
+						}
 						let mut ed = ed.borrow_mut();
+						let target = match path {
+								Some(p) => {
+										let p = ed.buffer.expand_tilde(p);
+										std::fs::canonicalize(&p).unwrap_or(p)
+								}
+								None => ed.diagnostic_target(),
+						};
+						ed.diagnostics.set(target, &provider, list);
+						Ok(())
+				})?,
+		)?;
+
+		let ed = editor.clone();
+		lua.globals().set(
+				"clear_diagnostics",
+				lua.create_function(move |_, provider: String| {
+						ed.borrow_mut().diagnostics.clear_provider(&provider);
+						Ok(())
+				})?,
+		)?;
+
+		// diagnostics() -> { { line, col, end_line, end_col, severity, message, source }, ... }
+		let ed = editor.clone();
+		lua.globals().set(
+				"diagnostics",
+				lua.create_function(move |lua, ()| {
+						let ed = ed.borrow();
+						let out = lua.create_table()?;
+						for (i, d) in ed.buffer_diagnostics().iter().enumerate() {
+								let t = lua.create_table()?;
+								t.set("line", d.line + 1)?;
+								t.set("col", d.col + 1)?;
+								t.set("end_line", d.end_line + 1)?;
+								t.set("end_col", d.end_col + 1)?;
+								t.set("severity", d.severity.name())?;
+								t.set("message", d.message.clone())?;
+								t.set("source", d.source.clone())?;
+								out.set(i + 1, t)?;
+						}
+						Ok(out)
+				})?,
+		)?;
+
+		// ---- External processes ----
+		// spawn_process{ cmd = "ls", args = {"-l"}, buffer = "*ls*", cwd = "/tmp",
+		//                input = "...", on_output = fn(text, stream, id), on_exit = fn(code, id) }
+		let procs = editor.borrow().processes.clone();
+		lua.globals().set(
+				"spawn_process",
+				lua.create_function(move |lua, opts: mlua::Table| {
+						let program: String = opts.get("cmd")?;
+						let args: Option<Vec<String>> = opts.get("args")?;
+						let sink = match opts.get::<_, Option<String>>("buffer")? {
+								Some(name) => ProcessSink::Buffer(name),
+								None => ProcessSink::Discard,
+						};
+						let mut spec = ProcessSpec::new(&program, args.unwrap_or_default(), sink);
+						spec.cwd = opts.get::<_, Option<String>>("cwd")?.map(Into::into);
+						spec.input = opts.get("input")?;
+						if let Some(f) = opts.get::<_, Option<mlua::Function>>("on_output")? {
+								spec.on_output = Some(lua.create_registry_value(f)?);
+						}
+						if let Some(f) = opts.get::<_, Option<mlua::Function>>("on_exit")? {
+								spec.on_exit = Some(lua.create_registry_value(f)?);
+						}
+						procs.borrow_mut().spawn(spec).map_err(mlua::Error::external)
+				})?,
+		)?;
 
-						ed.set_mode(lua, InputMode::MiniBuffer);
-						ed.minibuffer.activate(&prompt, MiniBufferMode::Command);
-						ed.minibuffer_action = MiniBufferAction::Command(cmd);
+		let procs = editor.borrow().processes.clone();
+		lua.globals().set(
+				"process_send",
+				lua.create_function(move |_, (id, text): (u64, String)| {
+						procs.borrow_mut().send_input(id, &text).map_err(mlua::Error::external)
+				})?,
+		)?;
 
+		let procs = editor.borrow().processes.clone();
+		lua.globals().set(
+				"process_close_stdin",
+				lua.create_function(move |_, id: u64| {
+						procs.borrow_mut().close_stdin(id);
 						Ok(())
 				})?,
 		)?;
 
-    let path = config_path();
+		let procs = editor.borrow().processes.clone();
+		lua.globals().set(
+				"process_kill",
+				lua.create_function(move |_, id: u64| {
+						Ok(procs.borrow_mut().kill(id).is_ok())
+				})?,
+		)?;
+
+		let procs = editor.borrow().processes.clone();
+		lua.globals().set(
+				"process_running",
+				lua.create_function(move |_, id: u64| {
+						Ok(procs.borrow().is_running(id))
+				})?,
+		)?;
+
+    let Some(path) = init else { return Ok(()) };
     if path.exists() {
-				lua.load(std::fs::read_to_string(&path)?).exec()?;
-    } else {
+				lua.load(std::fs::read_to_string(path)?).set_name(path.display().to_string()).exec()?;
+    } else if path == config_path() {
 				println!("Configuration file not found! Ctrl-c to exit [copy 'init.lua' to '~/.config/remux/init.lua']");
+    } else {
+				return Err(mlua::Error::RuntimeError(format!("{}: no such file", path.display())));
     }
 
 		Ok(())
@@ -0,0 +1,366 @@
+// core/src/bookmarks.rs
+//
+// Bookmarks: named spots in files, kept in `UserConfig::bookmark_file` (a
+// JSON list under the data directory) so they outlive the session. The file
+// is read before and written after every change, so several editors share
+// one set of bookmarks.
+//
+// Besides the line and column, a bookmark remembers a little of the text
+// before and after the spot. When the file has changed since, jumping
+// searches for that text, nearest to the old line first, and goes there
+// instead.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use crate::buffer::Buffer;
+use crate::editor::editor::{Editor, KeyMap, Modifiers};
+
+pub const BOOKMARK_LIST_BUFFER: &str = "*Bookmark List*";
+pub const BOOKMARK_LIST_MODE: &str = "bookmark-list";
+
+/// Chars of context kept on each side of the spot.
+const CONTEXT_LEN: usize = 32;
+/// The title line and the blank line after it.
+const HEADER_LINES: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub file: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    /// Text just before and just after the spot, on its line.
+    pub rear_context: String,
+    pub front_context: String,
+}
+
+pub fn default_bookmark_file() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(std::env::temp_dir);
+    path.push("remux");
+    path.push("bookmarks.json");
+    path
+}
+
+/// Bookmarks stored in `path`, sorted by name. A missing file holds none.
+pub fn load_bookmarks(path: &Path) -> io::Result<Vec<Bookmark>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let str_of = |v: &Value, key: &str| v[key].as_str().unwrap_or("").to_string();
+    let mut marks: Vec<Bookmark> = value.as_array().into_iter().flatten()
+        .filter(|v| v["name"].is_string() && v["file"].is_string())
+        .map(|v| Bookmark {
+            name: str_of(v, "name"),
+            file: PathBuf::from(str_of(v, "file")),
+            line: v["line"].as_u64().unwrap_or(1).max(1) as usize - 1,
+            col: v["col"].as_u64().unwrap_or(1).max(1) as usize - 1,
+            rear_context: str_of(v, "rear_context"),
+            front_context: str_of(v, "front_context"),
+        })
+        .collect();
+    marks.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(marks)
+}
+
+/// Write `marks` to `path`, creating its directory. Lines and columns are
+/// stored 1-based.
+pub fn save_bookmarks(path: &Path, marks: &[Bookmark]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let list: Vec<Value> = marks.iter()
+        .map(|b| json!({
+            "name": b.name,
+            "file": b.file.display().to_string(),
+            "line": b.line + 1,
+            "col": b.col + 1,
+            "rear_context": b.rear_context,
+            "front_context": b.front_context,
+        }))
+        .collect();
+    let text = serde_json::to_string_pretty(&list)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, text + "\n")
+}
+
+/// The text around char `x` of `text`: up to `CONTEXT_LEN` chars on each
+/// side.
+pub fn context_at(text: &str, x: usize) -> (String, String) {
+    let chars: Vec<char> = text.chars().collect();
+    let x = x.min(chars.len());
+    let rear = chars[x.saturating_sub(CONTEXT_LEN)..x].iter().collect();
+    let front = chars[x..(x + CONTEXT_LEN).min(chars.len())].iter().collect();
+    (rear, front)
+}
+
+/// Where `mark` is in `buffer` now, as `(x, y)`, and whether it had to be
+/// looked for. The stored spot wins while its context still matches;
+/// otherwise the closest line holding the context (both sides, else the
+/// text after the spot, else the text before it). With no context left,
+/// the stored spot, kept inside the buffer.
+pub fn relocate(buffer: &Buffer, mark: &Bookmark) -> ((usize, usize), bool) {
+    let (rear, front) = (&mark.rear_context, &mark.front_context);
+    if let Some(line) = buffer.lines.get(mark.line) {
+        let (r, f) = context_at(&line.text, mark.col);
+        if mark.col <= line.char_len && r.ends_with(rear.as_str()) && f.starts_with(front.as_str()) {
+            return ((mark.col, mark.line), false);
+        }
+    }
+    let rear_len = rear.chars().count();
+    let candidates = [
+        (format!("{rear}{front}"), rear_len),
+        (front.clone(), 0),
+        (rear.clone(), rear_len),
+    ];
+    for (needle, offset) in candidates {
+        if needle.trim().is_empty() {
+            continue;
+        }
+        let found = buffer.lines.iter().enumerate()
+            .filter_map(|(y, l)| l.text.find(&needle).map(|b| (l.text[..b].chars().count() + offset, y)))
+            .min_by_key(|&(_, y)| y.abs_diff(mark.line));
+        if let Some(pos) = found {
+            return (pos, true);
+        }
+    }
+    let y = mark.line.min(buffer.lines.len() - 1);
+    ((mark.col.min(buffer.lines[y].char_len), y), false)
+}
+
+pub fn bookmark_list_keymap() -> KeyMap {
+    let mut map = KeyMap::new();
+    for (key, cmd) in [
+        ('\n', "bookmark-list-jump"),
+        ('f', "bookmark-list-jump"),
+        ('d', "bookmark-list-delete"),
+        ('g', "list-bookmarks"),
+        ('n', "move-down"),
+        ('p', "move-up"),
+        ('q', "kill-buffer"),
+    ] {
+        map.bind(Modifiers::none(), key, cmd.to_string());
+    }
+    map
+}
+
+// ---- Editor integration ----
+impl Editor {
+    fn bookmark_file(&self) -> PathBuf {
+        self.user_config.borrow().bookmark_file.clone()
+    }
+
+    /// The stored bookmarks, or `None` after reporting why they could not
+    /// be read.
+    fn read_bookmarks(&mut self) -> Option<Vec<Bookmark>> {
+        let path = self.bookmark_file();
+        match load_bookmarks(&path) {
+            Ok(marks) => Some(marks),
+            Err(e) => {
+                self.minibuffer.message(&format!("Cannot read {}: {e}", path.display()));
+                None
+            }
+        }
+    }
+
+    fn write_bookmarks(&mut self, marks: &[Bookmark]) -> bool {
+        let path = self.bookmark_file();
+        if let Err(e) = save_bookmarks(&path, marks) {
+            self.minibuffer.message(&format!("Cannot write {}: {e}", path.display()));
+            return false;
+        }
+        // Keep an open list in step.
+        if self.find_buffer(BOOKMARK_LIST_BUFFER).is_some() {
+            self.render_bookmark_list(marks);
+        }
+        true
+    }
+
+    pub fn bookmark_names(&mut self) -> Vec<String> {
+        self.read_bookmarks().unwrap_or_default().into_iter().map(|b| b.name).collect()
+    }
+
+    /// `bookmark-set`: bookmark the cursor under `name` (the file name when
+    /// empty), replacing any bookmark of that name.
+    pub fn bookmark_set(&mut self, name: &str) {
+        let Some(file) = self.buffer.absolute_path() else {
+            self.minibuffer.message("Buffer is not visiting a file");
+            return;
+        };
+        let name = match name.trim() {
+            "" => self.buffer.file_name(),
+            name => name.to_string(),
+        };
+        let Some(mut marks) = self.read_bookmarks() else { return };
+        let (x, y) = (self.buffer.cursor_x, self.buffer.cursor_y);
+        let (rear_context, front_context) = context_at(&self.buffer.lines[y].text, x);
+        marks.retain(|b| b.name != name);
+        marks.push(Bookmark { name: name.clone(), file, line: y, col: x, rear_context, front_context });
+        marks.sort_by(|a, b| a.name.cmp(&b.name));
+        if self.write_bookmarks(&marks) {
+            self.minibuffer.message(&format!("Bookmark set: {name}"));
+        }
+    }
+
+    /// `bookmark-jump`: visit the bookmark's file and go to the spot,
+    /// relocating it when the text moved.
+    pub fn bookmark_jump(&mut self, name: &str) {
+        let Some(marks) = self.read_bookmarks() else { return };
+        let Some(mark) = marks.into_iter().find(|b| b.name == name) else {
+            self.minibuffer.message(&format!("No bookmark named {name}"));
+            return;
+        };
+        if let Err(e) = self.visit_file(mark.file.clone()) {
+            self.minibuffer.message(&format!("Cannot open {}: {e}", mark.file.display()));
+            return;
+        }
+        let ((x, y), moved) = relocate(&self.buffer, &mark);
+        self.buffer.clear_mark();
+        (self.buffer.cursor_x, self.buffer.cursor_y) = (x, y);
+        self.ensure_cursor_visible();
+        if moved {
+            self.minibuffer.message(&format!("Bookmark {name} relocated to line {}", y + 1));
+        }
+    }
+
+    /// `bookmark-delete`.
+    pub fn bookmark_delete(&mut self, name: &str) {
+        let Some(mut marks) = self.read_bookmarks() else { return };
+        let before = marks.len();
+        marks.retain(|b| b.name != name);
+        if marks.len() == before {
+            self.minibuffer.message(&format!("No bookmark named {name}"));
+            return;
+        }
+        if self.write_bookmarks(&marks) {
+            self.minibuffer.message(&format!("Deleted bookmark {name}"));
+        }
+    }
+
+    fn render_bookmark_list(&mut self, marks: &[Bookmark]) {
+        let width = marks.iter().map(|b| b.name.chars().count()).max().unwrap_or(0);
+        let mut text = format!("Bookmarks in {}\n\n", self.bookmark_file().display());
+        for b in marks {
+            text.push_str(&format!("{:<width$}  {}:{}:{}\n", b.name, b.file.display(), b.line + 1, b.col + 1));
+        }
+        let buf = self.buffer_mut_or_create(BOOKMARK_LIST_BUFFER);
+        let y = buf.cursor_y;
+        buf.set_text_raw(text.trim_end_matches('\n'));
+        buf.cursor_y = y.clamp(HEADER_LINES.min(buf.lines.len() - 1), buf.lines.len() - 1);
+        buf.major_mode = BOOKMARK_LIST_MODE.to_string();
+        buf.read_only = true;
+    }
+
+    /// `list-bookmarks`: show the bookmarks in `*Bookmark List*`.
+    pub fn list_bookmarks(&mut self) {
+        let Some(marks) = self.read_bookmarks() else { return };
+        self.render_bookmark_list(&marks);
+        self.switch_to_buffer(BOOKMARK_LIST_BUFFER);
+        if marks.is_empty() {
+            self.minibuffer.message("No bookmarks");
+        }
+    }
+
+    /// The bookmark on the cursor's line of `*Bookmark List*`.
+    fn bookmark_at_cursor(&mut self) -> Option<String> {
+        let i = self.buffer.cursor_y.checked_sub(HEADER_LINES);
+        let name = i.and_then(|i| self.read_bookmarks()?.into_iter().nth(i)).map(|b| b.name);
+        if name.is_none() {
+            self.minibuffer.message("No bookmark on this line");
+        }
+        name
+    }
+
+    /// `bookmark-list-jump`.
+    pub fn bookmark_list_jump(&mut self) {
+        if let Some(name) = self.bookmark_at_cursor() {
+            self.bookmark_jump(&name);
+        }
+    }
+
+    /// `bookmark-list-delete`, after confirmation.
+    pub fn bookmark_list_delete(&mut self) {
+        if let Some(name) = self.bookmark_at_cursor() {
+            self.confirm(&format!("Delete bookmark {name}? "), move |ed| ed.bookmark_delete(&name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> Buffer {
+        let mut buf = Buffer::new();
+        buf.set_text_raw(text);
+        buf
+    }
+
+    /// A bookmark at `(x, y)` of `text`, with its context from there.
+    fn mark_in(text: &str, x: usize, y: usize) -> Bookmark {
+        let (rear_context, front_context) = context_at(text.lines().nth(y).unwrap(), x);
+        Bookmark {
+            name: "m".to_string(),
+            file: PathBuf::from("/f"),
+            line: y,
+            col: x,
+            rear_context,
+            front_context,
+        }
+    }
+
+    #[test]
+    fn context_is_cut_to_length() {
+        let line = "x".repeat(40) + "|" + &"y".repeat(40);
+        let (rear, front) = context_at(&line, 40);
+        assert_eq!((rear.len(), front.len()), (CONTEXT_LEN, CONTEXT_LEN));
+        assert!(front.starts_with('|'));
+        assert_eq!(context_at("äbc", 9), ("äbc".to_string(), String::new()));
+    }
+
+    #[test]
+    fn unchanged_spot_stays() {
+        let text = "fn main() {\n    run();\n}";
+        let mark = mark_in(text, 4, 1);
+        assert_eq!(relocate(&buffer(text), &mark), ((4, 1), false));
+    }
+
+    #[test]
+    fn moved_text_is_followed() {
+        let mark = mark_in("fn main() {\n    run();\n}", 8, 1);
+        let buf = buffer("// header\n\nfn main() {\n    let x = 1;\n    run();\n}");
+        assert_eq!(relocate(&buf, &mark), ((8, 4), true));
+        // Chars, not bytes, before the spot.
+        let buf = buffer("fn main() {\n    é run();\n}");
+        assert_eq!(relocate(&buf, &mark), ((10, 1), true));
+    }
+
+    #[test]
+    fn nearest_copy_wins() {
+        let mark = mark_in("a\nb\nc\nhere\nd\ne\nf\ng", 2, 3);
+        let buf = buffer("here\nb\nc\nx\nd\nhere\nf\ng");
+        assert_eq!(relocate(&buf, &mark), ((2, 5), true));
+    }
+
+    #[test]
+    fn one_side_of_the_context_is_enough() {
+        let mark = mark_in("let total = compute(items);", 12, 0);
+        // The text after the spot is still there.
+        let buf = buffer("\nlet sum = compute(items);");
+        assert_eq!(relocate(&buf, &mark), ((10, 1), true));
+        // Only the text before it.
+        let buf = buffer("\nlet total = 0;");
+        assert_eq!(relocate(&buf, &mark), ((12, 1), true));
+    }
+
+    #[test]
+    fn lost_context_keeps_the_spot_inside_the_buffer() {
+        let mark = mark_in("one\ntwo\nthree four five", 10, 2);
+        assert_eq!(relocate(&buffer("xyz\nabc"), &mark), ((3, 1), false));
+    }
+}
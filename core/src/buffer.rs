@@ -1,8 +1,10 @@
+use std::collections::HashMap;
 use std::io;
 use std::path::{Path, PathBuf};
 use mlua::Lua;
 use crate::editor::hooks::HookRegistry;
-use crate::editor::layout::LineWrapMode;
+use crate::editor::layout::{wrap_starts, LineWrapMode, DEFAULT_TAB_WIDTH};
+use crate::cursors::Cursor;
 
 #[derive(Debug, Clone)]
 pub enum UndoAction {
@@ -10,6 +12,65 @@ pub enum UndoAction {
     Delete { x: usize, y: usize, text: String },
     InsertNewline { x: usize, y: usize },
     JoinLine { x: usize, y: usize },
+    /// Undone together, last first: an edit made at several cursors.
+    Group(Vec<UndoAction>),
+}
+
+/// An edit in char coordinates: chars `start..end` (as `(x, y)`) replaced
+/// by `text`.
+#[derive(Debug, Clone)]
+pub struct CharEdit {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub text: String,
+}
+
+impl CharEdit {
+    /// Where `(x, y)` ends up after this edit. Positions inside the
+    /// replaced range move to its start.
+    pub fn shift(&self, pos: (usize, usize)) -> (usize, usize) {
+        let (x, y) = pos;
+        if (y, x) < (self.start.1, self.start.0) {
+            return pos;
+        }
+        if (y, x) < (self.end.1, self.end.0) {
+            return self.start;
+        }
+        let added_lines = self.text.matches('\n').count();
+        let last_len = self.text.rsplit('\n').next().unwrap_or("").chars().count();
+        let new_y = y - (self.end.1 - self.start.1) + added_lines;
+        if y != self.end.1 {
+            return (x, new_y);
+        }
+        let base = if added_lines == 0 { self.start.0 + last_len } else { last_len };
+        (base + x - self.end.0, new_y)
+    }
+}
+
+/// Where `text`, inserted at `start`, ends.
+fn end_of_insert(start: (usize, usize), text: &str) -> (usize, usize) {
+    CharEdit { start, end: start, text: text.to_string() }.shift(start)
+}
+
+/// One edit as a language server sees it: the replaced range, in
+/// (line, UTF-16 column) of the text before the edit, and the new text.
+/// `range: None` replaces the whole document.
+#[derive(Debug, Clone)]
+pub struct TextChange {
+    pub range: Option<((usize, usize), (usize, usize))>,
+    pub text: String,
+}
+
+/// Char column of UTF-16 column `col` in `text`.
+pub fn utf16_to_char_col(text: &str, col: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.chars().enumerate() {
+        if units >= col {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.chars().count()
 }
 
 #[derive(Clone)]
@@ -19,6 +80,7 @@ pub struct VisualMetrics {
     pub dirty: bool,
     pub last_width: usize,
     pub last_wrap: LineWrapMode,
+    pub last_tab_width: usize,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -26,7 +88,7 @@ pub enum Motion {
     Left, Right, Up, Down, Bol, Eol, BufferStart, BufferEnd, WordLeft, WordRight,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -112,6 +174,7 @@ impl VisualMetrics {
             dirty: true,
             last_width: 0,
             last_wrap: LineWrapMode::Wrap,
+            last_tab_width: DEFAULT_TAB_WIDTH,
         }
     }
 }
@@ -121,8 +184,38 @@ pub struct Buffer {
     pub cursor_x: usize,
     pub cursor_y: usize,
     pub file_path: Option<PathBuf>,
+    /// Name of a buffer that is not visiting a file, e.g. `*compilation*`.
+    pub name: Option<String>,
+    /// Scroll position kept while the buffer is not displayed.
+    pub saved_scroll: (usize, usize),
+    /// Selects the mode keymap, e.g. `compilation`.
+    pub major_mode: String,
+    /// Set for listings and other generated buffers, and for files that
+    /// cannot be written.
+    pub read_only: bool,
+    /// `view-mode`, holding `read_only` from before it was turned on (see
+    /// `view_mode.rs`).
+    pub view_mode: Option<bool>,
+    /// Buffer-local `tab_width` / `indent_tabs_mode`; `None` uses the
+    /// user's default (see `Editor::tab_width`).
+    pub tab_width: Option<usize>,
+    pub indent_tabs_mode: Option<bool>,
+    /// Bumped on every edit while `track_changes` is set.
+    pub version: i32,
+    /// Record edits in `changes`, for document sync.
+    pub track_changes: bool,
+    changes: Vec<TextChange>,
+    /// Edits since `start_edit_log`, for keeping other cursors in place.
+    edit_log: Option<Vec<CharEdit>>,
     modified: bool,
     mark: Option<Position>,
+    /// Show the region as the rectangle between mark and cursor.
+    pub rectangle_mark: bool,
+    /// Cursors besides the main one (see `cursors.rs`).
+    pub cursors: Vec<Cursor>,
+    /// Named positions that follow the text around them as it is edited
+    /// (see `registers.rs`).
+    pub markers: HashMap<String, (usize, usize)>,
     undo_stack: Vec<UndoAction>,
     pub visual: VisualMetrics,
     pub lines: Vec<Line>,
@@ -134,14 +227,34 @@ impl Buffer {
             cursor_x: 0,
             cursor_y: 0,
             file_path: None,
+            name: None,
+            saved_scroll: (0, 0),
+            major_mode: "fundamental".to_string(),
+            read_only: false,
+            view_mode: None,
+            tab_width: None,
+            indent_tabs_mode: None,
+            version: 0,
+            track_changes: false,
+            changes: Vec::new(),
+            edit_log: None,
             modified: false,
             mark: None,
+            rectangle_mark: false,
+            cursors: Vec::new(),
+            markers: HashMap::new(),
             undo_stack: Vec::new(),
 						visual: VisualMetrics::new(),
 						lines: vec![Line::empty()],
         }
     }
 
+    pub fn named(name: &str) -> Self {
+        let mut buf = Self::new();
+        buf.name = Some(name.to_string());
+        buf
+    }
+
     fn char_to_byte_idx(s: &str, char_idx: usize) -> usize {
 				if char_idx == 0 {
             return 0;
@@ -152,66 +265,213 @@ impl Buffer {
             .unwrap_or_else(|| s.len())
     }
 
+    /// UTF-16 column of char column `x` on line `y`.
+    pub fn utf16_col(&self, x: usize, y: usize) -> usize {
+        self.lines.get(y).map_or(0, |l| l.text.chars().take(x).map(char::len_utf16).sum())
+    }
+
+    /// Char column of UTF-16 column `col` on line `y`.
+    pub fn char_col(&self, col: usize, y: usize) -> usize {
+        self.lines.get(y).map_or(0, |l| utf16_to_char_col(&l.text, col))
+    }
+
+    /// Note that chars `start..end` (as `(x, y)`) are about to be replaced
+    /// by `text`. Must run before the lines change.
+    fn record_change(&mut self, start: (usize, usize), end: (usize, usize), text: &str) {
+        self.modified = true;
+        let edit = CharEdit { start, end, text: text.to_string() };
+        self.shift_markers(&edit);
+        if let Some(log) = &mut self.edit_log {
+            log.push(edit);
+        }
+        if !self.track_changes {
+            return;
+        }
+        let from = (start.1, self.utf16_col(start.0, start.1));
+        let to = (end.1, self.utf16_col(end.0, end.1));
+        self.changes.push(TextChange { range: Some((from, to)), text: text.to_string() });
+        self.version += 1;
+    }
+
+    fn shift_markers(&mut self, edit: &CharEdit) {
+        for pos in self.markers.values_mut() {
+            *pos = edit.shift(*pos);
+        }
+    }
+
+    /// Keep markers inside the text, after edits that bypass
+    /// `record_change`.
+    fn clamp_markers(&mut self) {
+        let last = self.lines.len() - 1;
+        for (x, y) in self.markers.values_mut() {
+            *y = (*y).min(last);
+            *x = (*x).min(self.lines[*y].char_len);
+        }
+    }
+
+    fn record_full_change(&mut self) {
+        if !self.track_changes {
+            return;
+        }
+        self.changes.clear();
+        self.changes.push(TextChange { range: None, text: String::new() });
+        self.version += 1;
+    }
+
+    /// Edits recorded since the last call.
+    pub fn take_changes(&mut self) -> Vec<TextChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// The whole contents, lines joined with `\n`.
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&line.text);
+        }
+        out
+    }
+
+    /// Start recording edits in char coordinates.
+    pub fn start_edit_log(&mut self) {
+        self.edit_log = Some(Vec::new());
+    }
+
+    /// Edits since `start_edit_log`, which stops recording.
+    pub fn take_edit_log(&mut self) -> Vec<CharEdit> {
+        self.edit_log.take().unwrap_or_default()
+    }
+
     fn push_undo(&mut self, action: UndoAction) {
         self.undo_stack.push(action);
         self.modified = true;
     }
 
+    pub fn undo_len(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Make the undo entries pushed since the stack was `from` long a
+    /// single step.
+    pub fn group_undo(&mut self, from: usize) {
+        if self.undo_stack.len() > from + 1 {
+            let group = self.undo_stack.split_off(from);
+            self.undo_stack.push(UndoAction::Group(group));
+        }
+    }
+
     pub fn undo(&mut self) {
         if let Some(action) = self.undo_stack.pop() {
-            match action {
-                UndoAction::Insert { x, y, text } => {
-                    let line = &mut self.lines[y];
-                    let start = Self::char_to_byte_idx(&line.text, x);
-                    let end = Self::char_to_byte_idx(&line.text, x + text.chars().count());
-                    line.text.replace_range(start..end, "");
-										let removed = text.chars().count();
-										line.char_len -= removed;
-                    self.cursor_x = x;
-                    self.cursor_y = y;
-										self.visual.dirty = true;
-                }
-                UndoAction::Delete { x, y, text } => {
-                    let line = &mut self.lines[y];
-                    let byte = Self::char_to_byte_idx(&line.text, x);
-                    line.text.insert_str(byte, &text);
-										let removed = text.chars().count();
-										line.char_len -= removed;
-                    self.cursor_x = x + text.chars().count();
-                    self.cursor_y = y;
-										self.visual.dirty = true;
-                }
-                UndoAction::InsertNewline { x, y } => {
-                    let next = self.lines.remove(y + 1);
-                    self.lines[y].text.push_str(&next.text);
-                    self.cursor_x = x;
-                    self.cursor_y = y;
+            self.record_full_change();
+            self.undo_action(action);
+            self.clamp_markers();
+        }
+    }
+
+    /// The edit undoing `action` makes, for moving markers.
+    fn undo_edit(action: &UndoAction) -> Option<CharEdit> {
+        let edit = |start, end, text: &str| CharEdit { start, end, text: text.to_string() };
+        Some(match action {
+            UndoAction::Insert { x, y, text } => edit((*x, *y), end_of_insert((*x, *y), text), ""),
+            UndoAction::Delete { x, y, text } => edit((*x, *y), (*x, *y), text),
+            UndoAction::InsertNewline { x, y } => edit((*x, *y), (0, y + 1), ""),
+            UndoAction::JoinLine { x, y } => edit((*x, *y), (*x, *y), "\n"),
+            UndoAction::Group(_) => return None,
+        })
+    }
+
+    fn undo_action(&mut self, action: UndoAction) {
+        if let Some(edit) = Self::undo_edit(&action) {
+            self.shift_markers(&edit);
+        }
+        match action {
+            UndoAction::Insert { x, y, text } => {
+                self.remove_raw((x, y), end_of_insert((x, y), &text));
+                self.cursor_x = x;
+                self.cursor_y = y;
+            }
+            UndoAction::Delete { x, y, text } => {
+                (self.cursor_x, self.cursor_y) = self.insert_raw((x, y), &text);
+            }
+            UndoAction::InsertNewline { x, y } => {
+                let next = self.lines.remove(y + 1);
+                self.lines[y].text.push_str(&next.text);
+                self.lines[y].char_len += next.char_len;
+                self.cursor_x = x;
+                self.cursor_y = y;
 										self.visual.dirty = true;
-                }
-                UndoAction::JoinLine { x, y } => {
-                    let tail = self.lines[y].text.split_off(x);
-                    self.lines.insert(y + 1, Line::new(tail));
-                    self.cursor_x = 0;
-                    self.cursor_y = y + 1;
+            }
+            UndoAction::JoinLine { x, y } => {
+                let byte = Self::char_to_byte_idx(&self.lines[y].text, x);
+                let tail = self.lines[y].text.split_off(byte);
+                self.lines[y].char_len = x;
+                self.lines.insert(y + 1, Line::new(tail));
+                self.cursor_x = 0;
+                self.cursor_y = y + 1;
 										self.visual.dirty = true;
+            }
+            UndoAction::Group(actions) => {
+                for action in actions.into_iter().rev() {
+                    self.undo_action(action);
                 }
             }
         }
     }
 
-    pub fn ensure_visuals( &mut self, width: usize, wrap: LineWrapMode) {
+    /// Remove chars `start..end` (as `(x, y)`), which may span lines,
+    /// leaving no undo entry.
+    fn remove_raw(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let tail = self.lines[end.1].split_off(end.0);
+        self.lines.drain(start.1 + 1..=end.1);
+        let line = &mut self.lines[start.1];
+        line.split_off(start.0);
+        line.text.push_str(&tail.text);
+        line.char_len += tail.char_len;
+        self.visual.dirty = true;
+    }
+
+    /// Insert `text`, which may span lines, at `start`, leaving no undo
+    /// entry. Returns where it ends.
+    fn insert_raw(&mut self, start: (usize, usize), text: &str) -> (usize, usize) {
+        let (x, y) = start;
+        let tail = self.lines[y].split_off(x);
+        let mut end = start;
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                end = (0, end.1 + 1);
+                self.lines.insert(end.1, Line::empty());
+            }
+            let line = &mut self.lines[end.1];
+            line.text.push_str(part);
+            line.char_len += part.chars().count();
+            end.0 = line.char_len;
+        }
+        let line = &mut self.lines[end.1];
+        line.text.push_str(&tail.text);
+        line.char_len += tail.char_len;
+        self.visual.dirty = true;
+        end
+    }
+
+    pub fn ensure_visuals(&mut self, width: usize, wrap: LineWrapMode, tab_width: usize) {
 				if self.visual.dirty
             || self.visual.last_width != width
             || self.visual.last_wrap != wrap
+            || self.visual.last_tab_width != tab_width
 				{
-            self.rebuild_visual_metrics(width, wrap);
+            self.rebuild_visual_metrics(width, wrap, tab_width);
             self.visual.dirty = false;
             self.visual.last_width = width;
             self.visual.last_wrap = wrap;
+            self.visual.last_tab_width = tab_width;
 				}
-    } 
+    }
 
     pub fn insert_text_at(&mut self, x: usize, y: usize, text: &str) {
+        self.record_change((x, y), (x, y), text);
         let line = &mut self.lines[y];
         let byte = Self::char_to_byte_idx(&line.text, x);
 				let added = text.chars().count();
@@ -223,6 +483,8 @@ impl Buffer {
     }
     
     pub fn insert_char_raw(&mut self, ch: char) {
+				let at = (self.cursor_x, self.cursor_y);
+				self.record_change(at, at, ch.encode_utf8(&mut [0; 4]));
 				let line = &mut self.lines[self.cursor_y];
 				let byte_idx = Self::char_to_byte_idx(&line.text, self.cursor_x);
 				self.cursor_x += 1;
@@ -251,6 +513,7 @@ impl Buffer {
     pub fn insert_newline_raw(&mut self) {
 				let x = self.cursor_x;
 				let y = self.cursor_y;
+				self.record_change((x, y), (x, y), "\n");
 				let rest = self.lines[y].split_off(x);
 				self.lines.insert(y + 1, rest);
 				self.cursor_y += 1;
@@ -261,6 +524,7 @@ impl Buffer {
 
     
     pub fn delete_range(&mut self, start_x: usize, start_y: usize, end_x: usize, end_y: usize) -> String {
+        self.record_change((start_x, start_y), (end_x, end_y), "");
         if start_y == end_y {
             let line = &mut self.lines[start_y];
             let a = Self::char_to_byte_idx(&line.text, start_x);
@@ -277,6 +541,7 @@ impl Buffer {
             let first_line = &mut self.lines[start_y];
             let a = Self::char_to_byte_idx(&first_line.text, start_x);
             deleted.push_str(&first_line.text[a..]);
+						first_line.text.truncate(a);
 						first_line.char_len = start_x;
             for _ in start_y + 1..end_y {
                 deleted.push('\n');
@@ -290,7 +555,9 @@ impl Buffer {
 
             let rest = last_line.text[b..].to_string();
 						self.lines[start_y].char_len += rest.chars().count();
+						self.lines[start_y].text.push_str(&rest);
             self.lines.remove(start_y + 1);
+						self.visual.dirty = true;
 
             self.push_undo(UndoAction::Delete { x: start_x, y: start_y, text: deleted.clone() });
             self.cursor_x = start_x;
@@ -336,9 +603,7 @@ impl Buffer {
 				Some(self.delete_range(prev_len, prev_y, 0, y))
     }
     
-    pub fn rebuild_visual_metrics(&mut self, width: usize, wrap: LineWrapMode) {
-				let w = width.max(1);
-
+    pub fn rebuild_visual_metrics(&mut self, width: usize, wrap: LineWrapMode, tab_width: usize) {
 				self.visual.prefix_sum.clear();
 				self.visual.prefix_sum.reserve(self.lines.len());
 
@@ -346,10 +611,7 @@ impl Buffer {
 				for line in &mut self.lines {
             let vh = match wrap {
 								LineWrapMode::Truncate => 1,
-								LineWrapMode::Wrap => {
-                    let len = line.char_len.max(1);
-                    (len + w - 1) / w
-								}
+								LineWrapMode::Wrap => wrap_starts(&line.text, width, tab_width).len(),
             };
 
             line.visual_height = vh;
@@ -408,10 +670,12 @@ impl Buffer {
     {
         let y = self.cursor_y;
         let x = self.cursor_x;
-        let line = self.lines.get_mut(y)?;
+        let line = self.lines.get(y)?;
         let chars: Vec<char> = line.text.chars().collect();
         let (start, end) = calc(&chars, x)?;
         if start == end { return None; }
+        self.record_change((start, y), (end, y), "");
+        let line = &mut self.lines[y];
 
         let killed: String = chars[start..end].iter().collect();
 
@@ -570,8 +834,10 @@ impl Buffer {
         }
     }
 
-    pub fn set_mark(&mut self) { self.mark = Some(Position { x: self.cursor_x, y: self.cursor_y }) }
-    pub fn clear_mark(&mut self) { self.mark = None }
+    pub fn set_mark(&mut self) { self.mark = Some(Position { x: self.cursor_x, y: self.cursor_y }); self.rectangle_mark = false }
+    pub fn clear_mark(&mut self) { self.mark = None; self.rectangle_mark = false }
+    pub fn mark(&self) -> Option<Position> { self.mark }
+    pub fn set_mark_at(&mut self, mark: Option<Position>) { self.mark = mark }
     pub fn toggle_mark(&mut self) { if self.mark.is_some() { self.clear_mark() } else { self.set_mark() } }
 
     pub fn selection(&self) -> Option<Selection> {
@@ -615,15 +881,73 @@ impl Buffer {
     pub fn yank(&mut self, text: &str) {
         let y = self.cursor_y;
         let x = self.cursor_x;
+        let depth = self.undo_stack.len();
         for (i, line) in text.split('\n').enumerate() {
             if i != 0 { self.insert_newline_raw() }
             for ch in line.chars() { self.insert_char_raw(ch) }
         }
+        // One entry for the whole text, not one per line break.
+        self.undo_stack.truncate(depth);
         self.push_undo(UndoAction::Insert { x, y, text: text.to_string() });
 				self.visual.dirty = true;
     }
 
 
+    /// Replace chars `start..end` (as `(x, y)`) with `text`, which may span
+    /// lines, as one undo step. The cursor ends up after the inserted text.
+    pub fn replace_range(&mut self, start: (usize, usize), end: (usize, usize), text: &str) {
+        let undo_from = self.undo_len();
+        if start != end {
+            self.delete_range(start.0, start.1, end.0, end.1);
+        }
+        self.cursor_x = start.0;
+        self.cursor_y = start.1;
+        if !text.is_empty() {
+            self.yank(text);
+        }
+        self.group_undo(undo_from);
+    }
+
+    /// Append text at the end of the buffer without touching the undo
+    /// history, e.g. output streamed from a process.
+    pub fn append_raw(&mut self, text: &str) {
+        let at_end = self.cursor_y + 1 == self.lines.len()
+            && self.cursor_x == self.lines[self.cursor_y].char_len;
+        if self.track_changes {
+            let y = self.lines.len() - 1;
+            let end = (self.lines[y].char_len, y);
+            let text = text.replace('\r', "");
+            self.record_change(end, end, &text);
+        }
+
+        for (i, part) in text.split('\n').enumerate() {
+            let part = part.strip_suffix('\r').unwrap_or(part);
+            if i > 0 {
+                self.lines.push(Line::empty());
+            }
+            let last = self.lines.last_mut().unwrap();
+            last.text.push_str(part);
+            last.char_len += part.chars().count();
+        }
+        self.visual.dirty = true;
+
+        if at_end {
+            self.cursor_y = self.lines.len() - 1;
+            self.cursor_x = self.lines[self.cursor_y].char_len;
+        }
+    }
+
+    /// Replace the whole contents without touching the undo history.
+    pub fn set_text_raw(&mut self, text: &str) {
+        self.record_full_change();
+        self.lines = text.split('\n').map(|s| Line::new(s.to_string())).collect();
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.clear_mark();
+        self.clamp_markers();
+        self.visual.dirty = true;
+    }
+
     pub fn expand_tilde<P: AsRef<Path>>(&mut self, path: P) -> PathBuf {
 				let path = path.as_ref();
 
@@ -654,7 +978,9 @@ impl Buffer {
 						return Ok(())
 				}
         let content = std::fs::read_to_string(&path)?;
+        self.record_full_change();
         self.lines = content.lines().map(|s| Line::new(s.to_string())).collect();
+        self.read_only = std::fs::metadata(&path).is_ok_and(|m| m.permissions().readonly());
         self.file_path = Some(path);
         self.cursor_x = 0; self.cursor_y = 0;
         Ok(())
@@ -678,13 +1004,115 @@ impl Buffer {
 				}
 				std::fs::write(&path, out)?;
         self.file_path = Some(path);
+        self.modified = false;
         Ok(())
     }
 
     pub fn is_modified(&self) -> bool { self.modified }
     pub fn file_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
         self.file_path.as_ref().and_then(|p| p.file_name().and_then(|s| s.to_str())).unwrap_or("[No Name]").to_string()
     }
+
+    /// Absolute path of the visited file, for matching it against paths
+    /// reported by other tools.
+    pub fn absolute_path(&self) -> Option<PathBuf> {
+        let path = self.file_path.as_ref()?;
+        Some(path.canonicalize().unwrap_or_else(|_| {
+            std::env::current_dir().map(|d| d.join(path)).unwrap_or_else(|_| path.clone())
+        }))
+    }
     
     pub fn undo_depth(&self) -> usize { self.undo_stack.len() }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_marker(text: &str, pos: (usize, usize)) -> Buffer {
+        let mut buf = Buffer::new();
+        buf.set_text_raw(text);
+        buf.markers.insert("a".to_string(), pos);
+        buf
+    }
+
+    #[test]
+    fn markers_follow_edits_and_their_undo() {
+        let mut buf = with_marker("hello world", (6, 0));
+        buf.insert_text_at(0, 0, "say ");
+        assert_eq!(buf.markers["a"], (10, 0));
+        buf.undo();
+        assert_eq!(buf.markers["a"], (6, 0));
+
+        buf.delete_range(0, 0, 6, 0);
+        assert_eq!(buf.markers["a"], (0, 0));
+        buf.undo();
+        assert_eq!(buf.markers["a"], (6, 0));
+    }
+
+    #[test]
+    fn markers_follow_newlines_and_their_undo() {
+        let mut buf = with_marker("hello world", (6, 0));
+        (buf.cursor_x, buf.cursor_y) = (5, 0);
+        buf.insert_newline_raw();
+        assert_eq!(buf.markers["a"], (1, 1));
+        buf.undo();
+        assert_eq!(buf.markers["a"], (6, 0));
+        assert_eq!(buf.text(), "hello world");
+
+        buf.delete_range(5, 0, 6, 0);
+        buf.undo();
+        assert_eq!(buf.markers["a"], (6, 0));
+    }
+
+    #[test]
+    fn markers_stay_inside_replaced_text() {
+        let mut buf = with_marker("one\ntwo\nthree", (4, 2));
+        buf.set_text_raw("x");
+        assert_eq!(buf.markers["a"], (1, 0));
+    }
+
+    #[test]
+    fn typing_modifies_and_saving_clears() {
+        let path = std::env::temp_dir().join(format!("remux-modified-{}.txt", std::process::id()));
+        let mut buf = Buffer::new();
+        assert!(!buf.is_modified());
+        buf.insert_char_raw('x');
+        assert!(buf.is_modified());
+        buf.save_as(path.clone()).unwrap();
+        assert!(!buf.is_modified());
+        buf.insert_newline_raw();
+        assert!(buf.is_modified());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn undo_removes_a_multi_line_insert() {
+        let mut buf = Buffer::new();
+        buf.set_text_raw("xy");
+        buf.cursor_x = 1;
+        buf.yank("a\nb");
+        assert_eq!(buf.text(), "xa\nby");
+        buf.undo();
+        assert_eq!(buf.text(), "xy");
+        assert_eq!(buf.lines.len(), 1);
+        assert_eq!(buf.lines[0].char_len, 2);
+        assert_eq!((buf.cursor_x, buf.cursor_y), (1, 0));
+    }
+
+    #[test]
+    fn undo_restores_a_multi_line_delete() {
+        let mut buf = Buffer::new();
+        buf.set_text_raw("one\ntwo\nthree");
+        assert_eq!(buf.delete_range(1, 0, 2, 2), "ne\ntwo\nth");
+        assert_eq!(buf.text(), "oree");
+        buf.undo();
+        assert_eq!(buf.text(), "one\ntwo\nthree");
+        assert_eq!(buf.lines.len(), 3);
+        assert_eq!(buf.lines[2].char_len, 5);
+        assert_eq!((buf.cursor_x, buf.cursor_y), (2, 2));
+    }
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use std::collections::HashMap;
 use crate::editor::editor::Editor;
 
+#[derive(Clone)]
 pub enum CommandArg {
     None,
     Int(i64),
@@ -20,15 +21,10 @@ pub struct CommandContext<'a> {
     
 }
 
-pub enum EditorEvent<'a> {
-    BeforeCommand(&'a str),
-    AfterCommand(&'a str),
-}
-
-
-
 pub struct Command {
     pub name: &'static str,
+    /// One line, shown by `describe-command` and `describe-key`.
+    pub doc: &'static str,
     pub interactive: Interactive,
     pub run: fn(CommandContext),
 }
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, Interactive};
+
+pub fn register_bookmarks_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Bookmarks
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "bookmark-set",
+	doc: "Bookmark the cursor under a name (default: the file name)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let ed = ctx.editor;
+	    let default = ed.buffer.file_name();
+	    ed.read_from_minibuffer("Set bookmark: ", &default, |ed, name| ed.bookmark_set(&name));
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "bookmark-jump",
+	doc: "Go to a bookmark, finding the spot again if the file changed",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let ed = ctx.editor;
+	    let names = ed.bookmark_names();
+	    ed.completing_read("Jump to bookmark: ", names, |ed, name| {
+		if !name.is_empty() {
+		    ed.bookmark_jump(&name);
+		}
+	    });
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "bookmark-delete",
+	doc: "Delete a bookmark",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let ed = ctx.editor;
+	    let names = ed.bookmark_names();
+	    ed.completing_read("Delete bookmark: ", names, |ed, name| {
+		if !name.is_empty() {
+		    ed.bookmark_delete(&name);
+		}
+	    });
+	},
+    }));
+
+    reg.register(Arc::new(Command { name: "list-bookmarks", doc: "Show the bookmarks in *Bookmark List*", interactive: Interactive::None, run: |ctx| { ctx.editor.list_bookmarks(); } }));
+    reg.register(Arc::new(Command { name: "bookmark-list-jump", doc: "Go to the bookmark on this line of *Bookmark List*", interactive: Interactive::None, run: |ctx| { ctx.editor.bookmark_list_jump(); } }));
+    reg.register(Arc::new(Command { name: "bookmark-list-delete", doc: "Delete the bookmark on this line of *Bookmark List*", interactive: Interactive::None, run: |ctx| { ctx.editor.bookmark_list_delete(); } }));
+}
@@ -3,7 +3,24 @@ use crate::command::{Command, CommandRegistry, CommandContext, CommandArg, Inter
 use crate::minibuffer::MiniBufferMode;
 use crate::editor::editor::{InputMode, PrefixState, ScrollIntent, ISearchDir};
 use crate::editor::layout::LineWrapMode;
+use crate::editor::events::EditorEvent;
 use crate::buffer::Motion;
+use crate::commands::shell::register_shell_commands;
+use crate::commands::compile::register_compile_commands;
+use crate::commands::grep::register_grep_commands;
+use crate::commands::dired::register_dired_commands;
+use crate::commands::project::register_project_commands;
+use crate::commands::lsp::register_lsp_commands;
+use crate::commands::diagnostics::register_diagnostics_commands;
+use crate::commands::pairs::register_pairs_commands;
+use crate::commands::rectangle::register_rectangle_commands;
+use crate::commands::cursors::register_cursors_commands;
+use crate::commands::kmacro::register_kmacro_commands;
+use crate::commands::registers::register_registers_commands;
+use crate::commands::bookmarks::register_bookmarks_commands;
+use crate::commands::desktop::register_desktop_commands;
+use crate::commands::view_mode::register_view_mode_commands;
+use crate::commands::help::register_help_commands;
 
 fn digit_argument(ctx: CommandContext, digit: i32) {
     let ed = ctx.editor;
@@ -51,6 +68,9 @@ where
     F: FnOnce(&mut crate::buffer::Buffer) -> Option<String>
 {
     let ed = ctx.editor;
+    if ed.barf_if_read_only() {
+        return;
+    }
 
     match f(&mut ed.buffer) {
         Some(text) => {
@@ -88,6 +108,7 @@ fn keyboard_quit(ctx: CommandContext) {
         return;
     }
 
+    ctx.editor.remove_extra_cursors();
     ctx.editor.buffer.clear_mark();
 }
 
@@ -95,51 +116,61 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
     // ===============================
     // Cursor movement commands
     // ===============================
-    reg.register(Arc::new(Command { name: "move-left", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Left) }));
-    reg.register(Arc::new(Command { name: "move-right", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Right) }));
-    reg.register(Arc::new(Command { name: "move-up", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Up) }));
-    reg.register(Arc::new(Command { name: "move-down", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Down) }));
-    reg.register(Arc::new(Command { name: "move-beginning-of-line", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Bol) }));
-    reg.register(Arc::new(Command { name: "move-end-of-line", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Eol) }));
-    reg.register(Arc::new(Command { name: "move-beginning-of-buffer", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::BufferStart) }));
-    reg.register(Arc::new(Command { name: "move-end-of-buffer", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::BufferEnd) }));
-    reg.register(Arc::new(Command { name: "move-word-left", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::WordLeft) }));
-    reg.register(Arc::new(Command { name: "move-word-right", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::WordRight) }));
+    reg.register(Arc::new(Command { name: "move-left", doc: "Move back a char (prefix: count)", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Left) }));
+    reg.register(Arc::new(Command { name: "move-right", doc: "Move forward a char (prefix: count)", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Right) }));
+    reg.register(Arc::new(Command { name: "move-up", doc: "Move up a line (prefix: count)", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Up) }));
+    reg.register(Arc::new(Command { name: "move-down", doc: "Move down a line (prefix: count)", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Down) }));
+    reg.register(Arc::new(Command { name: "move-beginning-of-line", doc: "Go to the start of the line", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Bol) }));
+    reg.register(Arc::new(Command { name: "move-end-of-line", doc: "Go to the end of the line", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::Eol) }));
+    reg.register(Arc::new(Command { name: "move-beginning-of-buffer", doc: "Go to the start of the buffer", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::BufferStart) }));
+    reg.register(Arc::new(Command { name: "move-end-of-buffer", doc: "Go to the end of the buffer", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::BufferEnd) }));
+    reg.register(Arc::new(Command { name: "move-word-left", doc: "Move back a word (prefix: count)", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::WordLeft) }));
+    reg.register(Arc::new(Command { name: "move-word-right", doc: "Move forward a word (prefix: count)", interactive: Interactive::None, run: |ctx| move_cursor_command(ctx, Motion::WordRight) }));
 
     // ===============================
     // Undo, quitting, basic editing
     // ===============================
-    reg.register(Arc::new(Command { name: "undo", interactive: Interactive::None, run: |ctx| { ctx.editor.buffer.undo(); } }));
-    reg.register(Arc::new(Command { name: "keyboard-quit", interactive: Interactive::None, run: keyboard_quit }));
-    reg.register(Arc::new(Command { name: "kill-remux", interactive: Interactive::None, run: |ctx| { ctx.editor.should_quit = true; } }));
-    reg.register(Arc::new(Command { name: "delete-char", interactive: Interactive::None, run: |ctx| { ctx.editor.buffer.delete(Motion::Right); ctx.editor.ensure_cursor_visible(); } }));
-    reg.register(Arc::new(Command { name: "backward-delete-char", interactive: Interactive::None, run: |ctx| { ctx.editor.buffer.delete(Motion::Left); ctx.editor.ensure_cursor_visible(); } }));
-    reg.register(Arc::new(Command { name: "set-mark-command", interactive: Interactive::None, run: |ctx| { ctx.editor.buffer.toggle_mark(); } }));
-    reg.register(Arc::new(Command { name: "newline", interactive: Interactive::None, run: |ctx| { ctx.editor.insert_newline(); ctx.editor.ensure_cursor_visible(); } }));
+    reg.register(Arc::new(Command { name: "undo", doc: "Undo the last change", interactive: Interactive::None, run: |ctx| { if !ctx.editor.barf_if_read_only() { ctx.editor.buffer.undo(); } } }));
+    reg.register(Arc::new(Command { name: "keyboard-quit", doc: "Cancel the prompt, search, region or extra cursors", interactive: Interactive::None, run: keyboard_quit }));
+    reg.register(Arc::new(Command { name: "kill-remux", doc: "Quit (Kill Remux)", interactive: Interactive::None, run: |ctx| { ctx.editor.should_quit = true; } }));
+    reg.register(Arc::new(Command { name: "delete-char", doc: "Delete the char after the cursor", interactive: Interactive::None, run: |ctx| { if !ctx.editor.barf_if_read_only() { ctx.editor.buffer.delete(Motion::Right); ctx.editor.ensure_cursor_visible(); } } }));
+    reg.register(Arc::new(Command { name: "backward-delete-char", doc: "Delete the char before the cursor, or an empty bracket pair around it", interactive: Interactive::None, run: |ctx| { ctx.editor.delete_backward_char(); } }));
+    reg.register(Arc::new(Command { name: "set-mark-command", doc: "Set the mark at the cursor, or clear it", interactive: Interactive::None, run: |ctx| { ctx.editor.buffer.toggle_mark(); } }));
+    reg.register(Arc::new(Command { name: "newline", doc: "Break the line at the cursor", interactive: Interactive::None, run: |ctx| { ctx.editor.insert_newline(); ctx.editor.ensure_cursor_visible(); } }));
+    reg.register(Arc::new(Command { name: "newline-and-indent", doc: "Break the line and indent the new one (RET)", interactive: Interactive::None, run: |ctx| { ctx.editor.newline_and_indent(); } }));
+    reg.register(Arc::new(Command { name: "indent-region", doc: "Reindent every line of the region", interactive: Interactive::None, run: |ctx| { ctx.editor.indent_region(); } }));
+    reg.register(Arc::new(Command { name: "indent-for-tab-command", doc: "Indent to the next tab stop (TAB)", interactive: Interactive::None, run: |ctx| {
+	let n = match ctx.arg { CommandArg::Int(n) if n > 0 => n as usize, _ => 1 };
+	ctx.editor.indent_for_tab(n);
+    }}));
 
     // ===============================
     // Killing/copying/yanking
     // ===============================
     reg.register(Arc::new(Command {
 	name: "kill-word",
+	doc: "Kill to the end of the word",
 	interactive: Interactive::None,
 	run: |ctx| run_kill(ctx, |b| b.kill_word()),
     }));
 
     reg.register(Arc::new(Command {
 	name: "kill-backward-word",
+	doc: "Kill back to the start of the word",
 	interactive: Interactive::None,
 	run: |ctx| run_kill(ctx, |b| b.kill_backward_word()),
     }));
 
     reg.register(Arc::new(Command {
 	name: "kill-sentence",
+	doc: "Kill to the end of the sentence",
 	interactive: Interactive::None,
 	run: |ctx| run_kill(ctx, |b| b.kill_sentence()),
     }));
 
     reg.register(Arc::new(Command {
 	name: "kill-region",
+	doc: "Kill the text between mark and cursor",
 	interactive: Interactive::None,
 	run: |ctx| run_kill(ctx, |b| b.kill_region()),
     }));
@@ -147,6 +178,7 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 
     reg.register(Arc::new(Command {
 	name: "kill-ring-save",
+	doc: "Remember the region's text without killing it",
 	interactive: Interactive::None,
 	run: |ctx| {
             if let Some(text) = ctx.editor.buffer.copy_region() {
@@ -162,7 +194,10 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 
 
 
-    reg.register(Arc::new(Command { name: "yank", interactive: Interactive::None, run: |ctx| {
+    reg.register(Arc::new(Command { name: "yank", doc: "Insert the last killed text", interactive: Interactive::None, run: |ctx| {
+	if ctx.editor.barf_if_read_only() {
+	    return;
+	}
 	if let Some(text) = ctx.editor.kill_buffer.clone() {
 	    ctx.editor.buffer.yank(&text);
 	    ctx.editor.minibuffer.message("Yanked");
@@ -177,10 +212,13 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
     // ===============================
     reg.register(Arc::new(Command {
 	name: "save-buffer",
+	doc: "Save file (buffer)",
 	interactive: Interactive::None,
 	run: |ctx| {
-	    if ctx.editor.buffer.file_path.is_some() {
+	    if let Some(path) = ctx.editor.buffer.file_path.clone() {
+		ctx.editor.emit(EditorEvent::BeforeBufferWrite { path: path.clone() });
 		if ctx.editor.buffer.save().is_ok() {
+		    ctx.editor.emit(EditorEvent::BufferSaved { path });
 		    ctx.editor.minibuffer.message("Buffer saved!");
 		} else {
 		    ctx.editor.minibuffer.message("Save failed");
@@ -195,6 +233,7 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 
     reg.register(Arc::new(Command {
 	name: "save-buffer-as",
+	doc: "Save file as <Enter> Name",
 	interactive: Interactive::Str { prompt: "Save buffer as: " },
 	run: |ctx| {
 	    ctx.editor.mode = InputMode::MiniBuffer;
@@ -205,6 +244,7 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 
     reg.register(Arc::new(Command {
 	name: "execute-command",
+	doc: "Read a command name, with completion, and run it (M-x)",
 	interactive: Interactive::Str { prompt: "M-x " },
 	run: |ctx| {
 	    // just activate minibuffer; real command name comes from user input
@@ -216,16 +256,33 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 
     reg.register(Arc::new(Command {
 	name: "find-file",
+	doc: "Open file",
 	interactive: Interactive::Str { prompt: "Find file: " },
 	run: |ctx| {
-	    if let CommandArg::Str(path) = ctx.arg {
-		let _ = ctx.editor.buffer.open_file(path.into());
+	    if let CommandArg::Str(path) = ctx.arg
+		&& let Err(e) = ctx.editor.visit_file(path.into()) {
+		ctx.editor.minibuffer.message(&format!("Open failed: {e}"));
 	    }
 	}
     }));
 
+    reg.register(Arc::new(Command {
+	name: "switch-to-buffer",
+	doc: "Switch to (or create) a buffer by name",
+	interactive: Interactive::Str { prompt: "Switch to buffer: " },
+	run: |ctx| {
+	    if let CommandArg::Str(name) = ctx.arg
+		&& !name.is_empty() {
+		ctx.editor.switch_to_buffer(&name);
+	    }
+	}
+    }));
+
+    reg.register(Arc::new(Command { name: "kill-buffer", doc: "Close the current buffer, asking first if it has unsaved changes", interactive: Interactive::None, run: |ctx| ctx.editor.kill_buffer() }));
+
     reg.register(Arc::new(Command {
 	name: "goto-line",
+	doc: "Go to a line, by number",
 	interactive: Interactive::Str { prompt: "Goto line: " },
 	run: |ctx| {
 	    ctx.editor.mode = InputMode::MiniBuffer;
@@ -238,6 +295,7 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
     // =============================
     reg.register(Arc::new(Command {
 	name: "isearch-forward",
+	doc: "Search forward as you type",
 	interactive: Interactive::None,
 	run: |ctx| {
             ctx.editor.isearch_start(ISearchDir::Forward);
@@ -246,6 +304,7 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 
     reg.register(Arc::new(Command {
 	name: "isearch-backward",
+	doc: "Search backward as you type",
 	interactive: Interactive::None,
 	run: |ctx| {
             ctx.editor.isearch_start(ISearchDir::Backward);
@@ -255,7 +314,7 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
     // ===============================
     // Toggle features
     // ===============================
-    reg.register(Arc::new(Command { name: "toggle-line-wrap", interactive: Interactive::None, run: |ctx| {
+    reg.register(Arc::new(Command { name: "toggle-line-wrap", doc: "Wrap long lines, or truncate them", interactive: Interactive::None, run: |ctx| {
 	ctx.editor.wrap_mode = match ctx.editor.wrap_mode {
 	    LineWrapMode::Wrap => LineWrapMode::Truncate,
 	    LineWrapMode::Truncate => LineWrapMode::Wrap,
@@ -264,62 +323,102 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
 	ctx.editor.scroll_x = 0;
 	ctx.editor.ensure_cursor_visible();
     }}));
+
+    reg.register(Arc::new(Command { name: "toggle-line-numbers", doc: "Show or hide line numbers in the gutter", interactive: Interactive::None, run: |ctx| {
+	let on = {
+	    let mut config = ctx.editor.user_config.borrow_mut();
+	    config.line_numbers = !config.line_numbers;
+	    config.line_numbers
+	};
+	ctx.editor.minibuffer.message(if on { "Line numbers on" } else { "Line numbers off" });
+	ctx.editor.ensure_cursor_visible();
+    }}));
+
+    // Both apply to the current buffer only; the defaults are set from Lua.
+    reg.register(Arc::new(Command { name: "set-tab-width", doc: "Set the tab width of the current buffer", interactive: Interactive::Str { prompt: "Tab width: " }, run: |ctx| {
+	let CommandArg::Str(s) = ctx.arg else { return };
+	match s.trim().parse::<usize>() {
+	    Ok(n) if n > 0 => {
+		ctx.editor.buffer.tab_width = Some(n);
+		ctx.editor.minibuffer.message(&format!("Tab width: {n}"));
+		ctx.editor.ensure_cursor_visible();
+	    }
+	    _ => ctx.editor.minibuffer.message("Tab width must be a positive number"),
+	}
+    }}));
+
+    reg.register(Arc::new(Command { name: "toggle-indent-tabs-mode", doc: "Indent the current buffer with tabs or spaces", interactive: Interactive::None, run: |ctx| {
+	let on = !ctx.editor.indent_tabs_mode();
+	ctx.editor.buffer.indent_tabs_mode = Some(on);
+	ctx.editor.minibuffer.message(if on { "Indenting with tabs" } else { "Indenting with spaces" });
+    }}));
     
     // ===============================
     // Digital arguments
     // ===============================
     reg.register(Arc::new(Command {
 	name: "digit-argument-1",
+	doc: "Add the digit 1 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 1),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-2",
+	doc: "Add the digit 2 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 2),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-3",
+	doc: "Add the digit 3 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 3),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-4",
+	doc: "Add the digit 4 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 4),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-5",
+	doc: "Add the digit 5 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 5),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-6",
+	doc: "Add the digit 6 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 6),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-7",
+	doc: "Add the digit 7 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 7),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-8",
+	doc: "Add the digit 8 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 8),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-9",
+	doc: "Add the digit 9 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 9),
     }));
     reg.register(Arc::new(Command {
 	name: "digit-argument-0",
+	doc: "Add the digit 0 to the prefix argument",
 	interactive: Interactive::None,
 	run: |ctx| digit_argument(ctx, 0),
     }));
     reg.register(Arc::new(Command {
 	name: "universal-argument",
+	doc: "Start a prefix argument: 4, times 4 for each repeat",
 	interactive: Interactive::None,
 	run: |ctx| universal_argument(ctx),
     }));
@@ -327,9 +426,26 @@ pub fn register_builtins(reg: &mut CommandRegistry) {
     // ===============================
     // Scrolling commands
     // ===============================
-    reg.register(Arc::new(Command { name: "scroll-up-command", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_up_command() }));
-    reg.register(Arc::new(Command { name: "scroll-down-command", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_down_command() }));
-    reg.register(Arc::new(Command { name: "scroll-left-command", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_left() }));
-    reg.register(Arc::new(Command { name: "scroll-right-command", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_right() }));
+    reg.register(Arc::new(Command { name: "scroll-up-command", doc: "Scroll back a screen", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_up_command() }));
+    reg.register(Arc::new(Command { name: "scroll-down-command", doc: "Scroll forward a screen", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_down_command() }));
+    reg.register(Arc::new(Command { name: "scroll-left-command", doc: "Scroll the view left", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_left() }));
+    reg.register(Arc::new(Command { name: "scroll-right-command", doc: "Scroll the view right", interactive: Interactive::None, run: |ctx| ctx.editor.scroll_right() }));
+
+    register_shell_commands(reg);
+    register_compile_commands(reg);
+    register_grep_commands(reg);
+    register_dired_commands(reg);
+    register_project_commands(reg);
+    register_lsp_commands(reg);
+    register_diagnostics_commands(reg);
+    register_pairs_commands(reg);
+    register_rectangle_commands(reg);
+    register_cursors_commands(reg);
+    register_kmacro_commands(reg);
+    register_registers_commands(reg);
+    register_bookmarks_commands(reg);
+    register_desktop_commands(reg);
+    register_view_mode_commands(reg);
+    register_help_commands(reg);
 
 }
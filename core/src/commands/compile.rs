@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandContext, CommandArg, Interactive};
+use crate::compile::COMPILATION_BUFFER;
+
+fn count(arg: &CommandArg) -> isize {
+    match arg {
+        CommandArg::Int(n) => *n as isize,
+        _ => 1,
+    }
+}
+
+fn compile(ctx: CommandContext) {
+    let ed = ctx.editor;
+    let command = match ctx.arg {
+        CommandArg::Str(s) if !s.trim().is_empty() => s.trim().to_string(),
+        _ => ed.user_config.borrow().compile_command.clone(),
+    };
+    let dir = ed.project_directory();
+    ed.compile(&command, dir);
+}
+
+fn recompile(ctx: CommandContext) {
+    let ed = ctx.editor;
+    let last = ed.locations.get(COMPILATION_BUFFER)
+        .map(|l| (l.command.clone(), l.dir.clone()));
+    match last {
+        Some((command, dir)) => ed.compile(&command, dir),
+        None => {
+            let command = ed.user_config.borrow().compile_command.clone();
+            let dir = ed.project_directory();
+            ed.compile(&command, dir);
+        }
+    }
+}
+
+pub fn register_compile_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Compilation
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "compile",
+	doc: "Run a build command into *compilation* (default: cargo build)",
+	interactive: Interactive::Str { prompt: "Compile command: " },
+	run: compile,
+    }));
+
+    reg.register(Arc::new(Command { name: "recompile", doc: "Run the last compile command again", interactive: Interactive::None, run: recompile }));
+
+    reg.register(Arc::new(Command {
+	name: "next-error",
+	doc: "Go to the next compilation error or grep match (prefix: count)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.next_error(n);
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "previous-error",
+	doc: "Go to the previous compilation error or grep match (prefix: count)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.next_error(-n);
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "compile-goto-error",
+	doc: "Visit the location on this line of a compilation or grep buffer",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.goto_location_at_cursor(),
+    }));
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandArg, Interactive};
+
+fn count(arg: &CommandArg) -> isize {
+    match arg {
+        CommandArg::Int(n) => *n as isize,
+        _ => 1,
+    }
+}
+
+pub fn register_cursors_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Multiple cursors
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "add-cursor-next-line",
+	doc: "Add a cursor here and move to the next line",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.add_cursor_line(n);
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "add-cursor-previous-line",
+	doc: "Add a cursor here and move to the previous line",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.add_cursor_line(-n);
+	},
+    }));
+
+    reg.register(Arc::new(Command { name: "add-cursor-next-match", doc: "Add a cursor here and select the next match of the region", interactive: Interactive::None, run: |ctx| { ctx.editor.add_cursor_next_match(); } }));
+
+    reg.register(Arc::new(Command {
+	name: "add-cursors-in-region",
+	doc: "Put a cursor on every match of a string in the region",
+	interactive: Interactive::Str { prompt: "Cursors at: " },
+	run: |ctx| {
+	    if let CommandArg::Str(needle) = ctx.arg {
+		ctx.editor.add_cursors_in_region(&needle);
+	    }
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "remove-extra-cursors",
+	doc: "Go back to a single cursor",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    if !ctx.editor.remove_extra_cursors() {
+		ctx.editor.minibuffer.message("No other cursors");
+	    }
+	},
+    }));
+}
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, Interactive};
+
+pub fn register_desktop_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Sessions
+    // ===============================
+    reg.register(Arc::new(Command { name: "desktop-save", doc: "Save the open files, positions, minibuffer histories and last kill", interactive: Interactive::None, run: |ctx| { ctx.editor.desktop_save(); } }));
+    reg.register(Arc::new(Command { name: "desktop-read", doc: "Restore the saved session", interactive: Interactive::None, run: |ctx| { ctx.editor.desktop_read(); } }));
+}
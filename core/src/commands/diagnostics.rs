@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandArg, Interactive};
+
+fn count(arg: &CommandArg) -> isize {
+    match arg {
+        CommandArg::Int(n) => *n as isize,
+        _ => 1,
+    }
+}
+
+pub fn register_diagnostics_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Diagnostics
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "next-diagnostic",
+	doc: "Go to the next diagnostic of the buffer (prefix: count)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.next_diagnostic(n);
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "previous-diagnostic",
+	doc: "Go to the previous diagnostic of the buffer (prefix: count)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.next_diagnostic(-n);
+	},
+    }));
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandContext, CommandArg, Interactive};
+
+fn dired(ctx: CommandContext) {
+    let ed = ctx.editor;
+    let dir = match ctx.arg {
+        CommandArg::Str(s) if !s.trim().is_empty() => s.trim().into(),
+        _ => ed.default_directory(),
+    };
+    if let Err(e) = ed.dired(dir) {
+        ed.minibuffer.message(&format!("Cannot read directory: {e}"));
+    }
+}
+
+pub fn register_dired_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Directory buffers
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "dired",
+	doc: "List a directory (find-file on a directory does the same)",
+	interactive: Interactive::Str { prompt: "Dired (directory): " },
+	run: dired,
+    }));
+
+    reg.register(Arc::new(Command { name: "dired-find-file", doc: "Visit the file or directory on this line", interactive: Interactive::None, run: |ctx| ctx.editor.dired_find_file() }));
+    reg.register(Arc::new(Command { name: "dired-up-directory", doc: "List the parent directory", interactive: Interactive::None, run: |ctx| ctx.editor.dired_up_directory() }));
+    reg.register(Arc::new(Command { name: "dired-refresh", doc: "Read the listed directory again", interactive: Interactive::None, run: |ctx| {
+	let name = ctx.editor.buffer.file_name();
+	ctx.editor.dired_refresh(&name);
+    } }));
+    reg.register(Arc::new(Command { name: "dired-mark", doc: "Mark the file on this line and move down", interactive: Interactive::None, run: |ctx| ctx.editor.dired_mark(true) }));
+    reg.register(Arc::new(Command { name: "dired-unmark", doc: "Unmark the file on this line and move down", interactive: Interactive::None, run: |ctx| ctx.editor.dired_mark(false) }));
+    reg.register(Arc::new(Command { name: "dired-unmark-all", doc: "Unmark every file", interactive: Interactive::None, run: |ctx| ctx.editor.dired_unmark_all() }));
+    reg.register(Arc::new(Command { name: "dired-do-delete", doc: "Delete the marked files, or the file on this line, after confirmation", interactive: Interactive::None, run: |ctx| ctx.editor.dired_do_delete() }));
+    reg.register(Arc::new(Command { name: "dired-do-rename", doc: "Rename or move the marked files, or the file on this line", interactive: Interactive::None, run: |ctx| ctx.editor.dired_do_rename() }));
+    reg.register(Arc::new(Command { name: "dired-do-copy", doc: "Copy the marked files, or the file on this line", interactive: Interactive::None, run: |ctx| ctx.editor.dired_do_copy() }));
+    reg.register(Arc::new(Command { name: "dired-create-directory", doc: "Create a directory in the listed one", interactive: Interactive::None, run: |ctx| ctx.editor.dired_create_directory() }));
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandContext, CommandArg, Interactive};
+
+fn pattern_arg(ctx: &mut CommandContext) -> Option<String> {
+    match &ctx.arg {
+        CommandArg::Str(s) if !s.is_empty() => Some(s.clone()),
+        _ => {
+            ctx.editor.minibuffer.message("Empty search pattern");
+            None
+        }
+    }
+}
+
+/// Search the current project, or the default directory outside one.
+fn grep(mut ctx: CommandContext) {
+    let Some(pattern) = pattern_arg(&mut ctx) else { return };
+    let dir = ctx.editor.project_directory();
+    ctx.editor.grep(&pattern, dir);
+}
+
+/// Ask for the directory as well.
+fn rgrep(mut ctx: CommandContext) {
+    let Some(pattern) = pattern_arg(&mut ctx) else { return };
+    let dir = ctx.editor.project_directory().display().to_string();
+    ctx.editor.read_from_minibuffer("In directory: ", &dir, move |ed, dir| {
+        let dir = if dir.trim().is_empty() { ed.project_directory() } else { ed.buffer.expand_tilde(dir.trim()) };
+        ed.grep(&pattern, dir);
+    });
+}
+
+pub fn register_grep_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Searching files
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "grep",
+	doc: "Search files of the current project, results in *grep*",
+	interactive: Interactive::Str { prompt: "Grep: " },
+	run: grep,
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "rgrep",
+	doc: "Like grep, but asks for the directory",
+	interactive: Interactive::Str { prompt: "Search for: " },
+	run: rgrep,
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "grep-cancel",
+	doc: "Stop a running grep",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    if ctx.editor.grep_search.take().is_some() {
+		ctx.editor.minibuffer.message("Grep cancelled");
+	    }
+	},
+    }));
+}
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandContext, Interactive};
+
+fn describe_command(ctx: CommandContext) {
+    let names = ctx.editor.command_names();
+    ctx.editor.completing_read("Describe command: ", names, |ed, name| ed.describe_command(&name));
+}
+
+fn where_is(ctx: CommandContext) {
+    let names = ctx.editor.command_names();
+    ctx.editor.completing_read("Where is command: ", names, |ed, name| ed.where_is(&name));
+}
+
+pub fn register_help_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Help
+    // ===============================
+    reg.register(Arc::new(Command { name: "describe-key", doc: "Show the command the next key runs, and its doc", interactive: Interactive::None, run: |ctx| { ctx.editor.describe_key_start(); } }));
+    reg.register(Arc::new(Command { name: "describe-command", doc: "Show a command's doc and the keys that run it", interactive: Interactive::None, run: describe_command }));
+    reg.register(Arc::new(Command { name: "where-is", doc: "Say which keys run a command", interactive: Interactive::None, run: where_is }));
+    reg.register(Arc::new(Command { name: "describe-bindings", doc: "List the key bindings in effect in the buffer", interactive: Interactive::None, run: |ctx| { ctx.editor.describe_bindings(); } }));
+    reg.register(Arc::new(Command { name: "view-echo-area-messages", doc: "Show *Messages*, the log of past messages", interactive: Interactive::None, run: |ctx| { ctx.editor.view_echo_area_messages(); } }));
+}
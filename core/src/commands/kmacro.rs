@@ -0,0 +1,47 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandArg, Interactive};
+
+pub fn register_kmacro_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Keyboard macros
+    // ===============================
+    reg.register(Arc::new(Command { name: "start-kbd-macro", doc: "Start recording a keyboard macro", interactive: Interactive::None, run: |ctx| { ctx.editor.start_kbd_macro(); } }));
+    reg.register(Arc::new(Command { name: "end-kbd-macro", doc: "Stop recording it", interactive: Interactive::None, run: |ctx| { ctx.editor.end_kbd_macro(); } }));
+
+    reg.register(Arc::new(Command {
+	name: "call-last-kbd-macro",
+	doc: "Replay the last keyboard macro (prefix: repeat count)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let count = match ctx.arg {
+		CommandArg::Int(n) => n.max(1) as usize,
+		_ => 1,
+	    };
+	    ctx.editor.call_last_kbd_macro(count);
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "name-last-kbd-macro",
+	doc: "Name the last macro, so it runs like a command",
+	interactive: Interactive::Str { prompt: "Name for last kbd macro: " },
+	run: |ctx| {
+	    if let CommandArg::Str(name) = ctx.arg {
+		ctx.editor.name_last_kbd_macro(&name);
+	    }
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "insert-kbd-macro",
+	doc: "Insert Lua that defines a macro",
+	interactive: Interactive::Str { prompt: "Insert kbd macro (name, empty for the last): " },
+	run: |ctx| {
+	    if let CommandArg::Str(name) = ctx.arg {
+		ctx.editor.insert_kbd_macro(&name);
+	    }
+	},
+    }));
+
+    reg.register(Arc::new(Command { name: "apply-macro-to-region-lines", doc: "Run the last macro on each line of the region", interactive: Interactive::None, run: |ctx| { ctx.editor.apply_macro_to_region_lines(); } }));
+}
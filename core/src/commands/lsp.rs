@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandContext, Interactive};
+
+/// Prompt for the new name, starting from the identifier at point.
+fn lsp_rename(ctx: CommandContext) {
+    let ed = ctx.editor;
+    let buf = &ed.buffer;
+    let chars: Vec<char> = buf.lines[buf.cursor_y].text.chars().collect();
+    let is_ident = |c: &char| c.is_alphanumeric() || *c == '_';
+    let x = buf.cursor_x.min(chars.len());
+    let start = chars[..x].iter().rposition(|c| !is_ident(c)).map_or(0, |i| i + 1);
+    let end = chars[x..].iter().position(|c| !is_ident(c)).map_or(chars.len(), |i| x + i);
+    let current: String = chars[start..end].iter().collect();
+
+    ed.read_from_minibuffer("Rename to: ", &current, |ed, name| {
+        let name = name.trim();
+        if name.is_empty() {
+            ed.minibuffer.message("Empty name");
+        } else {
+            ed.lsp_rename(name);
+        }
+    });
+}
+
+pub fn register_lsp_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Language servers
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "lsp-goto-definition",
+	doc: "Jump to the definition of the symbol at point (language server)",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.lsp_goto_definition(),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "lsp-find-references",
+	doc: "List references of the symbol at point in *references*",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.lsp_find_references(),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "lsp-hover",
+	doc: "Show the type / documentation of the symbol at point",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.lsp_hover(),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "lsp-rename",
+	doc: "Rename the symbol at point across the project",
+	interactive: Interactive::None,
+	run: lsp_rename,
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "lsp-complete",
+	doc: "Complete the symbol at point",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.lsp_complete(),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "lsp-restart",
+	doc: "Restart the language server of the current buffer",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.lsp_restart(),
+    }));
+}
@@ -1 +1,17 @@
 pub mod builtins;
+pub mod shell;
+pub mod compile;
+pub mod grep;
+pub mod dired;
+pub mod project;
+pub mod lsp;
+pub mod diagnostics;
+pub mod pairs;
+pub mod rectangle;
+pub mod cursors;
+pub mod kmacro;
+pub mod registers;
+pub mod bookmarks;
+pub mod desktop;
+pub mod view_mode;
+pub mod help;
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandArg, Interactive};
+
+fn count(arg: &CommandArg) -> isize {
+    match arg {
+        CommandArg::Int(n) => *n as isize,
+        _ => 1,
+    }
+}
+
+pub fn register_pairs_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Brackets and quotes
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "electric-pair-mode",
+	doc: "Toggle inserting closing brackets and quotes",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let on = {
+		let mut config = ctx.editor.user_config.borrow_mut();
+		config.electric_pair = !config.electric_pair;
+		config.electric_pair
+	    };
+	    ctx.editor.minibuffer.message(if on { "Electric-Pair mode enabled" } else { "Electric-Pair mode disabled" });
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "show-paren-mode",
+	doc: "Toggle highlighting the matching bracket",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let on = {
+		let mut config = ctx.editor.user_config.borrow_mut();
+		config.show_paren = !config.show_paren;
+		config.show_paren
+	    };
+	    ctx.editor.minibuffer.message(if on { "Show-Paren mode enabled" } else { "Show-Paren mode disabled" });
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "forward-sexp",
+	doc: "Move over the next balanced expression",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.forward_sexp(n);
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "backward-sexp",
+	doc: "Move over the previous balanced expression",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let n = count(&ctx.arg);
+	    ctx.editor.forward_sexp(-n);
+	},
+    }));
+}
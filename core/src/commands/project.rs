@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, Interactive};
+
+pub fn register_project_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Projects
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "project-find-file",
+	doc: "Open a file of the current project (fuzzy completion)",
+	interactive: Interactive::None,
+	run: |ctx| {
+	    let ed = ctx.editor;
+	    match ed.project_root() {
+		Some(root) => ed.project_find_file(root),
+		None => ed.minibuffer.message("Not in a project"),
+	    }
+	},
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "project-switch",
+	doc: "Pick a recent project and open a file in it",
+	interactive: Interactive::None,
+	run: |ctx| ctx.editor.project_switch(),
+    }));
+}
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandArg, Interactive};
+
+pub fn register_rectangle_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Rectangles
+    // ===============================
+    reg.register(Arc::new(Command { name: "rectangle-mark-mode", doc: "Show the region as the rectangle between mark and cursor", interactive: Interactive::None, run: |ctx| { ctx.editor.rectangle_mark_mode(); } }));
+    reg.register(Arc::new(Command { name: "kill-rectangle", doc: "Delete the rectangle and remember it", interactive: Interactive::None, run: |ctx| { ctx.editor.kill_rectangle(); } }));
+    reg.register(Arc::new(Command { name: "copy-rectangle", doc: "Remember the rectangle", interactive: Interactive::None, run: |ctx| { ctx.editor.copy_rectangle(); } }));
+    reg.register(Arc::new(Command { name: "yank-rectangle", doc: "Insert the last rectangle at the cursor", interactive: Interactive::None, run: |ctx| { ctx.editor.yank_rectangle(); } }));
+    reg.register(Arc::new(Command { name: "open-rectangle", doc: "Shift the rectangle's text right, leaving blanks", interactive: Interactive::None, run: |ctx| { ctx.editor.open_rectangle(); } }));
+    reg.register(Arc::new(Command { name: "clear-rectangle", doc: "Replace the rectangle with spaces", interactive: Interactive::None, run: |ctx| { ctx.editor.clear_rectangle(); } }));
+
+    reg.register(Arc::new(Command {
+	name: "string-rectangle",
+	doc: "Replace each line of the rectangle with a string",
+	interactive: Interactive::Str { prompt: "String rectangle: " },
+	run: |ctx| {
+	    if let CommandArg::Str(text) = ctx.arg {
+		ctx.editor.string_rectangle(&text);
+	    }
+	},
+    }));
+}
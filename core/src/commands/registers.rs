@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandContext, CommandRegistry, CommandArg, Interactive};
+use crate::editor::editor::Editor;
+use crate::registers::register_name;
+
+/// Run `f` on the register named by the minibuffer input.
+fn with_register(ctx: CommandContext, f: fn(&mut Editor, char)) {
+    let CommandArg::Str(input) = ctx.arg else { return };
+    match register_name(&input) {
+        Some(c) => f(ctx.editor, c),
+        None => ctx.editor.minibuffer.message("No register given"),
+    }
+}
+
+pub fn register_registers_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Registers
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "copy-to-register",
+	doc: "Save the region's text in a register",
+	interactive: Interactive::Str { prompt: "Copy to register: " },
+	run: |ctx| with_register(ctx, Editor::copy_to_register),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "insert-register",
+	doc: "Insert a text or number register at the cursor",
+	interactive: Interactive::Str { prompt: "Insert register: " },
+	run: |ctx| with_register(ctx, Editor::insert_register),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "point-to-register",
+	doc: "Save the cursor's position in a register",
+	interactive: Interactive::Str { prompt: "Point to register: " },
+	run: |ctx| with_register(ctx, Editor::point_to_register),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "jump-to-register",
+	doc: "Go to the position saved in a register",
+	interactive: Interactive::Str { prompt: "Jump to register: " },
+	run: |ctx| with_register(ctx, Editor::jump_to_register),
+    }));
+
+    // The prefix argument is the number to store (default 0) or to add
+    // (default 1).
+    reg.register(Arc::new(Command {
+	name: "number-to-register",
+	doc: "Store the prefix argument (default 0) in a register",
+	interactive: Interactive::Str { prompt: "Number to register: " },
+	run: |ctx| with_register(ctx, |ed, c| {
+	    let n = ed.prefix.consume().unwrap_or(0);
+	    ed.number_to_register(c, n);
+	}),
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "increment-register",
+	doc: "Add the prefix argument (default 1) to a number register, or append the region to a text one",
+	interactive: Interactive::Str { prompt: "Increment register: " },
+	run: |ctx| with_register(ctx, |ed, c| {
+	    let n = ed.prefix.consume().unwrap_or(1);
+	    ed.increment_register(c, n);
+	}),
+    }));
+
+    reg.register(Arc::new(Command { name: "list-registers", doc: "Show every register in *Registers*", interactive: Interactive::None, run: |ctx| { ctx.editor.list_registers(); } }));
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, CommandContext, CommandArg, Interactive};
+use crate::editor::editor::Editor;
+use crate::process::{ProcessSpec, ProcessSink};
+
+pub const ASYNC_SHELL_BUFFER: &str = "*Async Shell Command*";
+
+fn command_arg(ctx: &mut CommandContext) -> Option<String> {
+    match &ctx.arg {
+        CommandArg::Str(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => {
+            ctx.editor.minibuffer.message("No command given");
+            None
+        }
+    }
+}
+
+fn run_async(ed: &mut Editor, command: &str) {
+    let mut spec = ProcessSpec::shell(command, ProcessSink::Buffer(ASYNC_SHELL_BUFFER.to_string()));
+    spec.cwd = Some(ed.default_directory());
+    if ed.start_process(spec).is_some() {
+        ed.switch_to_buffer(ASYNC_SHELL_BUFFER);
+    }
+}
+
+fn shell_command(mut ctx: CommandContext) {
+    let Some(command) = command_arg(&mut ctx) else { return };
+    // Like Emacs, a trailing `&` runs the command asynchronously.
+    if let Some(background) = command.strip_suffix('&') {
+        run_async(ctx.editor, background.trim());
+        return;
+    }
+    let mut spec = ProcessSpec::shell(&command, ProcessSink::Echo);
+    spec.cwd = Some(ctx.editor.default_directory());
+    ctx.editor.start_process(spec);
+}
+
+fn async_shell_command(mut ctx: CommandContext) {
+    let Some(command) = command_arg(&mut ctx) else { return };
+    run_async(ctx.editor, &command);
+}
+
+fn shell_command_on_region(mut ctx: CommandContext) {
+    let Some(command) = command_arg(&mut ctx) else { return };
+    let ed = ctx.editor;
+    if ed.barf_if_read_only() {
+        return;
+    }
+    let (Some(sel), Some(text)) = (ed.buffer.selection(), ed.buffer.copy_region()) else {
+        ed.minibuffer.message("No active region");
+        return;
+    };
+    let mut spec = ProcessSpec::shell(&command, ProcessSink::ReplaceRegion {
+        buffer: ed.buffer.file_name(),
+        start: (sel.start.x, sel.start.y),
+        end: (sel.end.x, sel.end.y),
+        undo_depth: ed.buffer.undo_depth(),
+    });
+    spec.cwd = Some(ed.default_directory());
+    spec.input = Some(if text.ends_with('\n') { text } else { text + "\n" });
+    ed.start_process(spec);
+}
+
+pub fn register_shell_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // External processes
+    // ===============================
+    reg.register(Arc::new(Command {
+	name: "shell-command",
+	doc: "Run a shell command, show its output (\"cmd &\" runs it async)",
+	interactive: Interactive::Str { prompt: "Shell command: " },
+	run: shell_command,
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "async-shell-command",
+	doc: "Run a shell command, stream output into *Async Shell Command*",
+	interactive: Interactive::Str { prompt: "Async shell command: " },
+	run: async_shell_command,
+    }));
+
+    reg.register(Arc::new(Command {
+	name: "shell-command-on-region",
+	doc: "Pipe the region through a filter (sort, jq, ...) and replace it",
+	interactive: Interactive::Str { prompt: "Shell command on region: " },
+	run: shell_command_on_region,
+    }));
+}
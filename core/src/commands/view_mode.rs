@@ -0,0 +1,11 @@
+use std::sync::Arc;
+use crate::command::{Command, CommandRegistry, Interactive};
+
+pub fn register_view_mode_commands(reg: &mut CommandRegistry) {
+    // ===============================
+    // Read-only buffers and view mode
+    // ===============================
+    reg.register(Arc::new(Command { name: "toggle-read-only", doc: "Make the buffer read-only, or writable again", interactive: Interactive::None, run: |ctx| { ctx.editor.toggle_read_only(); } }));
+    reg.register(Arc::new(Command { name: "view-mode", doc: "Read the buffer: SPC/DEL page, < and > go to either end, q quits", interactive: Interactive::None, run: |ctx| { ctx.editor.toggle_view_mode(); } }));
+    reg.register(Arc::new(Command { name: "view-quit", doc: "Leave view mode and go back to the previous buffer", interactive: Interactive::None, run: |ctx| { ctx.editor.view_quit(); } }));
+}
@@ -0,0 +1,348 @@
+// core/src/compile.rs
+//
+// Compilation buffers: locations parsed from compiler / grep output and the
+// `next-error` machinery that jumps between them. A `LocationList` belongs to
+// the buffer the output was written to, so `*compilation*` and later result
+// buffers all navigate the same way.
+
+use std::path::{Path, PathBuf};
+use crate::buffer::Buffer;
+use crate::editor::editor::{Editor, KeyMap, Modifiers};
+use crate::process::{ProcessId, ProcessSpec, ProcessSink};
+
+pub const COMPILATION_BUFFER: &str = "*compilation*";
+pub const COMPILATION_MODE: &str = "compilation";
+
+/// A `file:line[:col]` reference found in a result buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    /// 1-based, as printed.
+    pub line: usize,
+    pub col: Option<usize>,
+    /// Span of the reference inside the result buffer, in chars.
+    pub y: usize,
+    pub x: usize,
+    pub len: usize,
+}
+
+pub struct LocationList {
+    /// Relative file names are resolved against this directory.
+    pub dir: PathBuf,
+    /// The command that produced the output, for `recompile`.
+    pub command: String,
+    pub items: Vec<Location>,
+    pub current: Option<usize>,
+    pub process: Option<ProcessId>,
+    /// Lines of the result buffer already scanned.
+    scanned: usize,
+}
+
+impl LocationList {
+    pub fn new(dir: PathBuf, command: &str) -> Self {
+        Self {
+            dir,
+            command: command.to_string(),
+            items: Vec::new(),
+            current: None,
+            process: None,
+            scanned: 0,
+        }
+    }
+
+    /// Parse the lines added to `buf` since the last scan. The last line is
+    /// only parsed once `complete` is set, since it may still be growing.
+    pub fn scan(&mut self, buf: &Buffer, complete: bool) {
+        let end = if complete { buf.lines.len() } else { buf.lines.len().saturating_sub(1) };
+        for y in self.scanned..end {
+            if let Some(mut loc) = parse_location(&buf.lines[y].text) {
+                loc.y = y;
+                self.items.push(loc);
+            }
+        }
+        self.scanned = self.scanned.max(end);
+    }
+
+    pub fn push(&mut self, loc: Location) {
+        self.items.push(loc);
+    }
+
+    /// Move `n` locations forward (or back when negative) from the current
+    /// one and return the new index.
+    pub fn step(&mut self, n: isize) -> Option<usize> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let next = match self.current {
+            None if n > 0 => n - 1,
+            None => return None,
+            Some(i) => i as isize + n,
+        };
+        if next < 0 || next as usize >= self.items.len() {
+            return None;
+        }
+        self.current = Some(next as usize);
+        self.current
+    }
+
+    /// Index of the location printed on line `y` of the result buffer.
+    pub fn at_line(&self, y: usize) -> Option<usize> {
+        self.items.iter().position(|l| l.y == y)
+    }
+
+    /// The output line describing `loc`. rustc prints the message on the
+    /// line above the ` --> ` arrow.
+    pub fn message(buf: &Buffer, loc: &Location) -> String {
+        let y = match buf.lines.get(loc.y) {
+            Some(l) if l.text.trim_start().starts_with("-->") => loc.y.saturating_sub(1),
+            _ => loc.y,
+        };
+        buf.lines.get(y).map(|l| l.text.trim().to_string()).unwrap_or_default()
+    }
+
+    pub fn resolve(&self, loc: &Location) -> PathBuf {
+        let path = Path::new(&loc.file);
+        if path.is_absolute() { path.to_path_buf() } else { self.dir.join(path) }
+    }
+}
+
+/// Find the first `file:line[:col]` reference in a line of output.
+///
+/// Understands rustc (`  --> src/main.rs:3:5`), gcc/clang
+/// (`main.c:3:5: error: ...`, `main.c:3: warning`) and grep
+/// (`src/lib.rs:42:text`). The file part must look like a path or a bare
+/// name such as `Makefile`, and the rest like a location, which keeps
+/// things like timestamps and `host:port` from matching.
+pub fn parse_location(text: &str) -> Option<Location> {
+    for (start, token) in tokens(text) {
+        let lead = token.len() - token.trim_start_matches(['(', '"', '\'']).len();
+        if let Some((file, line, col, len)) = parse_token(&token[lead..]) {
+            let x = text[..start + lead].chars().count();
+            return Some(Location {
+                file: file.to_string(),
+                line,
+                col,
+                y: 0,
+                x,
+                len,
+            });
+        }
+    }
+    None
+}
+
+/// The whitespace-separated tokens of `text`, with their byte offsets.
+/// Separators can be wider than a byte (U+3000, NBSP).
+fn tokens(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = None;
+    text.char_indices().chain(std::iter::once((text.len(), ' '))).filter_map(move |(i, c)| {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                start = None;
+                Some((s, &text[s..i]))
+            }
+            (false, None) => {
+                start = Some(i);
+                None
+            }
+            _ => None,
+        }
+    })
+}
+
+/// `file:line[:col]`, followed by `:` or the end of the token.
+fn parse_token(token: &str) -> Option<(&str, usize, Option<usize>, usize)> {
+    let colon = token.find(':')?;
+    let file = &token[..colon];
+    let bare = !(file.contains('.') || file.contains('/'));
+    if file.is_empty() || file.contains("//") || bare && !is_bare_file_name(file) {
+        return None;
+    }
+
+    let rest = &token[colon + 1..];
+    let (line, rest) = leading_number(rest)?;
+    if line == 0 {
+        return None;
+    }
+    let mut len = colon + 1 + digits_len(line);
+
+    let rest = match rest.strip_prefix(':') {
+        Some(r) => r,
+        None if rest.is_empty() => return Some((file, line, None, len)),
+        None => return None,
+    };
+    let col = match leading_number(rest) {
+        Some((c, after)) if after.is_empty() || after.starts_with(':') => {
+            len += 1 + digits_len(c);
+            Some(c)
+        }
+        _ => None,
+    };
+    Some((file, line, col, len))
+}
+
+/// A file name without a directory or extension, as in `Makefile:12:`.
+/// These are capitalized by convention, unlike host names.
+fn is_bare_file_name(file: &str) -> bool {
+    file.starts_with(|c: char| c.is_ascii_uppercase())
+        && file.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn leading_number(s: &str) -> Option<(usize, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    Some((s[..end].parse().ok()?, &s[end..]))
+}
+
+fn digits_len(n: usize) -> usize {
+    n.to_string().len()
+}
+
+/// Default bindings for compilation buffers.
+pub fn compilation_keymap() -> KeyMap {
+    let mut map = KeyMap::new();
+    map.bind(Modifiers::none(), '\n', "compile-goto-error".into());
+    map.bind(Modifiers::none(), 'n', "next-error".into());
+    map.bind(Modifiers::none(), 'p', "previous-error".into());
+    map.bind(Modifiers::none(), 'g', "recompile".into());
+    map
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Run `command` through the shell into `*compilation*`.
+    pub fn compile(&mut self, command: &str, dir: PathBuf) {
+        if let Some(old) = self.locations.get(COMPILATION_BUFFER).and_then(|l| l.process) {
+            self.processes.borrow_mut().discard(old);
+        }
+
+        let mut spec = ProcessSpec::shell(command, ProcessSink::Buffer(COMPILATION_BUFFER.to_string()));
+        spec.cwd = Some(dir.clone());
+        let Some(id) = self.start_process(spec) else { return };
+
+        let mut list = LocationList::new(dir, command);
+        list.process = Some(id);
+        self.locations.insert(COMPILATION_BUFFER.to_string(), list);
+        self.next_error_buffer = Some(COMPILATION_BUFFER.to_string());
+        self.user_config.borrow_mut().compile_command = command.to_string();
+
+        let buf = self.buffer_mut_or_create(COMPILATION_BUFFER);
+        buf.set_text_raw("");
+        buf.major_mode = COMPILATION_MODE.to_string();
+        self.switch_to_buffer(COMPILATION_BUFFER);
+    }
+
+    /// Pick up locations in output that just arrived in `name`.
+    pub fn scan_locations(&mut self, name: &str, complete: bool) {
+        let Some(mut list) = self.locations.remove(name) else { return };
+        if let Some(buf) = self.find_buffer_mut(name) {
+            list.scan(buf, complete);
+        }
+        if complete {
+            list.process = None;
+        }
+        self.locations.insert(name.to_string(), list);
+        if complete && name == COMPILATION_BUFFER {
+            self.publish_compile_diagnostics();
+        }
+    }
+
+    /// `next-error` / `previous-error`: step through the most recently used
+    /// location list.
+    pub fn next_error(&mut self, n: isize) {
+        // From inside a result buffer, navigate that one.
+        let name = match self.locations.contains_key(&self.buffer.file_name()) {
+            true => Some(self.buffer.file_name()),
+            false => self.next_error_buffer.clone(),
+        };
+        let Some(name) = name else {
+            self.minibuffer.message("No error locations");
+            return;
+        };
+        let index = self.locations.get_mut(&name).and_then(|l| l.step(n));
+        match index {
+            Some(i) => self.goto_location(&name, i),
+            None if n < 0 => self.minibuffer.message("Moved back before first error"),
+            None => self.minibuffer.message("No more errors"),
+        }
+    }
+
+    /// Jump to the location on the cursor line of a result buffer.
+    pub fn goto_location_at_cursor(&mut self) {
+        let name = self.buffer.file_name();
+        let index = self.locations.get(&name).and_then(|l| l.at_line(self.buffer.cursor_y));
+        match index {
+            Some(i) => self.goto_location(&name, i),
+            None => self.minibuffer.message("No error location on this line"),
+        }
+    }
+
+    pub fn goto_location(&mut self, list_name: &str, index: usize) {
+        let Some(list) = self.locations.get_mut(list_name) else { return };
+        let Some(loc) = list.items.get(index).cloned() else { return };
+        list.current = Some(index);
+        let path = list.resolve(&loc);
+
+        let mut text = String::new();
+        if let Some(buf) = self.find_buffer_mut(list_name) {
+            buf.cursor_y = loc.y.min(buf.lines.len().saturating_sub(1));
+            buf.cursor_x = loc.x;
+            text = LocationList::message(buf, &loc);
+        }
+        self.next_error_buffer = Some(list_name.to_string());
+
+        if let Err(e) = self.visit_file(path.clone()) {
+            self.minibuffer.message(&format!("Cannot open {}: {e}", path.display()));
+            return;
+        }
+        self.goto_line(loc.line);
+        let line_len = self.buffer.lines.get(self.buffer.cursor_y).map_or(0, |l| l.char_len);
+        self.buffer.cursor_x = loc.col.unwrap_or(1).saturating_sub(1).min(line_len);
+        self.ensure_cursor_visible();
+        self.minibuffer.message(&text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcc_and_rustc_locations() {
+        let loc = parse_location("main.c:3:5: error: expected ';'").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col, loc.x, loc.len), ("main.c", 3, Some(5), 0, 10));
+        let loc = parse_location("  --> src/main.rs:12:9").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col, loc.x), ("src/main.rs", 12, Some(9), 6));
+        assert!(parse_location("at 12:30:00 on localhost:8080").is_none());
+    }
+
+    #[test]
+    fn bare_file_names() {
+        let loc = parse_location("Makefile:12: *** missing separator.  Stop.").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col, loc.len), ("Makefile", 12, None, 11));
+        let loc = parse_location("Dockerfile:3").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col), ("Dockerfile", 3, None));
+        let loc = parse_location("  GNUmakefile:7:2: warning: overriding recipe").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col, loc.x), ("GNUmakefile", 7, Some(2), 2));
+    }
+
+    #[test]
+    fn things_that_are_not_locations() {
+        assert!(parse_location("error: could not compile `remux`").is_none());
+        assert!(parse_location("Note:see below").is_none());
+        assert!(parse_location("localhost:8080").is_none());
+        assert!(parse_location("http://example.com:80/").is_none());
+        assert!(parse_location("Makefile:0:").is_none());
+        assert!(parse_location("$HOME:3").is_none());
+    }
+
+    #[test]
+    fn non_ascii_whitespace() {
+        let loc = parse_location("エラー\u{3000}src/main.rs:3:4: error").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col, loc.x), ("src/main.rs", 3, Some(4), 4));
+        let loc = parse_location("see\u{a0}\u{a0}lib.rs:7").unwrap();
+        assert_eq!((loc.file.as_str(), loc.line, loc.col, loc.x), ("lib.rs", 7, None, 5));
+    }
+}
@@ -0,0 +1,143 @@
+// core/src/completion.rs
+//
+// Fuzzy completion for minibuffer prompts. The query's chars must appear in
+// the candidate in order; matches are ranked so that consecutive runs, word
+// starts and short candidates come first.
+
+/// Score of `candidate` for `query`, higher is better; `None` when the
+/// query is not a subsequence of the candidate. Case-insensitive unless
+/// the query has capitals.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let smart = query.chars().any(char::is_uppercase);
+    let fold = |c: char| if smart { c } else { c.to_ascii_lowercase() };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let cand: Vec<char> = candidate.chars().collect();
+    // Chars of the file name part count a little more.
+    let base = cand.iter().rposition(|c| *c == '/').map_or(0, |b| b + 1);
+
+    let char_score = |i: usize| {
+        let boundary = i == 0 || matches!(cand[i - 1], '/' | '_' | '-' | '.' | ' ');
+        1 + if boundary { 6 } else { 0 } + i64::from(i >= base)
+    };
+
+    // best[i]: best score with the current query char matched at `i`.
+    let mut best: Vec<Option<i64>> = cand.iter().enumerate()
+        .map(|(i, c)| (fold(*c) == query[0]).then(|| char_score(i)))
+        .collect();
+    for q in &query[1..] {
+        let mut next = vec![None; cand.len()];
+        // Best score of the previous char anywhere before `i - 1`.
+        let mut earlier: Option<i64> = None;
+        for i in 1..cand.len() {
+            if fold(cand[i]) == *q {
+                let adjacent = best[i - 1].map(|s| s + 8);
+                let gap = earlier.map(|s| s - 1);
+                next[i] = adjacent.max(gap).map(|s| s + char_score(i));
+            }
+            earlier = earlier.max(best[i - 1]);
+        }
+        best = next;
+    }
+    let score = best.into_iter().flatten().max()?;
+    Some(score - (cand.len() as i64) / 8)
+}
+
+/// Candidates of an active prompt and the ones matching its input.
+#[derive(Debug, Clone, Default)]
+pub struct Completions {
+    candidates: Vec<String>,
+    /// Indices into `candidates`, best first.
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Completions {
+    pub fn new(candidates: Vec<String>) -> Self {
+        let mut c = Self { candidates, matches: Vec::new(), selected: 0 };
+        c.update("");
+        c
+    }
+
+    /// Re-rank for a new input. An empty input keeps the given order.
+    pub fn update(&mut self, input: &str) {
+        let mut scored: Vec<(i64, usize)> = self.candidates.iter().enumerate()
+            .filter_map(|(i, c)| fuzzy_score(input, c).map(|s| (s, i)))
+            .collect();
+        // Stable: equal scores keep the candidates' order.
+        scored.sort_by_key(|&(s, _)| -s);
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    pub fn matches(&self) -> impl Iterator<Item = &str> {
+        self.matches.iter().map(|&i| self.candidates[i].as_str())
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn is_candidate(&self, s: &str) -> bool {
+        self.candidates.iter().any(|c| c == s)
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.matches.get(self.selected).map(|&i| self.candidates[i].as_str())
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Move the selection by `delta`, wrapping around.
+    pub fn cycle(&mut self, delta: isize) {
+        let n = self.matches.len() as isize;
+        if n > 0 {
+            self.selected = (self.selected as isize + delta).rem_euclid(n) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(query: &str, candidate: &str) -> i64 {
+        fuzzy_score(query, candidate).unwrap()
+    }
+
+    #[test]
+    fn query_must_be_a_subsequence() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert!(fuzzy_score("abc", "axbxc").is_some());
+        assert!(fuzzy_score("acb", "abc").is_none());
+        assert!(fuzzy_score("abcd", "abc").is_none());
+    }
+
+    #[test]
+    fn capitals_make_it_case_sensitive() {
+        assert!(fuzzy_score("mak", "Makefile").is_some());
+        assert!(fuzzy_score("Mak", "Makefile").is_some());
+        assert!(fuzzy_score("Mak", "makefile").is_none());
+    }
+
+    #[test]
+    fn runs_word_starts_and_short_names_rank_first() {
+        assert!(score("abc", "abc_def") > score("abc", "a_b_c"));
+        assert!(score("fb", "foo_bar") > score("fb", "fabric"));
+        assert!(score("ab", "ab") > score("ab", "ab_and_much_more"));
+        // The file name counts more than its directory.
+        assert!(score("x", "ab/x") > score("x", "x/ab"));
+    }
+
+    #[test]
+    fn matches_are_ordered_best_first() {
+        let mut c = Completions::new(vec!["a_b_c".into(), "xyz".into(), "abc_def".into()]);
+        c.update("abc");
+        assert_eq!(c.matches().collect::<Vec<_>>(), ["abc_def", "a_b_c"]);
+        assert_eq!(c.selected(), Some("abc_def"));
+    }
+}
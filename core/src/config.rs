@@ -1,5 +1,15 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use crate::editor::editor::PhysicalModifiers;
+use crate::editor::layout::{LineNumberStyle, DEFAULT_TAB_WIDTH};
+use crate::grep::GrepProgram;
+use crate::indent::{self, IndentRule};
+use crate::pairs;
+use crate::bookmarks;
+use crate::desktop;
+use crate::lsp::{self, LspServer};
+use crate::project;
+use crate::status::ModeLineFormat;
 
 #[derive(Clone)]
 pub struct UserConfig {
@@ -8,6 +18,39 @@ pub struct UserConfig {
     pub prefix_masks: [PhysicalModifiers; 3], // Physical Modifiers with activates prefix
     pub buffer_borders: bool,
 		pub isearch_highlight: bool,
+    /// Line numbers in the gutter, and how they count.
+    pub line_numbers: bool,
+    pub line_number_style: LineNumberStyle,
+    pub mode_line: ModeLineFormat,
+    /// Defaults for buffers that set no `tab_width` / `indent_tabs_mode`
+    /// of their own.
+    pub tab_width: usize,
+    pub indent_tabs_mode: bool,
+    /// Indentation rule of each major mode.
+    pub indent_rules: HashMap<String, IndentRule>,
+    /// Reindent a line when a closer is typed at its start.
+    pub electric_indent: bool,
+    /// Brackets and quotes, as (opener, closer).
+    pub pairs: Vec<(char, char)>,
+    pub electric_pair: bool,
+    pub show_paren: bool,
+    /// Default for `compile`; updated to the last command run.
+    pub compile_command: String,
+    pub grep_program: GrepProgram,
+    /// Lines of context shown around grep matches.
+    pub grep_context: usize,
+    /// Files or directories whose presence marks a project root.
+    pub project_markers: Vec<String>,
+    /// Language server to start for each LSP language id.
+    pub lsp_servers: HashMap<String, LspServer>,
+    /// Where bookmarks are kept between sessions.
+    pub bookmark_file: PathBuf,
+    /// Save the session on exit and restore it on start.
+    pub desktop_save_mode: bool,
+    pub desktop_file: PathBuf,
+    /// Reopen files where the cursor was left.
+    pub save_place: bool,
+    pub places_file: PathBuf,
 }
 
 impl Default for UserConfig {
@@ -32,6 +75,26 @@ impl Default for UserConfig {
 	    
 						buffer_borders: false,
 						isearch_highlight: false,
+						line_numbers: false,
+						line_number_style: LineNumberStyle::Absolute,
+						mode_line: ModeLineFormat::default(),
+						tab_width: DEFAULT_TAB_WIDTH,
+						indent_tabs_mode: false,
+						indent_rules: indent::default_rules(),
+						electric_indent: true,
+						pairs: pairs::default_pairs(),
+						electric_pair: false,
+						show_paren: true,
+						compile_command: "cargo build".to_string(),
+						grep_program: GrepProgram::Auto,
+						grep_context: 2,
+						project_markers: project::default_markers(),
+						lsp_servers: lsp::default_servers(),
+						bookmark_file: bookmarks::default_bookmark_file(),
+						desktop_save_mode: false,
+						desktop_file: desktop::default_desktop_file(),
+						save_place: false,
+						places_file: desktop::default_places_file(),
         }
     }
 }
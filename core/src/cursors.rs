@@ -0,0 +1,242 @@
+// core/src/cursors.rs
+//
+// Multiple cursors. The main cursor stays in `Buffer::cursor_x/y` and the
+// mark; the others live in `Buffer::cursors`. Editing and motion commands
+// (`EACH_CURSOR_COMMANDS`, plus self-insertion and the editing keys) run
+// once per cursor: each cursor in turn is loaded into the buffer, the
+// command runs, and the edits it made shift the other cursors. The undo
+// entries of one such run become a single undo step. Other commands run
+// once, at the main cursor.
+//
+// Each cursor has its own kill, so killing at every cursor and yanking
+// puts every piece back where it belongs.
+
+use crate::buffer::{Buffer, CharEdit, Position};
+use crate::editor::editor::Editor;
+
+pub const EACH_CURSOR_COMMANDS: &[&str] = &[
+    "move-left",
+    "move-right",
+    "move-up",
+    "move-down",
+    "move-beginning-of-line",
+    "move-end-of-line",
+    "move-word-left",
+    "move-word-right",
+    "forward-sexp",
+    "backward-sexp",
+    "delete-char",
+    "backward-delete-char",
+    "set-mark-command",
+    "newline",
+    "newline-and-indent",
+    "indent-for-tab-command",
+    "kill-word",
+    "kill-backward-word",
+    "kill-sentence",
+    "kill-region",
+    "kill-ring-save",
+    "yank",
+];
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub x: usize,
+    pub y: usize,
+    pub mark: Option<Position>,
+    pub kill: Option<String>,
+}
+
+fn shift_cursor(cursor: &mut Cursor, edits: &[CharEdit]) {
+    for edit in edits {
+        (cursor.x, cursor.y) = edit.shift((cursor.x, cursor.y));
+        if let Some(mark) = &mut cursor.mark {
+            (mark.x, mark.y) = edit.shift((mark.x, mark.y));
+        }
+    }
+}
+
+/// First occurrence of `needle` (a single line) at or after `(x, y)`, as
+/// the char position of its start.
+fn find_from(buffer: &Buffer, x: usize, y: usize, needle: &str) -> Option<(usize, usize)> {
+    for (ly, line) in buffer.lines.iter().enumerate().skip(y) {
+        let from = if ly == y { x } else { 0 };
+        let byte = line.text.char_indices().nth(from).map_or(line.text.len(), |(b, _)| b);
+        if let Some(found) = line.text[byte..].find(needle) {
+            return Some((from + line.text[byte..byte + found].chars().count(), ly));
+        }
+    }
+    None
+}
+
+// ---- Editor integration ----
+impl Editor {
+    fn main_cursor(&self) -> Cursor {
+        Cursor {
+            x: self.buffer.cursor_x,
+            y: self.buffer.cursor_y,
+            mark: self.buffer.mark(),
+            kill: self.kill_buffer.clone(),
+        }
+    }
+
+    fn load_cursor(&mut self, cursor: &Cursor) {
+        self.buffer.cursor_x = cursor.x;
+        self.buffer.cursor_y = cursor.y;
+        self.buffer.set_mark_at(cursor.mark);
+        self.kill_buffer = cursor.kill.clone();
+    }
+
+    /// Run `f` once per cursor, the main one last, so it ends up loaded.
+    pub fn at_each_cursor(&mut self, mut f: impl FnMut(&mut Editor)) {
+        if self.buffer.cursors.is_empty() {
+            f(self);
+            return;
+        }
+        let undo_from = self.buffer.undo_len();
+        let mut all = std::mem::take(&mut self.buffer.cursors);
+        all.push(self.main_cursor());
+
+        for i in 0..all.len() {
+            self.load_cursor(&all[i]);
+            self.buffer.start_edit_log();
+            f(self);
+            let edits = self.buffer.take_edit_log();
+            all[i] = self.main_cursor();
+            for (j, other) in all.iter_mut().enumerate() {
+                if j != i {
+                    shift_cursor(other, &edits);
+                }
+            }
+        }
+
+        let main = all.pop().expect("main cursor");
+        self.load_cursor(&main);
+        self.buffer.cursors = all;
+        self.clamp_cursors();
+        self.buffer.group_undo(undo_from);
+    }
+
+    /// Keep the extra cursors inside the buffer and drop those that landed
+    /// on another cursor.
+    pub fn clamp_cursors(&mut self) {
+        let buf = &mut self.buffer;
+        if buf.cursors.is_empty() {
+            return;
+        }
+        let last = buf.lines.len() - 1;
+        let mut seen = vec![(buf.cursor_x, buf.cursor_y)];
+        let mut cursors = std::mem::take(&mut buf.cursors);
+        for cursor in &mut cursors {
+            cursor.y = cursor.y.min(last);
+            cursor.x = cursor.x.min(buf.lines[cursor.y].char_len);
+        }
+        cursors.retain(|c| {
+            let new = !seen.contains(&(c.x, c.y));
+            seen.push((c.x, c.y));
+            new
+        });
+        buf.cursors = cursors;
+    }
+
+    /// Whether `name` runs at every cursor.
+    pub fn runs_at_each_cursor(&self, name: &str) -> bool {
+        !self.buffer.cursors.is_empty() && EACH_CURSOR_COMMANDS.contains(&name)
+    }
+
+    /// `add-cursor-next-line` / `add-cursor-previous-line`: leave a cursor
+    /// here and move the main one `n` lines down (up when negative).
+    pub fn add_cursor_line(&mut self, n: isize) {
+        let (x, y) = (self.buffer.cursor_x, self.buffer.cursor_y);
+        let target = y as isize + n;
+        if target < 0 || target as usize >= self.buffer.lines.len() {
+            self.minibuffer.message(if n < 0 { "Beginning of buffer" } else { "End of buffer" });
+            return;
+        }
+        let target = target as usize;
+        self.buffer.cursors.push(self.main_cursor());
+        self.buffer.cursor_y = target;
+        self.buffer.cursor_x = x.min(self.buffer.lines[target].char_len);
+        self.clamp_cursors();
+        self.ensure_cursor_visible();
+    }
+
+    /// Text of the region when it is on one line and not empty.
+    fn region_needle(&self) -> Option<String> {
+        let sel = self.buffer.selection()?;
+        if sel.start.y != sel.end.y || sel.start.x == sel.end.x {
+            return None;
+        }
+        Some(self.buffer.lines[sel.start.y].text.chars().skip(sel.start.x).take(sel.end.x - sel.start.x).collect())
+    }
+
+    /// `add-cursor-next-match`: leave a cursor (with its region) here and
+    /// select the next occurrence of the region with the main one. Without
+    /// a region, add a cursor on the next line.
+    pub fn add_cursor_next_match(&mut self) {
+        let Some(needle) = self.region_needle() else {
+            self.add_cursor_line(1);
+            return;
+        };
+        let sel = self.buffer.selection().expect("region");
+        let Some((x, y)) = find_from(&self.buffer, sel.end.x, sel.end.y, &needle) else {
+            self.minibuffer.message(&format!("No more matches for \"{needle}\""));
+            return;
+        };
+        let forward = (self.buffer.cursor_y, self.buffer.cursor_x) == (sel.end.y, sel.end.x);
+        let len = needle.chars().count();
+        self.buffer.cursors.push(self.main_cursor());
+        let (start, end) = (Position { x, y }, Position { x: x + len, y });
+        let (mark, point) = if forward { (start, end) } else { (end, start) };
+        self.buffer.set_mark_at(Some(mark));
+        self.buffer.cursor_x = point.x;
+        self.buffer.cursor_y = point.y;
+        self.ensure_cursor_visible();
+    }
+
+    /// `add-cursors-in-region`: a cursor at the end of every occurrence of
+    /// `needle` in the region, selecting it. The main cursor takes the
+    /// first one.
+    pub fn add_cursors_in_region(&mut self, needle: &str) {
+        let Some(sel) = self.buffer.selection() else {
+            self.minibuffer.message("The mark is not set now, so there is no region");
+            return;
+        };
+        if needle.is_empty() || needle.contains('\n') {
+            self.minibuffer.message("Search for a non-empty string on one line");
+            return;
+        }
+        let len = needle.chars().count();
+        let mut found = Vec::new();
+        let (mut x, mut y) = (sel.start.x, sel.start.y);
+        while let Some((mx, my)) = find_from(&self.buffer, x, y, needle) {
+            if (my, mx + len) > (sel.end.y, sel.end.x) {
+                break;
+            }
+            found.push(Cursor {
+                x: mx + len,
+                y: my,
+                mark: Some(Position { x: mx, y: my }),
+                kill: self.kill_buffer.clone(),
+            });
+            (x, y) = (mx + len, my);
+        }
+        if found.is_empty() {
+            self.minibuffer.message(&format!("No matches for \"{needle}\" in the region"));
+            return;
+        }
+        let count = found.len();
+        let main = found.remove(0);
+        self.load_cursor(&main);
+        self.buffer.cursors = found;
+        self.ensure_cursor_visible();
+        self.minibuffer.message(&format!("{count} cursors"));
+    }
+
+    /// Drop every cursor but the main one.
+    pub fn remove_extra_cursors(&mut self) -> bool {
+        let had = !self.buffer.cursors.is_empty();
+        self.buffer.cursors.clear();
+        had
+    }
+}
@@ -0,0 +1,278 @@
+// core/src/desktop.rs
+//
+// Sessions. With `desktop_save_mode` on, leaving the editor writes the
+// desktop: the files being visited (the current one first) with their
+// cursor and scroll positions, the minibuffer histories and the last kill.
+// The next start reads it back, as does `desktop-read`. There is a single
+// window, so the layout is just which buffer is shown.
+//
+// `save_place` is separate and works for every file, not only those open at
+// exit: the cursor position of a file is remembered when its buffer is
+// killed or the editor exits, and visiting the file again starts there.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use crate::buffer::Buffer;
+use crate::editor::editor::Editor;
+
+/// Files whose place is remembered; the least recently left go first.
+const MAX_PLACES: usize = 1000;
+
+pub struct DesktopBuffer {
+    pub file: PathBuf,
+    pub x: usize,
+    pub y: usize,
+    pub scroll: (usize, usize),
+}
+
+pub struct Desktop {
+    /// The current buffer first.
+    pub buffers: Vec<DesktopBuffer>,
+    pub history: HashMap<String, Vec<String>>,
+    pub kill: Option<String>,
+}
+
+fn data_file(name: &str) -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(std::env::temp_dir);
+    path.push("remux");
+    path.push(name);
+    path
+}
+
+pub fn default_desktop_file() -> PathBuf {
+    data_file("desktop.json")
+}
+
+pub fn default_places_file() -> PathBuf {
+    data_file("places.json")
+}
+
+fn invalid(e: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// JSON in `path`, or `None` when there is no such file.
+fn read_json(path: &Path) -> io::Result<Option<Value>> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map(Some).map_err(invalid),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_json(path: &Path, value: &Value) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value).map_err(invalid)? + "\n")
+}
+
+fn usize_of(v: &Value) -> usize {
+    v.as_u64().unwrap_or(0) as usize
+}
+
+impl Desktop {
+    /// The desktop saved in `path`, or `None` when there is none.
+    pub fn load(path: &Path) -> io::Result<Option<Desktop>> {
+        let Some(value) = read_json(path)? else { return Ok(None) };
+        let buffers = value["buffers"].as_array().into_iter().flatten()
+            .filter_map(|b| Some(DesktopBuffer {
+                file: PathBuf::from(b["file"].as_str()?),
+                x: usize_of(&b["col"]),
+                y: usize_of(&b["line"]),
+                scroll: (usize_of(&b["scroll_x"]), usize_of(&b["scroll_y"])),
+            }))
+            .collect();
+        let history = value["history"].as_object().into_iter().flatten()
+            .map(|(prompt, list)| {
+                let entries = list.as_array().into_iter().flatten()
+                    .filter_map(|e| e.as_str().map(str::to_string))
+                    .collect();
+                (prompt.clone(), entries)
+            })
+            .collect();
+        let kill = value["kill"].as_str().map(str::to_string);
+        Ok(Some(Desktop { buffers, history, kill }))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let buffers: Vec<Value> = self.buffers.iter()
+            .map(|b| json!({
+                "file": b.file.display().to_string(),
+                "line": b.y,
+                "col": b.x,
+                "scroll_x": b.scroll.0,
+                "scroll_y": b.scroll.1,
+            }))
+            .collect();
+        write_json(path, &json!({
+            "buffers": buffers,
+            "history": self.history,
+            "kill": self.kill,
+        }))
+    }
+}
+
+/// Remembered places, most recently left first, as (file, x, y).
+fn load_places(path: &Path) -> io::Result<Vec<(PathBuf, usize, usize)>> {
+    let Some(value) = read_json(path)? else { return Ok(Vec::new()) };
+    Ok(value.as_array().into_iter().flatten()
+        .filter_map(|p| Some((PathBuf::from(p["file"].as_str()?), usize_of(&p["col"]), usize_of(&p["line"]))))
+        .collect())
+}
+
+fn save_places(path: &Path, places: &[(PathBuf, usize, usize)]) -> io::Result<()> {
+    let list: Vec<Value> = places.iter()
+        .map(|(file, x, y)| json!({ "file": file.display().to_string(), "line": y, "col": x }))
+        .collect();
+    write_json(path, &Value::Array(list))
+}
+
+/// The name a file's place is kept under.
+fn place_key(buffer: &Buffer) -> Option<PathBuf> {
+    let path = buffer.absolute_path()?;
+    Some(fs::canonicalize(&path).unwrap_or(path))
+}
+
+fn place_of(buffer: &Buffer) -> Option<(PathBuf, usize, usize)> {
+    Some((place_key(buffer)?, buffer.cursor_x, buffer.cursor_y))
+}
+
+// ---- Editor integration ----
+impl Editor {
+    fn desktop_file(&self) -> PathBuf {
+        self.user_config.borrow().desktop_file.clone()
+    }
+
+    /// Files being visited, the current one first.
+    pub fn desktop(&self) -> Desktop {
+        let mut buffers = Vec::new();
+        for (i, b) in std::iter::once(&self.buffer).chain(self.buffers.iter()).enumerate() {
+            let Some(file) = b.absolute_path() else { continue };
+            let scroll = if i == 0 { (self.scroll_x, self.scroll_y) } else { b.saved_scroll };
+            buffers.push(DesktopBuffer { file, x: b.cursor_x, y: b.cursor_y, scroll });
+        }
+        Desktop {
+            buffers,
+            history: self.minibuffer.histories().clone(),
+            kill: self.kill_buffer.clone(),
+        }
+    }
+
+    /// `desktop-save`.
+    pub fn desktop_save(&mut self) {
+        let path = self.desktop_file();
+        match self.desktop().save(&path) {
+            Ok(()) => self.minibuffer.message(&format!("Desktop saved in {}", path.display())),
+            Err(e) => self.minibuffer.message(&format!("Cannot write {}: {e}", path.display())),
+        }
+    }
+
+    /// `desktop-read`: visit the saved files, put their cursors and
+    /// scrolling back and show the buffer that was current. Histories are
+    /// merged into the ones of this session.
+    pub fn desktop_read(&mut self) {
+        let path = self.desktop_file();
+        let desktop = match Desktop::load(&path) {
+            Ok(Some(desktop)) => desktop,
+            Ok(None) => {
+                self.minibuffer.message("No desktop saved yet");
+                return;
+            }
+            Err(e) => {
+                self.minibuffer.message(&format!("Cannot read {}: {e}", path.display()));
+                return;
+            }
+        };
+
+        let mut missing = 0;
+        // The first one last, so it ends up current.
+        for b in desktop.buffers.iter().rev() {
+            if self.visit_file(b.file.clone()).is_err() {
+                missing += 1;
+                continue;
+            }
+            let buf = &mut self.buffer;
+            buf.cursor_y = b.y.min(buf.lines.len() - 1);
+            buf.cursor_x = b.x.min(buf.lines[buf.cursor_y].char_len);
+            (self.scroll_x, self.scroll_y) = b.scroll;
+        }
+        self.clamp_scroll();
+
+        let mut history = desktop.history;
+        for (prompt, list) in self.minibuffer.histories() {
+            let merged = history.entry(prompt.clone()).or_default();
+            merged.retain(|h| !list.contains(h));
+            merged.extend(list.iter().cloned());
+        }
+        self.minibuffer.set_histories(history);
+        if self.kill_buffer.is_none() {
+            self.kill_buffer = desktop.kill;
+        }
+
+        let read = desktop.buffers.len() - missing;
+        let msg = match missing {
+            0 => format!("Desktop: {read} buffers restored"),
+            _ => format!("Desktop: {read} buffers restored, {missing} files missing"),
+        };
+        self.minibuffer.message(&msg);
+    }
+
+    /// Remember `places`, as (file, x, y).
+    fn remember_places(&mut self, new: Vec<(PathBuf, usize, usize)>) {
+        let path = self.user_config.borrow().places_file.clone();
+        let mut places = load_places(&path).unwrap_or_default();
+        places.retain(|(f, _, _)| !new.iter().any(|(n, _, _)| n == f));
+        places.splice(0..0, new);
+        places.truncate(MAX_PLACES);
+        if let Err(e) = save_places(&path, &places) {
+            self.minibuffer.message(&format!("Cannot write {}: {e}", path.display()));
+        }
+    }
+
+    /// With `save_place` on, remember the current buffer's place before it
+    /// is killed.
+    pub(crate) fn save_place(&mut self) {
+        if !self.user_config.borrow().save_place {
+            return;
+        }
+        if let Some(place) = place_of(&self.buffer) {
+            self.remember_places(vec![place]);
+        }
+    }
+
+    /// With `save_place` on, move the cursor of a freshly visited file to
+    /// where it was left.
+    pub(crate) fn restore_place(&mut self) {
+        if !self.user_config.borrow().save_place {
+            return;
+        }
+        let Some(file) = place_key(&self.buffer) else { return };
+        let path = self.user_config.borrow().places_file.clone();
+        let places = load_places(&path).unwrap_or_default();
+        let Some(&(_, x, y)) = places.iter().find(|(f, _, _)| *f == file) else { return };
+        let buf = &mut self.buffer;
+        buf.cursor_y = y.min(buf.lines.len() - 1);
+        buf.cursor_x = x.min(buf.lines[buf.cursor_y].char_len);
+        self.ensure_cursor_visible();
+    }
+
+    /// Called when the editor exits: save places and, with
+    /// `desktop_save_mode` on, the desktop. The terminal is still the
+    /// editor's here, so a failure is logged rather than printed.
+    pub fn save_session(&mut self) {
+        if self.user_config.borrow().save_place {
+            let places = std::iter::once(&self.buffer).chain(self.buffers.iter()).filter_map(place_of).collect();
+            self.remember_places(places);
+        }
+        if self.user_config.borrow().desktop_save_mode {
+            let path = self.desktop_file();
+            if let Err(e) = self.desktop().save(&path) {
+                self.log.borrow_mut().error(&format!("Cannot write {}: {e}", path.display()));
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+// core/src/diagnostics.rs
+//
+// Problems reported about files and buffers, whoever found them. Each
+// provider (the language server, the last compilation, a Lua linter) owns
+// its own list per target and replaces it wholesale; the store keeps the
+// merged, sorted list that rendering, the status line and
+// `next-diagnostic` read.
+//
+// Targets are absolute file paths; buffers that visit no file are keyed by
+// their name (`Editor::diagnostic_target`).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::buffer::Buffer;
+use crate::compile::{LocationList, COMPILATION_BUFFER};
+use crate::editor::editor::Editor;
+
+pub const LSP_PROVIDER: &str = "lsp";
+pub const COMPILE_PROVIDER: &str = "compile";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(Self::Error),
+            "warning" => Some(Self::Warning),
+            "info" => Some(Self::Info),
+            "hint" => Some(Self::Hint),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+            Self::Hint => "hint",
+        }
+    }
+}
+
+/// A problem in a range of a buffer. Lines and columns are 0-based chars;
+/// the end is exclusive.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+    pub severity: Severity,
+    pub message: String,
+    /// The tool that reported it (`rustc`, `clippy`, ...), if known.
+    pub source: Option<String>,
+}
+
+#[derive(Default)]
+pub struct DiagnosticStore {
+    by_provider: HashMap<PathBuf, HashMap<String, Vec<Diagnostic>>>,
+    merged: HashMap<PathBuf, Vec<Diagnostic>>,
+}
+
+impl DiagnosticStore {
+    /// Replace what `provider` reported for `target`.
+    pub fn set(&mut self, target: PathBuf, provider: &str, items: Vec<Diagnostic>) {
+        let providers = self.by_provider.entry(target.clone()).or_default();
+        if items.is_empty() {
+            providers.remove(provider);
+        } else {
+            providers.insert(provider.to_string(), items);
+        }
+        self.merge(target);
+    }
+
+    /// Forget everything `provider` reported.
+    pub fn clear_provider(&mut self, provider: &str) {
+        let targets: Vec<PathBuf> = self.by_provider.iter()
+            .filter(|(_, p)| p.contains_key(provider))
+            .map(|(t, _)| t.clone())
+            .collect();
+        for target in targets {
+            self.set(target, provider, Vec::new());
+        }
+    }
+
+    /// All diagnostics of `target`, by position.
+    pub fn get(&self, target: &Path) -> &[Diagnostic] {
+        self.merged.get(target).map_or(&[], |d| d.as_slice())
+    }
+
+    fn merge(&mut self, target: PathBuf) {
+        let mut all: Vec<Diagnostic> = self.by_provider.get(&target)
+            .map(|p| p.values().flatten().cloned().collect())
+            .unwrap_or_default();
+        if all.is_empty() {
+            self.by_provider.remove(&target);
+            self.merged.remove(&target);
+            return;
+        }
+        all.sort_by_key(|d| (d.line, d.col, d.severity));
+        self.merged.insert(target, all);
+    }
+}
+
+/// Guess the severity of a compiler message from its first
+/// `error` / `warning` / `note` / `help` word.
+fn compile_severity(text: &str) -> Severity {
+    for word in text.split(|c: char| !c.is_ascii_alphabetic()) {
+        match word {
+            "error" => return Severity::Error,
+            "warning" => return Severity::Warning,
+            "note" | "help" => return Severity::Info,
+            _ => {}
+        }
+    }
+    Severity::Error
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Key of `buf` in the diagnostic store.
+    pub fn diagnostic_target_of(buf: &Buffer) -> PathBuf {
+        buf.absolute_path().unwrap_or_else(|| PathBuf::from(buf.file_name()))
+    }
+
+    pub fn diagnostic_target(&self) -> PathBuf {
+        Self::diagnostic_target_of(&self.buffer)
+    }
+
+    /// Diagnostics of the current buffer.
+    pub fn buffer_diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.get(&self.diagnostic_target())
+    }
+
+    /// Turn the locations of a finished `*compilation*` into diagnostics of
+    /// the files they point at, replacing those of the previous run.
+    pub fn publish_compile_diagnostics(&mut self) {
+        self.diagnostics.clear_provider(COMPILE_PROVIDER);
+        let Some(list) = self.locations.get(COMPILATION_BUFFER) else { return };
+        let Some(buf) = self.find_buffer(COMPILATION_BUFFER) else { return };
+
+        let mut per_file: HashMap<PathBuf, Vec<Diagnostic>> = HashMap::new();
+        for loc in &list.items {
+            let message = LocationList::message(buf, loc);
+            let path = list.resolve(loc);
+            let path = path.canonicalize().unwrap_or(path);
+            let line = loc.line.saturating_sub(1);
+            let col = loc.col.unwrap_or(1).saturating_sub(1);
+            per_file.entry(path).or_default().push(Diagnostic {
+                line,
+                col,
+                end_line: line,
+                end_col: col + 1,
+                severity: compile_severity(&message),
+                message,
+                source: None,
+            });
+        }
+        for (path, items) in per_file {
+            self.diagnostics.set(path, COMPILE_PROVIDER, items);
+        }
+    }
+
+    /// Move to the `n`th diagnostic after the cursor (before it when `n` is
+    /// negative) and show its message.
+    pub fn next_diagnostic(&mut self, n: isize) {
+        let here = (self.buffer.cursor_y, self.buffer.cursor_x);
+        let diagnostics = self.buffer_diagnostics();
+        if diagnostics.is_empty() {
+            self.minibuffer.message("No diagnostics");
+            return;
+        }
+        // Where the cursor goes for each: a column past the end of its line
+        // stops at the end, and must not be found again from there.
+        let lines = &self.buffer.lines;
+        let start = |d: &Diagnostic| (d.line, lines.get(d.line).map_or(d.col, |l| d.col.min(l.char_len)));
+        // Several diagnostics can start at the same place; step over them
+        // as one.
+        let mut starts: Vec<(usize, usize)> = diagnostics.iter().map(start).collect();
+        starts.dedup();
+        let target = if n > 0 {
+            starts.iter().filter(|p| **p > here).nth(n as usize - 1)
+        } else {
+            starts.iter().rev().filter(|p| **p < here).nth(n.unsigned_abs().saturating_sub(1))
+        };
+        let Some(&(line, col)) = target else {
+            self.minibuffer.message(if n < 0 { "No previous diagnostic" } else { "No more diagnostics" });
+            return;
+        };
+
+        let text = diagnostics.iter()
+            .filter(|d| start(d) == (line, col))
+            .map(|d| match &d.source {
+                Some(source) => format!("{}: {} [{source}]", d.severity.name(), d.message),
+                None => format!("{}: {}", d.severity.name(), d.message),
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        self.goto_line(line + 1);
+        let line_len = self.buffer.lines.get(self.buffer.cursor_y).map_or(0, |l| l.char_len);
+        self.buffer.cursor_x = col.min(line_len);
+        self.ensure_cursor_visible();
+        self.minibuffer.message(&text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::command::CommandRegistry;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+
+    fn diag(line: usize, col: usize, severity: Severity, message: &str) -> Diagnostic {
+        Diagnostic {
+            line,
+            col,
+            end_line: line,
+            end_col: col + 1,
+            severity,
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    fn messages(store: &DiagnosticStore, target: &str) -> Vec<String> {
+        store.get(Path::new(target)).iter().map(|d| d.message.clone()).collect()
+    }
+
+    #[test]
+    fn providers_merge_by_position_and_severity() {
+        let mut store = DiagnosticStore::default();
+        store.set("/a.rs".into(), LSP_PROVIDER, vec![diag(3, 0, Severity::Warning, "lsp late"), diag(1, 4, Severity::Hint, "lsp hint")]);
+        store.set("/a.rs".into(), COMPILE_PROVIDER, vec![diag(1, 4, Severity::Error, "rustc error"), diag(0, 0, Severity::Info, "rustc note")]);
+        assert_eq!(messages(&store, "/a.rs"), ["rustc note", "rustc error", "lsp hint", "lsp late"]);
+
+        // A provider's list is replaced whole; the other is kept.
+        store.set("/a.rs".into(), LSP_PROVIDER, vec![diag(2, 0, Severity::Error, "lsp new")]);
+        assert_eq!(messages(&store, "/a.rs"), ["rustc note", "rustc error", "lsp new"]);
+        store.set("/a.rs".into(), LSP_PROVIDER, Vec::new());
+        assert_eq!(messages(&store, "/a.rs"), ["rustc note", "rustc error"]);
+        store.set("/a.rs".into(), COMPILE_PROVIDER, Vec::new());
+        assert!(store.get(Path::new("/a.rs")).is_empty());
+        assert!(store.by_provider.is_empty() && store.merged.is_empty());
+    }
+
+    #[test]
+    fn clearing_a_provider_keeps_the_others() {
+        let mut store = DiagnosticStore::default();
+        store.set("/a.rs".into(), COMPILE_PROVIDER, vec![diag(0, 0, Severity::Error, "a compile")]);
+        store.set("/a.rs".into(), LSP_PROVIDER, vec![diag(1, 0, Severity::Error, "a lsp")]);
+        store.set("/b.rs".into(), COMPILE_PROVIDER, vec![diag(0, 0, Severity::Error, "b compile")]);
+        store.clear_provider(COMPILE_PROVIDER);
+        assert_eq!(messages(&store, "/a.rs"), ["a lsp"]);
+        assert!(store.get(Path::new("/b.rs")).is_empty());
+        assert_eq!(store.by_provider.len(), 1);
+    }
+
+    #[test]
+    fn compile_messages_name_their_severity() {
+        assert_eq!(compile_severity("main.c:3:5: warning: unused"), Severity::Warning);
+        assert_eq!(compile_severity("note: required by a bound"), Severity::Info);
+        assert_eq!(compile_severity("error[E0308]: mismatched types"), Severity::Error);
+        assert_eq!(compile_severity("undefined reference to `x'"), Severity::Error);
+    }
+
+    #[test]
+    fn next_diagnostic_steps_over_shared_starts() {
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let mut ed = Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(UserConfig::default())));
+        ed.next_diagnostic(1);
+        assert_eq!(ed.minibuffer.get(), "No diagnostics");
+
+        ed.buffer.set_text_raw("zero\none\ntwo\nthree");
+        let mut lint = diag(1, 2, Severity::Warning, "unused");
+        lint.source = Some("clippy".to_string());
+        ed.diagnostics.set(ed.diagnostic_target(), LSP_PROVIDER, vec![
+            diag(1, 2, Severity::Error, "bad"),
+            lint,
+            diag(2, 9, Severity::Hint, "past the end"),
+            diag(3, 0, Severity::Info, "last"),
+        ]);
+
+        ed.next_diagnostic(1);
+        assert_eq!((ed.buffer.cursor_y, ed.buffer.cursor_x), (1, 2));
+        assert_eq!(ed.minibuffer.get(), "error: bad | warning: unused [clippy]");
+        ed.next_diagnostic(1);
+        // Columns past the end of the line stop at it.
+        assert_eq!((ed.buffer.cursor_y, ed.buffer.cursor_x), (2, 3));
+        ed.next_diagnostic(2);
+        assert_eq!(ed.minibuffer.get(), "No more diagnostics");
+        assert_eq!(ed.buffer.cursor_y, 2);
+        ed.next_diagnostic(1);
+        assert_eq!((ed.buffer.cursor_y, ed.buffer.cursor_x), (3, 0));
+
+        ed.buffer.cursor_y = 3;
+        ed.buffer.cursor_x = 4;
+        ed.next_diagnostic(-2);
+        assert_eq!((ed.buffer.cursor_y, ed.buffer.cursor_x), (2, 3));
+        ed.buffer.cursor_x = 0;
+        ed.buffer.cursor_y = 1;
+        ed.next_diagnostic(-1);
+        assert_eq!(ed.minibuffer.get(), "No previous diagnostic");
+    }
+}
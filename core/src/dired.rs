@@ -0,0 +1,473 @@
+// core/src/dired.rs
+//
+// Directory buffers. Visiting a directory shows a read-only listing; the
+// listing itself is kept on the editor by buffer name, like location lists,
+// and the text is re-rendered from it after every change. Every filesystem
+// operation asks for confirmation in the minibuffer first.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::editor::editor::{Editor, KeyMap, Modifiers};
+use crate::util::DateTime;
+
+pub const DIRED_MODE: &str = "dired";
+
+/// The `  /path/to/dir:` line.
+const HEADER_LINES: usize = 1;
+/// Column where file names start: mark, mode, size and mtime come first.
+const NAME_COL: usize = 41;
+
+pub struct DiredEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+    pub mode: String,
+    pub mtime: Option<SystemTime>,
+    pub marked: bool,
+}
+
+pub struct DiredListing {
+    pub dir: PathBuf,
+    /// `..` first, then directories, then files, each sorted by name.
+    pub entries: Vec<DiredEntry>,
+}
+
+impl DiredListing {
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        if let Some(parent) = dir.parent()
+            && let Ok(meta) = fs::metadata(parent)
+        {
+            entries.push(DiredEntry::new("..".to_string(), &meta, false));
+        }
+
+        let mut rest = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let Ok(link_meta) = entry.path().symlink_metadata() else { continue };
+            let is_symlink = link_meta.file_type().is_symlink();
+            // Links show what they point to, when it exists.
+            let meta = fs::metadata(entry.path()).unwrap_or(link_meta);
+            rest.push(DiredEntry::new(entry.file_name().to_string_lossy().into_owned(), &meta, is_symlink));
+        }
+        rest.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        entries.extend(rest);
+
+        Ok(Self { dir: dir.to_path_buf(), entries })
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("  {}:", self.dir.display());
+        for e in &self.entries {
+            let mark = if e.marked { '*' } else { ' ' };
+            let time = e.mtime.map_or_else(|| "-".repeat(16), |t| DateTime::from_system(t).format_minutes());
+            let suffix = if e.is_dir && e.name != ".." { "/" } else if e.is_symlink { "@" } else { "" };
+            out.push_str(&format!("\n{mark} {} {:>10} {time} {}{suffix}", e.mode, e.size, e.name));
+        }
+        out
+    }
+
+    /// Entry shown on buffer line `y`.
+    pub fn entry_at(&self, y: usize) -> Option<usize> {
+        y.checked_sub(HEADER_LINES).filter(|i| *i < self.entries.len())
+    }
+
+    /// The marked entries, or the one on line `y` when nothing is marked.
+    pub fn targets(&self, y: usize) -> Vec<usize> {
+        let marked: Vec<usize> = (0..self.entries.len()).filter(|i| self.entries[*i].marked).collect();
+        if !marked.is_empty() {
+            return marked;
+        }
+        self.entry_at(y)
+            .filter(|i| self.entries[*i].name != "..")
+            .into_iter()
+            .collect()
+    }
+
+    pub fn path(&self, i: usize) -> PathBuf {
+        self.dir.join(&self.entries[i].name)
+    }
+}
+
+impl DiredEntry {
+    fn new(name: String, meta: &fs::Metadata, is_symlink: bool) -> Self {
+        Self {
+            name,
+            is_dir: meta.is_dir(),
+            is_symlink,
+            size: meta.len(),
+            mode: mode_string(meta, is_symlink),
+            mtime: meta.modified().ok(),
+            marked: false,
+        }
+    }
+}
+
+/// `drwxr-xr-x`, as printed by `ls -l`.
+#[cfg(unix)]
+fn mode_string(meta: &fs::Metadata, is_symlink: bool) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode();
+    let kind = if is_symlink { 'l' } else if meta.is_dir() { 'd' } else { '-' };
+    let mut s = String::with_capacity(10);
+    s.push(kind);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        s.push(if bits & 4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 2 != 0 { 'w' } else { '-' });
+        s.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    s
+}
+
+#[cfg(not(unix))]
+fn mode_string(meta: &fs::Metadata, is_symlink: bool) -> String {
+    let kind = if is_symlink { 'l' } else if meta.is_dir() { 'd' } else { '-' };
+    let w = if meta.permissions().readonly() { '-' } else { 'w' };
+    format!("{kind}r{w}-r{w}-r{w}-")
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Where `src` ends up when moved or copied to `dest`: into it when `dest`
+/// is a directory.
+fn destination(src: &Path, dest: &Path) -> PathBuf {
+    match src.file_name() {
+        Some(name) if dest.is_dir() => dest.join(name),
+        _ => dest.to_path_buf(),
+    }
+}
+
+fn describe(paths: &[PathBuf]) -> String {
+    match paths {
+        [one] => one.file_name().map_or_else(|| one.display().to_string(), |n| n.to_string_lossy().into_owned()),
+        many => format!("{} files", many.len()),
+    }
+}
+
+/// Default bindings for directory buffers.
+pub fn dired_keymap() -> KeyMap {
+    let mut map = KeyMap::new();
+    for (key, cmd) in [
+        ('\n', "dired-find-file"),
+        ('f', "dired-find-file"),
+        ('^', "dired-up-directory"),
+        ('g', "dired-refresh"),
+        ('m', "dired-mark"),
+        ('u', "dired-unmark"),
+        ('U', "dired-unmark-all"),
+        ('D', "dired-do-delete"),
+        ('R', "dired-do-rename"),
+        ('C', "dired-do-copy"),
+        ('+', "dired-create-directory"),
+        ('n', "move-down"),
+        ('p', "move-up"),
+        ('q', "kill-buffer"),
+    ] {
+        map.bind(Modifiers::none(), key, cmd.to_string());
+    }
+    map
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Show a listing of `dir`, reusing its buffer if there is one.
+    pub fn dired(&mut self, dir: PathBuf) -> io::Result<()> {
+        let dir = self.buffer.expand_tilde(dir);
+        let dir = fs::canonicalize(&dir)?;
+        let listing = DiredListing::read(&dir)?;
+        let name = format!("{}/", dir.display().to_string().trim_end_matches('/'));
+
+        self.dired.insert(name.clone(), listing);
+        self.switch_to_buffer(&name);
+        self.buffer.major_mode = DIRED_MODE.to_string();
+        self.buffer.read_only = true;
+        self.dired_redisplay(&name, Some(HEADER_LINES + 1));
+        Ok(())
+    }
+
+    fn dired_listing(&self) -> Option<(String, &DiredListing)> {
+        let name = self.buffer.file_name();
+        let listing = self.dired.get(&name)?;
+        Some((name, listing))
+    }
+
+    /// Re-render `name` from its listing, keeping the cursor line unless
+    /// `line` says otherwise.
+    fn dired_redisplay(&mut self, name: &str, line: Option<usize>) {
+        let Some(text) = self.dired.get(name).map(|l| l.render()) else { return };
+        let Some(buf) = self.find_buffer_mut(name) else { return };
+        let y = line.unwrap_or(buf.cursor_y);
+        buf.set_text_raw(&text);
+        buf.cursor_y = y.min(buf.lines.len() - 1);
+        buf.cursor_x = if buf.cursor_y < HEADER_LINES { 0 } else { NAME_COL };
+        self.ensure_cursor_visible();
+    }
+
+    /// Read the directory again. Marks on files that still exist are kept.
+    pub fn dired_refresh(&mut self, name: &str) {
+        let Some(old) = self.dired.get(name) else { return };
+        let marked: Vec<String> = old.entries.iter().filter(|e| e.marked).map(|e| e.name.clone()).collect();
+        match DiredListing::read(&old.dir) {
+            Ok(mut listing) => {
+                for e in listing.entries.iter_mut() {
+                    e.marked = marked.contains(&e.name);
+                }
+                self.dired.insert(name.to_string(), listing);
+                self.dired_redisplay(name, None);
+            }
+            Err(e) => self.minibuffer.message(&format!("Cannot read directory: {e}")),
+        }
+    }
+
+    pub fn dired_find_file(&mut self) {
+        let Some((_, listing)) = self.dired_listing() else { return };
+        let Some(i) = listing.entry_at(self.buffer.cursor_y) else { return };
+        let path = listing.path(i);
+        let result = if listing.entries[i].is_dir { self.dired(path) } else { self.visit_file(path) };
+        if let Err(e) = result {
+            self.minibuffer.message(&format!("Open failed: {e}"));
+        }
+    }
+
+    pub fn dired_up_directory(&mut self) {
+        let Some((_, listing)) = self.dired_listing() else { return };
+        let Some(parent) = listing.dir.parent().map(Path::to_path_buf) else { return };
+        if let Err(e) = self.dired(parent) {
+            self.minibuffer.message(&format!("Open failed: {e}"));
+        }
+    }
+
+    /// Mark or unmark the entry at the cursor and move to the next line.
+    pub fn dired_mark(&mut self, marked: bool) {
+        let name = self.buffer.file_name();
+        let y = self.buffer.cursor_y;
+        let Some(listing) = self.dired.get_mut(&name) else { return };
+        if let Some(i) = listing.entry_at(y).filter(|i| listing.entries[*i].name != "..") {
+            listing.entries[i].marked = marked;
+        }
+        self.dired_redisplay(&name, Some(y + 1));
+    }
+
+    pub fn dired_unmark_all(&mut self) {
+        let name = self.buffer.file_name();
+        let Some(listing) = self.dired.get_mut(&name) else { return };
+        listing.entries.iter_mut().for_each(|e| e.marked = false);
+        self.dired_redisplay(&name, None);
+    }
+
+    fn dired_targets(&mut self) -> Option<(String, Vec<PathBuf>)> {
+        let (name, listing) = self.dired_listing()?;
+        let paths: Vec<PathBuf> = listing.targets(self.buffer.cursor_y).into_iter().map(|i| listing.path(i)).collect();
+        if paths.is_empty() {
+            self.minibuffer.message("No file on this line");
+            return None;
+        }
+        Some((name, paths))
+    }
+
+    /// Report the outcome of a bulk operation and show the new state.
+    fn dired_finish(&mut self, name: &str, done: usize, errors: Vec<String>) {
+        if let Some(listing) = self.dired.get_mut(name) {
+            listing.entries.iter_mut().for_each(|e| e.marked = false);
+        }
+        self.dired_refresh(name);
+        match errors.first() {
+            Some(first) => self.minibuffer.message(&format!("{} failed: {first}", errors.len())),
+            None => self.minibuffer.message(&format!("{done} done")),
+        }
+    }
+
+    pub fn dired_do_delete(&mut self) {
+        let Some((name, paths)) = self.dired_targets() else { return };
+        let question = format!("Delete {}?", describe(&paths));
+        self.confirm(&question, move |ed| {
+            let mut errors = Vec::new();
+            for p in &paths {
+                let is_dir = p.symlink_metadata().is_ok_and(|m| m.is_dir());
+                let res = if is_dir { fs::remove_dir_all(p) } else { fs::remove_file(p) };
+                if let Err(e) = res {
+                    errors.push(format!("{}: {e}", p.display()));
+                }
+            }
+            ed.dired_finish(&name, paths.len() - errors.len(), errors);
+        });
+    }
+
+    pub fn dired_do_rename(&mut self) {
+        self.dired_transfer(true);
+    }
+
+    pub fn dired_do_copy(&mut self) {
+        self.dired_transfer(false);
+    }
+
+    /// Rename/move or copy the targets: ask for the destination, then for
+    /// confirmation.
+    fn dired_transfer(&mut self, rename: bool) {
+        let Some((name, paths)) = self.dired_targets() else { return };
+        let verb = if rename { "Rename" } else { "Copy" };
+        let prompt = format!("{verb} {} to: ", describe(&paths));
+        let initial = match paths.as_slice() {
+            [one] => one.display().to_string(),
+            _ => format!("{}/", paths[0].parent().unwrap_or(Path::new("/")).display()),
+        };
+
+        self.read_from_minibuffer(&prompt, &initial, move |ed, input| {
+            let input = input.trim();
+            if input.is_empty() {
+                ed.minibuffer.message("No destination given");
+                return;
+            }
+            let dest = ed.buffer.expand_tilde(input);
+            if paths.len() > 1 && !dest.is_dir() {
+                ed.minibuffer.message(&format!("{} is not a directory", dest.display()));
+                return;
+            }
+            let question = format!("{verb} {} to {}?", describe(&paths), dest.display());
+            ed.confirm(&question, move |ed| {
+                let mut errors = Vec::new();
+                for src in &paths {
+                    let to = destination(src, &dest);
+                    let res = if rename { fs::rename(src, &to) } else { copy_recursive(src, &to) };
+                    match res {
+                        Ok(()) if rename => ed.file_renamed(src, &to),
+                        Ok(()) => {}
+                        Err(e) => errors.push(format!("{}: {e}", src.display())),
+                    }
+                }
+                ed.dired_finish(&name, paths.len() - errors.len(), errors);
+            });
+        });
+    }
+
+    pub fn dired_create_directory(&mut self) {
+        let Some((name, listing)) = self.dired_listing() else { return };
+        let initial = format!("{}/", listing.dir.display().to_string().trim_end_matches('/'));
+        self.read_from_minibuffer("Create directory: ", &initial, move |ed, input| {
+            let dir = ed.buffer.expand_tilde(input.trim());
+            let question = format!("Create directory {}?", dir.display());
+            ed.confirm(&question, move |ed| {
+                let errors = match fs::create_dir_all(&dir) {
+                    Ok(()) => Vec::new(),
+                    Err(e) => vec![format!("{}: {e}", dir.display())],
+                };
+                ed.dired_finish(&name, 1 - errors.len(), errors);
+            });
+        });
+    }
+
+    /// Buffers visiting a renamed file follow it, and are named after
+    /// its new name.
+    fn file_renamed(&mut self, from: &Path, to: &Path) {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let all = std::iter::once(&mut self.buffer).chain(self.buffers.iter_mut());
+        let mut moved = Vec::new();
+        for (i, buf) in all.enumerate() {
+            let Some(path) = buf.file_path.as_ref().map(|p| cwd.join(p)) else { continue };
+            if let Ok(rest) = path.strip_prefix(from) {
+                buf.file_path = Some(if rest.as_os_str().is_empty() { to.to_path_buf() } else { to.join(rest) });
+                moved.push(i);
+            }
+        }
+        for i in moved {
+            self.rename_after_file(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::command::CommandRegistry;
+    use crate::config::UserConfig;
+
+    /// A fresh directory holding `b.txt`, `a.txt` and `sub/`.
+    fn tree(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("remux-dired-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.txt"), "bb").unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    fn editor() -> Editor {
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(UserConfig::default())))
+    }
+
+    fn marked(ed: &Editor) -> Vec<String> {
+        let listing = &ed.dired[&ed.buffer.file_name()];
+        listing.entries.iter().filter(|e| e.marked).map(|e| e.name.clone()).collect()
+    }
+
+    #[test]
+    fn listing_puts_directories_first() {
+        let dir = tree("list");
+        let listing = DiredListing::read(&dir).unwrap();
+        let names: Vec<&str> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["..", "sub", "a.txt", "b.txt"]);
+
+        let text = listing.render();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], format!("  {}:", dir.display()));
+        let shown: Vec<String> = lines[1..].iter().map(|l| l.chars().skip(NAME_COL).collect()).collect();
+        assert_eq!(shown, ["..", "sub/", "a.txt", "b.txt"]);
+        assert!(lines[4].starts_with("  -"));
+        assert!(lines[4].contains("         2 "));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn marks_pick_the_targets() {
+        let dir = tree("marks");
+        let mut ed = editor();
+        ed.dired(dir.clone()).unwrap();
+        // On the first entry after `..`.
+        assert_eq!((ed.buffer.cursor_x, ed.buffer.cursor_y), (NAME_COL, 2));
+
+        // `..` cannot be marked; marking moves on either way.
+        ed.buffer.cursor_y = 1;
+        ed.dired_mark(true);
+        ed.dired_mark(true);
+        assert_eq!(ed.buffer.cursor_y, 3);
+        ed.buffer.cursor_y = 4;
+        ed.dired_mark(true);
+        assert_eq!(marked(&ed), ["sub", "b.txt"]);
+        assert!(ed.buffer.lines[4].text.starts_with("* "));
+
+        let listing = &ed.dired[&ed.buffer.file_name()];
+        assert_eq!(listing.targets(1), [1, 3]);
+
+        // Marks on files that are still there survive a refresh.
+        fs::remove_file(dir.join("b.txt")).unwrap();
+        ed.dired_refresh(&ed.buffer.file_name());
+        assert_eq!(marked(&ed), ["sub"]);
+
+        ed.dired_unmark_all();
+        assert!(marked(&ed).is_empty());
+        let listing = &ed.dired[&ed.buffer.file_name()];
+        // Without marks, the entry on the line, but never `..`.
+        assert_eq!(listing.targets(3), [2]);
+        assert!(listing.targets(1).is_empty());
+        assert!(listing.targets(0).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,85 @@
+// core/src/editor/bus.rs
+//
+// The editor's single event bus. Producers `emit` events, the editor drains
+// the queue in `process_events` and every notification is dispatched to the
+// Rust subscribers first and then to the Lua hooks, in emission order.
+
+use std::collections::VecDeque;
+use mlua::{Lua, Function, Result};
+use crate::editor::events::EditorEvent;
+use crate::editor::hooks::{HookRegistry, RustHookRegistry};
+
+/// Subscribing under this name receives every notification.
+pub const ANY_EVENT: &str = "*";
+
+pub struct EventBus {
+    queue: VecDeque<EditorEvent>,
+    pub lua_hooks: HookRegistry,
+    pub rust_hooks: RustHookRegistry,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            lua_hooks: HookRegistry::new(),
+            rust_hooks: RustHookRegistry::new(),
+        }
+    }
+
+    pub fn emit(&mut self, ev: EditorEvent) {
+        self.queue.push_back(ev);
+    }
+
+    pub fn extend<I: IntoIterator<Item = EditorEvent>>(&mut self, events: I) {
+        self.queue.extend(events);
+    }
+
+    pub fn pop(&mut self) -> Option<EditorEvent> {
+        self.queue.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Register a Lua hook.
+    pub fn add_hook(&mut self, lua: &Lua, name: &str, func: Function) -> Result<()> {
+        self.lua_hooks.add(lua, name, func)
+    }
+
+    /// Register a Rust handler. Use `ANY_EVENT` to observe the whole stream.
+    pub fn subscribe<F>(&mut self, name: &str, f: F)
+    where
+        F: Fn(&EditorEvent) + Send + Sync + 'static,
+    {
+        self.rust_hooks.add(name, f);
+    }
+
+    /// Deliver one notification to all of its subscribers. Returns what
+    /// went wrong in Lua hooks, to be logged.
+    pub fn dispatch(&self, lua: &Lua, ev: &EditorEvent) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.rust_hooks.run(ANY_EVENT, ev);
+
+        for name in ev.hook_names() {
+            self.rust_hooks.run(name, ev);
+
+            if !self.lua_hooks.has(name) {
+                continue;
+            }
+            match ev.to_lua(lua) {
+                Ok(arg) => errors.extend(self.lua_hooks.run_value(lua, name, arg).into_iter()
+                    .map(|e| format!("Error in {name} hook: {e}"))),
+                Err(_) => continue,
+            }
+        }
+        errors
+    }
+}
@@ -1,15 +1,32 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Instant;
 use bitflags::bitflags;
 use mlua::Lua;
 use crate::{
     command::{CommandRegistry, CommandContext, CommandArg, Interactive},
     minibuffer::{MiniBuffer, MiniBufferMode},
-    editor::hooks::HookRegistry,
-    editor::layout::LineWrapMode,
-    buffer::Buffer,
+    editor::bus::EventBus,
+    editor::events::EditorEvent,
+    editor::layout::{LineWrapMode, LineNumberStyle, char_at_column, column_of, text_width, wrap_starts},
+    buffer::{Buffer, Motion},
     config::UserConfig,
+    timer::{LuaTimers, TimerWheel},
+    process::SharedProcesses,
+    compile::{LocationList, COMPILATION_MODE, compilation_keymap},
+    grep::{GrepSearch, GREP_BUFFER, GREP_MODE, grep_keymap},
+    dired::{DiredListing, DIRED_MODE, dired_keymap},
+    bookmarks::{BOOKMARK_LIST_MODE, bookmark_list_keymap},
+    view_mode::{VIEW_MODE, view_mode_keymap},
+    lsp::client::LspClient,
+    diagnostics::DiagnosticStore,
+    status::LuaSegments,
+    kmacro::MacroState,
+    input::KeyEvent,
+    registers::Register,
+    log::SharedLog,
 };
 
 /// ---- Prefix / Argument Handling ----
@@ -50,42 +67,31 @@ impl InputMode {
 }
 
 
+/// Receives the input submitted to `read_from_minibuffer`.
+pub type MiniBufferCallback = Box<dyn FnOnce(&mut Editor, String)>;
+
 pub enum MiniBufferAction {
     None,
     BeforeExitConfirm,
 		Command(String),
+		/// Hand the submitted input to a callback (see `read_from_minibuffer`).
+		Read(MiniBufferCallback),
 }
 
 
-// ---- Editor Events ----
-#[derive(Debug)]
-pub enum EditorEvent {
-    ExecuteCommand(String),
-    Message(String),
-    OpenFile(String),
-    AddHook { name: String, func: mlua::RegistryKey },
-    ISearchStarted { dir: ISearchDir},
-		ISearchUpdate {
-        dir: ISearchDir,
-        query: String,
-        found: bool,
-				cursor: (usize, usize),
-    },
-		ISearchFinished,
-    ISearchAborted,
-		MiniBufferSubmit(String),
-		BeforeExit,
-}
-
+/// One screen row of a buffer line.
 pub struct VisualLine {
     pub buffer_y: usize,
+    /// First char of the row and how many chars it holds.
     pub start_x: usize,
     pub len: usize,
+    /// Column of the line shown at the left edge.
+    pub start_col: usize,
 }
 
 // ---- Modifiers / KeyMap ----
 bitflags! {
-    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct PhysicalModifiers: u8 {
         const CTRL = 0b0001;
         const ALT = 0b0010;
@@ -117,6 +123,13 @@ impl KeyMap {
             .find(|((bm, bk), _)| *bk == key && mods.contains(*bm))
             .map(|(_, cmd)| cmd)
     }
+    /// Like `lookup`, but the modifiers must match exactly.
+    pub fn lookup_exact(&self, mods: Modifiers, key: char) -> Option<&String> {
+        self.bindings.get(&(mods, key))
+    }
+    pub fn bindings(&self) -> impl Iterator<Item = (Modifiers, char, &String)> {
+        self.bindings.iter().map(|((mods, key), cmd)| (*mods, *key, cmd))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -125,7 +138,7 @@ pub enum ISearchDir {
     Backward,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Selection {
     pub start: (usize, usize),
     pub end: (usize, usize),
@@ -152,6 +165,8 @@ pub struct VisibleVisualLines<'a> {
     remaining: usize,
     buf_y: usize,
     buf_vy_base: usize,
+    /// Row starts of line `buf_y` when wrapping.
+    starts: Option<(usize, Vec<usize>)>,
 }
 
 impl<'a> VisibleVisualLines<'a> {
@@ -162,6 +177,7 @@ impl<'a> VisibleVisualLines<'a> {
             remaining: 0,
             buf_y: 0,
             buf_vy_base: 0,
+            starts: None,
         }
     }
 }
@@ -187,10 +203,22 @@ impl<'a> Iterator for VisibleVisualLines<'a> {
             }
 
             let sub = self.cur_vy - self.buf_vy_base;
-
-            let start_x = match ed.wrap_mode {
-                LineWrapMode::Truncate => ed.scroll_x,
-                LineWrapMode::Wrap => sub * ed.viewport_width,
+            let tab = ed.tab_width();
+
+            let (start_x, len, start_col) = match ed.wrap_mode {
+                LineWrapMode::Truncate => {
+                    let start_x = char_at_column(&line.text, ed.scroll_x, tab);
+                    (start_x, line.char_len - start_x, ed.scroll_x)
+                }
+                LineWrapMode::Wrap => {
+                    if self.starts.as_ref().map(|(y, _)| *y) != Some(self.buf_y) {
+                        self.starts = Some((self.buf_y, wrap_starts(&line.text, ed.viewport_width, tab)));
+                    }
+                    let starts = self.starts.as_ref().map_or(&[][..], |(_, s)| s.as_slice());
+                    let start_x = starts.get(sub).copied().unwrap_or(line.char_len);
+                    let end = starts.get(sub + 1).copied().unwrap_or(line.char_len);
+                    (start_x, end - start_x, column_of(&line.text, start_x, tab))
+                }
             };
 
             self.cur_vy += 1;
@@ -199,7 +227,8 @@ impl<'a> Iterator for VisibleVisualLines<'a> {
             return Some(VisualLine {
                 buffer_y: self.buf_y,
                 start_x,
-                len: ed.viewport_width,
+                len,
+                start_col,
             });
         }
 
@@ -207,18 +236,39 @@ impl<'a> Iterator for VisibleVisualLines<'a> {
     }
 }
 
+/// `base`, or `base<N>` with the least `N` from 2 that is not `taken`.
+fn unique_name(base: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..).map(|n| format!("{base}<{n}>")).find(|name| !taken(name)).unwrap()
+}
+
 // ---- Editor ----
 pub struct Editor {
     pub buffer: Buffer,
     pub keymap: Rc<RefCell<KeyMap>>,
     pub kill_buffer: Option<String>,
+    /// Keyboard macro being defined, the last one, and keys to replay.
+    pub macros: MacroState,
+    /// Macros named with `name-last-kbd-macro` or `define_macro`; they run
+    /// like commands.
+    pub named_macros: HashMap<String, Vec<KeyEvent>>,
+    /// Docs of commands defined in Lua (see `help.rs`).
+    pub command_docs: HashMap<String, String>,
+    /// The next key is described instead of run (`describe-key`).
+    pub describing_key: bool,
+    /// Lines of the last killed or copied rectangle.
+    pub killed_rectangle: Option<Vec<String>>,
+    pub registers: HashMap<char, Register>,
     pub minibuffer: MiniBuffer,
+    /// Messages and logged events (see `log.rs`).
+    pub log: SharedLog,
     pub user_config: Rc<RefCell<UserConfig>>,
     pub commands: CommandRegistry,
-    pub hooks: HookRegistry,
+    pub bus: EventBus,
     pub mode: InputMode,
     pub should_quit: bool,
-    pub event_queue: Vec<EditorEvent>,
     pub wrap_mode: LineWrapMode,
     pub scroll_y: usize,
     pub scroll_x: usize,
@@ -234,22 +284,56 @@ pub struct Editor {
 		pub selection: Option<Selection>,
 		pub last_selection: Option<Selection>,
 		pub minibuffer_action: MiniBufferAction,
-
+    pub timers: LuaTimers,
+    pub processes: SharedProcesses,
+    /// Buffers that are not displayed; `buffer` is the current one.
+    pub buffers: Vec<Buffer>,
+    /// Keymaps consulted before `keymap`, by the buffer's major mode.
+    pub mode_keymaps: HashMap<String, KeyMap>,
+    /// `file:line` locations found in result buffers, by buffer name.
+    pub locations: HashMap<String, LocationList>,
+    /// The location list `next-error` steps through.
+    pub next_error_buffer: Option<String>,
+    pub grep_search: Option<GrepSearch>,
+    /// Directory listings, by buffer name.
+    pub dired: HashMap<String, DiredListing>,
+    /// Project root of each directory looked up so far.
+    pub project_roots: RefCell<HashMap<PathBuf, Option<PathBuf>>>,
+    /// Projects visited with `project-find-file`, most recent first.
+    pub recent_projects: Vec<PathBuf>,
+    /// `git_branch` results by directory, with when they were read.
+    pub git_branches: RefCell<HashMap<PathBuf, (Instant, Option<String>)>>,
+    /// Running language servers.
+    pub lsp_clients: Vec<LspClient>,
+    /// (language, root) of servers that could not be started.
+    pub lsp_failed: HashSet<(String, PathBuf)>,
+    /// Diagnostics of every provider, by file or buffer.
+    pub diagnostics: DiagnosticStore,
+    /// Mode line segments defined from Lua, and their latest values.
+    pub mode_line_segments: LuaSegments,
+    pub mode_line_values: HashMap<String, String>,
 }
 
 impl Editor {
     pub fn new(commands: CommandRegistry, keymap: Rc<RefCell<KeyMap>>, user_config: Rc<RefCell<UserConfig>>) -> Self {
+        let log = SharedLog::default();
         Self {
             buffer: Buffer::new(),
 						keymap,
 						kill_buffer: None,
-            minibuffer: MiniBuffer::default(),
+						macros: MacroState::default(),
+						named_macros: HashMap::new(),
+						command_docs: HashMap::new(),
+						describing_key: false,
+						killed_rectangle: None,
+						registers: HashMap::new(),
+            minibuffer: MiniBuffer::new(log.clone()),
+            log,
 						user_config,
             commands,
-            hooks: HookRegistry::new(),
+            bus: EventBus::new(),
             mode: InputMode::Normal,
             should_quit: false,
-            event_queue: Vec::new(),
             wrap_mode: LineWrapMode::Wrap,
             scroll_y: 0,
             scroll_x: 0,
@@ -265,60 +349,271 @@ impl Editor {
 						selection: None,
 						last_selection: None,
 						minibuffer_action: MiniBufferAction::None,
+            timers: Rc::new(RefCell::new(TimerWheel::default())),
+            processes: Rc::new(RefCell::new(Default::default())),
+            buffers: Vec::new(),
+            mode_keymaps: HashMap::from([
+                (COMPILATION_MODE.to_string(), compilation_keymap()),
+                (GREP_MODE.to_string(), grep_keymap()),
+                (DIRED_MODE.to_string(), dired_keymap()),
+                (BOOKMARK_LIST_MODE.to_string(), bookmark_list_keymap()),
+                (VIEW_MODE.to_string(), view_mode_keymap()),
+            ]),
+            locations: HashMap::new(),
+            next_error_buffer: None,
+            grep_search: None,
+            dired: HashMap::new(),
+            project_roots: RefCell::new(HashMap::new()),
+            recent_projects: Vec::new(),
+            git_branches: RefCell::new(HashMap::new()),
+            lsp_clients: Vec::new(),
+            lsp_failed: HashSet::new(),
+            diagnostics: DiagnosticStore::default(),
+            mode_line_segments: Rc::new(RefCell::new(HashMap::new())),
+            mode_line_values: HashMap::new(),
         }
     }
 
     // ---- Event Handling ----
-    pub fn process_events(&mut self, lua: &Lua) {
-        let events = std::mem::take(&mut self.event_queue);
-        for ev in events {
-            match ev {
-                EditorEvent::ExecuteCommand(name) => self.execute_named(&name, lua),
-                EditorEvent::Message(msg) => self.minibuffer.activate(&msg, MiniBufferMode::Message { ttl: 2 }),
-								EditorEvent::OpenFile(path) => {
-										if self.buffer.open_file(path.clone().into()).is_ok() {
-												self.scroll_y = 0;
-												self.scroll_x = 0;
-												self.rebuild_visual_metrics();
-												self.hooks.run(lua, "buffer-loaded", &path);
-										}
-								}
-								EditorEvent::AddHook { name, func } => self.hooks.add_key(name, func),
-								EditorEvent::ISearchStarted { dir: _ } => {
-										self.hooks.run(lua, "isearch-started", "");
-								}
-								EditorEvent::ISearchUpdate { dir, query, found, cursor } => {
-										self.push_isearch_update_lua(
-												lua,
-												dir,
-												&query,
-												found,
-												cursor,
-										);
-								}
-								EditorEvent::ISearchFinished => {
-										self.hooks.run(lua, "isearch-finish", "");
-										self.hooks.run(&lua, "isearch-end", "");
-								}
-								
-								EditorEvent::ISearchAborted => {
-										self.hooks.run(lua, "isearch-abort", "");
-										self.hooks.run(&lua, "isearch-end", "");
-								}
-								EditorEvent::MiniBufferSubmit(_Input) => {
-										
-								}
-								EditorEvent::BeforeExit => {
-										
-								}
-						}
+
+    /// Drain the event bus. Requests are handled here, notifications are
+    /// dispatched to Rust and Lua subscribers. Returns the dispatched
+    /// notifications, in order, so the frontend sees the same stream.
+    pub fn process_events(&mut self, lua: &Lua) -> Vec<EditorEvent> {
+        let mut dispatched = Vec::new();
+        while let Some(ev) = self.bus.pop() {
+            if ev.is_request() {
+                self.handle_request(ev);
+                continue;
+            }
+            for error in self.bus.dispatch(lua, &ev) {
+                self.log.borrow_mut().error(&error);
+            }
+            if let EditorEvent::BufferSaved { path } = &ev {
+                self.lsp_did_save(path);
+            }
+            dispatched.push(ev);
         }
-				self.minibuffer.tick();
+        self.minibuffer.tick();
+        self.flush_messages();
+        dispatched
     }
-		
+
+    fn handle_request(&mut self, ev: EditorEvent) {
+        match ev {
+            EditorEvent::ExecuteCommand(name) => self.execute_named(&name),
+            EditorEvent::Message(msg) => self.minibuffer.message(&msg),
+            EditorEvent::OpenFile(path) => {
+                if let Err(e) = self.visit_file(path.into()) {
+                    self.minibuffer.message(&format!("Open failed: {e}"));
+                }
+            }
+            EditorEvent::AddHook { name, func } => self.bus.lua_hooks.add_key(name, func),
+            EditorEvent::MiniBufferPrompt { prompt, command } => {
+                self.set_mode(InputMode::MiniBuffer);
+                self.minibuffer.activate(&prompt, MiniBufferMode::Command);
+                self.minibuffer_action = MiniBufferAction::Command(command);
+            }
+            _ => {}
+        }
+    }
+
     #[inline]
-    fn push_event(&mut self, ev: EditorEvent) {
-        self.event_queue.push(ev);
+    pub fn emit(&mut self, ev: EditorEvent) {
+        self.bus.emit(ev);
+    }
+
+    /// Resolve a key: the view mode keymap while it is on, the current
+    /// major mode's keymap, then the global one.
+    pub fn lookup_key(&self, mods: Modifiers, key: char) -> Option<String> {
+        let view = self.buffer.view_mode.is_some().then_some(VIEW_MODE);
+        let local = view.into_iter().chain([self.buffer.major_mode.as_str()])
+            .find_map(|mode| self.mode_keymaps.get(mode)?.lookup_exact(mods, key));
+        if let Some(cmd) = local {
+            return Some(cmd.clone());
+        }
+        self.keymap.borrow().lookup(mods, key).cloned()
+    }
+
+    // ---- Buffers ----
+    pub fn buffer_names(&self) -> Vec<String> {
+        std::iter::once(&self.buffer)
+            .chain(self.buffers.iter())
+            .map(|b| b.file_name())
+            .collect()
+    }
+
+    /// Directory commands run in: the listed directory in a directory
+    /// buffer, the current file's directory, else the working directory.
+    pub fn default_directory(&self) -> std::path::PathBuf {
+        if let Some(listing) = self.dired.get(&self.buffer.file_name()) {
+            return listing.dir.clone();
+        }
+        self.buffer.file_path.as_ref()
+            .and_then(|p| p.parent())
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| p.to_path_buf())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| ".".into())
+    }
+
+    pub fn find_buffer(&self, name: &str) -> Option<&Buffer> {
+        std::iter::once(&self.buffer).chain(self.buffers.iter()).find(|b| b.file_name() == name)
+    }
+
+    pub fn find_buffer_mut(&mut self, name: &str) -> Option<&mut Buffer> {
+        if self.buffer.file_name() == name {
+            return Some(&mut self.buffer);
+        }
+        self.buffers.iter_mut().find(|b| b.file_name() == name)
+    }
+
+    /// `base`, or the first of `base<2>`, `base<3>`, ... no buffer has:
+    /// files in different directories can share a name.
+    pub fn unique_buffer_name(&self, base: &str) -> String {
+        unique_name(base, |name| self.find_buffer(name).is_some())
+    }
+
+    /// Name `buf`, about to be added, apart from the buffers there are.
+    pub fn name_new_buffer(&self, buf: &mut Buffer) {
+        let name = self.unique_buffer_name(&buf.file_name());
+        if name != buf.file_name() {
+            buf.name = Some(name);
+        }
+    }
+
+    /// Name the buffer at `i` of the current one and then `buffers` after
+    /// its file again, once the file has a new path.
+    pub(crate) fn rename_after_file(&mut self, i: usize) {
+        let buf = if i == 0 { &mut self.buffer } else { &mut self.buffers[i - 1] };
+        buf.name = None;
+        let base = buf.file_name();
+        let others = std::iter::once(&self.buffer).chain(self.buffers.iter())
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, b)| b.file_name())
+            .collect::<Vec<_>>();
+        let name = unique_name(&base, |name| others.iter().any(|o| o == name));
+        if name != base {
+            let buf = if i == 0 { &mut self.buffer } else { &mut self.buffers[i - 1] };
+            buf.name = Some(name);
+        }
+    }
+
+    pub fn buffer_mut_or_create(&mut self, name: &str) -> &mut Buffer {
+        if self.find_buffer_mut(name).is_none() {
+            self.buffers.push(Buffer::named(name));
+        }
+        self.find_buffer_mut(name).unwrap()
+    }
+
+    /// Make `name` the current buffer, creating it if needed.
+    pub fn switch_to_buffer(&mut self, name: &str) {
+        if self.buffer.file_name() == name {
+            return;
+        }
+        let next = match self.buffers.iter().position(|b| b.file_name() == name) {
+            Some(i) => self.buffers.remove(i),
+            None => Buffer::named(name),
+        };
+        self.show_buffer(next);
+    }
+
+    pub(crate) fn show_buffer(&mut self, next: Buffer) {
+        let mut prev = std::mem::replace(&mut self.buffer, next);
+        prev.saved_scroll = (self.scroll_x, self.scroll_y);
+        self.buffers.insert(0, prev);
+        (self.scroll_x, self.scroll_y) = self.buffer.saved_scroll;
+        self.scroll_intent = ScrollIntent::FollowCursor;
+        self.buffer.visual.dirty = true;
+        self.ensure_cursor_visible();
+    }
+
+    /// Show the buffer visiting `path`, reading the file if no buffer does.
+    /// Directories open as a listing.
+    pub fn visit_file(&mut self, path: PathBuf) -> std::io::Result<()> {
+        let path = self.buffer.expand_tilde(path);
+        if path.is_dir() {
+            return self.dired(path);
+        }
+        let key = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        let visits = |b: &Buffer| {
+            b.file_path.as_ref().is_some_and(|p| {
+                p == &path || std::fs::canonicalize(p).is_ok_and(|c| c == key)
+            })
+        };
+
+        if visits(&self.buffer) {
+            return Ok(());
+        }
+        if let Some(i) = self.buffers.iter().position(visits) {
+            let buf = self.buffers.remove(i);
+            self.show_buffer(buf);
+            return Ok(());
+        }
+
+        let mut buf = Buffer::new();
+        buf.open_file(path)?;
+        let loaded = buf.file_path.clone();
+        self.name_new_buffer(&mut buf);
+
+        // The initial empty buffer is replaced rather than kept around.
+        let pristine = self.buffer.file_path.is_none()
+            && self.buffer.name.is_none()
+            && !self.buffer.is_modified()
+            && self.buffer.lines.iter().all(|l| l.char_len == 0);
+        if pristine {
+            self.buffer = buf;
+            self.scroll_x = 0;
+            self.scroll_y = 0;
+            self.rebuild_visual_metrics();
+        } else {
+            self.show_buffer(buf);
+        }
+        self.restore_place();
+
+        if let Some(path) = loaded {
+            self.emit(EditorEvent::BufferLoaded { path });
+        }
+        Ok(())
+    }
+
+    /// `kill-buffer`: kill the current buffer, asking first when it
+    /// visits a file and has unsaved changes.
+    pub fn kill_buffer(&mut self) {
+        if self.buffer.file_path.is_some() && self.buffer.is_modified() {
+            let question = format!("Buffer {} modified; kill anyway?", self.buffer.file_name());
+            self.confirm(&question, |ed| ed.kill_current_buffer());
+        } else {
+            self.kill_current_buffer();
+        }
+    }
+
+    /// Drop the current buffer and show the most recently used one.
+    pub fn kill_current_buffer(&mut self) {
+        self.save_register_positions();
+        self.save_place();
+        let name = self.buffer.file_name();
+        self.locations.remove(&name);
+        self.dired.remove(&name);
+        // Output still on its way would bring the buffer back.
+        self.processes.borrow_mut().detach_buffer(&name);
+        if name == GREP_BUFFER {
+            self.grep_search = None;
+        }
+        if self.buffer.track_changes
+            && let Some(path) = self.buffer.file_path.clone() {
+            self.lsp_did_close(&path);
+        }
+        let next = if self.buffers.is_empty() {
+            Buffer::named("*scratch*")
+        } else {
+            self.buffers.remove(0)
+        };
+        self.buffer = next;
+        (self.scroll_x, self.scroll_y) = self.buffer.saved_scroll;
+        self.buffer.visual.dirty = true;
+        self.ensure_cursor_visible();
     }
 
 		#[inline]
@@ -338,84 +633,68 @@ impl Editor {
         self.selection = Some(Selection { start, end });
     }
 		
-		fn emit_selection_changed(&mut self, lua: &Lua) {
+		fn emit_selection_changed(&mut self) {
 				if self.selection != self.last_selection {
 						self.last_selection = self.selection.clone();
-
-						let tbl = lua.create_table().unwrap();
-
-						if let Some(sel) = &self.selection {
-								let s = lua.create_table().unwrap();
-								s.set("x", sel.start.0).unwrap();
-								s.set("y", sel.start.1).unwrap();
-
-								let e = lua.create_table().unwrap();
-								e.set("x", sel.end.0).unwrap();
-								e.set("y", sel.end.1).unwrap();
-
-								tbl.set("start", s).unwrap();
-								tbl.set("end", e).unwrap();
-						} else {
-								tbl.set("cleared", true).unwrap();
-						}
-
-						self.hooks.run_table(lua, "selection-changed", tbl);
+						self.emit(EditorEvent::SelectionChanged(self.selection.clone()));
 				}
 		}
-    fn push_isearch_update_lua(
-				&self,
-				lua: &Lua,
-				dir: ISearchDir,
-				query: &str,
-				found: bool,
-				cursor: (usize, usize),
-		) {
-				let tbl = lua.create_table().unwrap();
-
-				tbl.set("dir", match dir {
-						ISearchDir::Forward => "forward",
-						ISearchDir::Backward => "backward",
-				}).unwrap();
-
-				tbl.set("query", query).unwrap();
-				tbl.set("found", found).unwrap();
-
-				let cur = lua.create_table().unwrap();
-				cur.set("x", cursor.0).unwrap();
-				cur.set("y", cursor.1).unwrap();
-
-				tbl.set("cursor", cur).unwrap();
-
-				self.hooks.run_table(lua, "isearch-update", tbl);
-		}
 
-    fn emit_cursor_moved(&mut self, lua: &Lua) {
+    fn emit_cursor_moved(&mut self) {
 				let cur = (self.buffer.cursor_x, self.buffer.cursor_y);
 				if cur != self.last_cursor {
             self.last_cursor = cur;
-            self.hooks.run(lua, "cursor-moved", &format!("{},{}", cur.0, cur.1));
+            self.emit(EditorEvent::CursorMoved { x: cur.0, y: cur.1 });
 				}
     }
     
-    fn emit_buffer_changed(&mut self, lua: &Lua, reason: &str) {
-        self.hooks.run(lua, "buffer-changed", reason);
+    pub(crate) fn emit_buffer_changed(&mut self, reason: &'static str) {
+        self.emit(EditorEvent::BufferChanged { reason });
     }
     
-    pub fn insert_char(&mut self, lua: &Lua, ch: char) {
-				self.hooks.run(lua, "before-insert-char", "");
-				self.buffer.insert_char_raw(ch);
+    /// True (and says so) when the current buffer must not be edited.
+    pub fn barf_if_read_only(&mut self) -> bool {
+        if self.buffer.read_only {
+            self.minibuffer.message(&format!("Buffer is read-only: {}", self.buffer.file_name()));
+        }
+        self.buffer.read_only
+    }
+
+    pub fn insert_char(&mut self, ch: char) {
+				if self.barf_if_read_only() {
+						return;
+				}
+				self.emit(EditorEvent::BeforeInsertChar(ch));
+				if !self.electric_pair(ch) {
+						self.buffer.insert_char_raw(ch);
+				}
+				if !(ch.is_alphanumeric() || ch == '_') {
+						self.electric_reindent();
+				}
 				self.scroll_intent = ScrollIntent::FollowCursor;
 				self.ensure_cursor_visible();
-				self.emit_cursor_moved(lua);
-				self.emit_selection_changed(lua);
-				self.emit_buffer_changed(lua, "insert-char");
-				self.hooks.run(lua, "after-insert-char", "");
+				self.emit_cursor_moved();
+				self.emit_selection_changed();
+				self.emit_buffer_changed("insert-char");
+				self.emit(EditorEvent::AfterInsertChar(ch));
     }
     
-    pub fn set_mode(&mut self, lua: &Lua, mode: InputMode) {
+    /// Delete the char before the cursor, or the empty pair around it in
+    /// `electric-pair-mode`.
+    pub fn delete_backward_char(&mut self) {
+				if self.barf_if_read_only() {
+						return;
+				}
+				if !self.electric_pair_delete() {
+						self.buffer.delete(Motion::Left);
+				}
+				self.ensure_cursor_visible();
+    }
+    
+    pub fn set_mode(&mut self, mode: InputMode) {
         if self.mode != mode {
             self.mode = mode;
-            self.hooks.run(lua, "mode-changed", mode.as_str());
+            self.emit(EditorEvent::ModeChanged(mode));
         }
     }
     // ---- Scroll / Viewport ----
@@ -451,8 +730,7 @@ impl Editor {
 				let mut vy = ps[cy];
 
 				if self.wrap_mode == LineWrapMode::Wrap {
-            let w = self.viewport_width.max(1);
-            vy += self.buffer.cursor_x / w;
+            vy += self.cursor_row_col().0;
 				}
 
 				vy
@@ -480,10 +758,7 @@ impl Editor {
 				if let ScrollIntent::Manual = self.scroll_intent {
             return;
 				}
-				self.buffer.ensure_visuals(
-            self.viewport_width,
-            self.wrap_mode,
-				);
+				self.ensure_visuals();
 				let height = self.viewport_height.max(1);
 				let cy = self.cursor_global_visual_y();
 				if cy < self.scroll_y {
@@ -493,7 +768,7 @@ impl Editor {
 				}
 				if self.wrap_mode == LineWrapMode::Truncate {
             let width = self.viewport_width.max(1);
-            let cx = self.buffer.cursor_x;
+            let cx = self.cursor_row_col().1;
             if cx < self.scroll_x {
 								self.scroll_x = cx;
             } else if cx >= self.scroll_x + width {
@@ -501,7 +776,7 @@ impl Editor {
             }
             let max = self.buffer.lines
 								.get(self.buffer.cursor_y)
-								.map(|l| l.text.chars().count().saturating_sub(width))
+								.map(|l| (text_width(&l.text, self.tab_width()) + 1).saturating_sub(width))
 								.unwrap_or(0);
 
             self.scroll_x = self.scroll_x.min(max);
@@ -525,44 +800,111 @@ impl Editor {
             remaining: self.viewport_height,
             buf_y,
             buf_vy_base,
+            starts: None,
 				}
     }
     
     pub fn rebuild_visual_metrics(&mut self) {
 				let w = self.viewport_width;
 				let wrap = self.wrap_mode;
-				self.buffer.rebuild_visual_metrics(w, wrap);
+				let tab = self.tab_width();
+				self.buffer.rebuild_visual_metrics(w, wrap, tab);
     }
-    pub fn cursor_visual_pos(&mut self) -> (usize, usize) {
-				self.buffer.ensure_visuals(self.viewport_width, self.wrap_mode);
-				let width = self.viewport_width.max(1);
-				let mut visual_y = self.buffer.visual.prefix_sum[self.buffer.cursor_y];
-				if self.wrap_mode == LineWrapMode::Wrap {
-            visual_y += self.buffer.cursor_x / width;
+
+    pub fn ensure_visuals(&mut self) {
+				let tab = self.tab_width();
+				self.buffer.ensure_visuals(self.viewport_width, self.wrap_mode, tab);
+    }
+
+    /// Row of the cursor within its line and its column within that row
+    /// (within the line when truncating).
+    fn cursor_row_col(&self) -> (usize, usize) {
+				let Some(line) = self.buffer.lines.get(self.buffer.cursor_y) else { return (0, 0) };
+				let tab = self.tab_width();
+				let col = column_of(&line.text, self.buffer.cursor_x, tab);
+				match self.wrap_mode {
+            LineWrapMode::Truncate => (0, col),
+            LineWrapMode::Wrap => {
+								let starts = wrap_starts(&line.text, self.viewport_width, tab);
+								let row = starts.iter().rposition(|s| *s <= self.buffer.cursor_x).unwrap_or(0);
+								// At the end of a full row the cursor stays on its last cell.
+								let in_row = col - column_of(&line.text, starts[row], tab);
+								(row, in_row.min(self.viewport_width.saturating_sub(1)))
+            }
 				}
+    }
+
+    pub fn cursor_visual_pos(&mut self) -> (usize, usize) {
+				self.ensure_visuals();
+				let (row, col) = self.cursor_row_col();
+				let visual_y = self.buffer.visual.prefix_sum[self.buffer.cursor_y] + row;
 				let screen_y = visual_y.saturating_sub(self.scroll_y);
 				let screen_x = match self.wrap_mode {
-            LineWrapMode::Truncate =>
-								self.buffer.cursor_x.saturating_sub(self.scroll_x),
-            LineWrapMode::Wrap =>
-								self.buffer.cursor_x % width,
+            LineWrapMode::Truncate => col.saturating_sub(self.scroll_x),
+            LineWrapMode::Wrap => col,
 				};
-				(screen_x, screen_y)
+				(self.gutter_width() + screen_x, screen_y)
+    }
+
+    /// Tab width of the current buffer.
+    pub fn tab_width(&self) -> usize {
+				self.buffer.tab_width.unwrap_or_else(|| self.user_config.borrow().tab_width).max(1)
+    }
+
+    /// Whether indentation in the current buffer uses tabs.
+    pub fn indent_tabs_mode(&self) -> bool {
+				self.buffer.indent_tabs_mode.unwrap_or_else(|| self.user_config.borrow().indent_tabs_mode)
+    }
+
+    // ---- Gutter ----
+    // Left of the text: a sign column while the buffer has diagnostics, then
+    // line numbers when enabled. `viewport_width` excludes it, and
+    // `cursor_visual_pos` includes it.
+
+    pub fn sign_column_width(&self) -> usize {
+				if self.buffer_diagnostics().is_empty() { 0 } else { 2 }
+    }
+
+    /// Digits for the largest line number, plus a separating space.
+    pub fn line_number_width(&self) -> usize {
+				if !self.user_config.borrow().line_numbers {
+            return 0;
+				}
+				self.buffer.lines.len().to_string().len().max(2) + 1
+    }
+
+    pub fn gutter_width(&self) -> usize {
+				self.sign_column_width() + self.line_number_width()
+    }
+
+    /// The number shown for line `y` in the configured style.
+    pub fn line_number_label(&self, y: usize) -> usize {
+				let cursor = self.buffer.cursor_y;
+				match self.user_config.borrow().line_number_style {
+            LineNumberStyle::Absolute => y + 1,
+            LineNumberStyle::Relative => y.abs_diff(cursor),
+            LineNumberStyle::Hybrid if y == cursor => y + 1,
+            LineNumberStyle::Hybrid => y.abs_diff(cursor),
+				}
     }
 
     #[deprecated(note = "O(N) — do not use in rendering")]
     pub fn build_visual_lines(&self) -> Vec<VisualLine> {
-				let width = self.viewport_width.max(1);
+				let tab = self.tab_width();
 				let mut all = Vec::new();
 				for (y, line) in self.buffer.lines.iter().enumerate() {
 						let len = line.char_len;
             match self.wrap_mode {
-								LineWrapMode::Truncate => all.push(VisualLine { buffer_y: y, start_x: self.scroll_x, len: width }),
+								LineWrapMode::Truncate => {
+                    let x = char_at_column(&line.text, self.scroll_x, tab);
+                    all.push(VisualLine { buffer_y: y, start_x: x, len: len - x, start_col: self.scroll_x });
+								}
 								LineWrapMode::Wrap => {
-                    let mut x = 0;
-                    while x < len {
-												all.push(VisualLine { buffer_y: y, start_x: x, len: width });
-												x += width;
+                    let starts = wrap_starts(&line.text, self.viewport_width, tab);
+                    for (i, &x) in starts.iter().enumerate() {
+												let end = starts.get(i + 1).copied().unwrap_or(len);
+												let start_col = column_of(&line.text, x, tab);
+												all.push(VisualLine { buffer_y: y, start_x: x, len: end - x, start_col });
                     }
 								}
             }
@@ -621,7 +963,7 @@ impl Editor {
 
 						let max = self.buffer.lines
 								.get(self.buffer.cursor_y)
-								.map(|l| l.text.chars().count().saturating_sub(self.viewport_width.max(1)))
+								.map(|l| text_width(&l.text, self.tab_width()).saturating_sub(self.viewport_width.max(1)))
 								.unwrap_or(0);
 
             self.scroll_x = (self.scroll_x + 4).min(max);
@@ -661,7 +1003,7 @@ impl Editor {
 						ISearchDir::Backward => MiniBufferMode::ISearchBackward,
 				};
 
-				self.push_event(EditorEvent::ISearchStarted { dir });
+				self.emit(EditorEvent::ISearchStarted { dir });
 				self.minibuffer.activate(prompt, mode);
 				self.mode = InputMode::MiniBuffer;
     }
@@ -707,7 +1049,7 @@ impl Editor {
 						self.buffer.cursor_x,
 						self.buffer.cursor_y,
 				));
-				self.push_event(EditorEvent::ISearchUpdate {
+				self.emit(EditorEvent::ISearchUpdate {
 						dir,
 						query,
 						found: found.is_some(),
@@ -760,7 +1102,7 @@ impl Editor {
 				self.minibuffer.deactivate();
 				self.mode = InputMode::Normal;
 
-				self.push_event(EditorEvent::ISearchFinished);
+				self.emit(EditorEvent::ISearchFinished);
 		}
 
 		pub fn isearch_abort(&mut self) {
@@ -771,45 +1113,57 @@ impl Editor {
 				self.minibuffer.deactivate();
 				self.mode = InputMode::Normal;
 
-				self.push_event(EditorEvent::ISearchAborted);
+				self.emit(EditorEvent::ISearchAborted);
 		}
 
 		pub fn insert_newline(&mut self) {
+				if self.barf_if_read_only() {
+						return;
+				}
 				self.buffer.insert_newline_raw();
 				self.scroll_intent = ScrollIntent::FollowCursor;
 				self.ensure_cursor_visible();
 		}
 
 		// ---- Prefix / Command Execution ----
-		pub fn run_command<F>(&mut self, lua: &Lua, name: &str, f: F)
+		pub fn run_command<F>(&mut self, name: &str, f: F)
 		where F: FnOnce(&mut Self)
 		{
-				self.hooks.run(lua, "before-command", name);
+				self.emit(EditorEvent::BeforeCommand { name: name.to_string() });
 				f(self);
-				self.hooks.run(lua, "after-command", name);
-				self.emit_cursor_moved(lua);
-				self.emit_selection_changed(lua);
+				self.clamp_cursors();
+				self.emit(EditorEvent::AfterCommand { name: name.to_string() });
+				self.emit_cursor_moved();
+				self.emit_selection_changed();
 				self.scroll_intent = ScrollIntent::FollowCursor;
 		}
 
-		pub fn execute_named(&mut self, name: &str, lua: &Lua) {
+		pub fn execute_named(&mut self, name: &str) {
 				if let Some(cmd) = self.commands.get(name) {
 						if name == "universal-argument" {
-								self.run_command(lua, name, |ed| (cmd.as_ref().run)(CommandContext { editor: ed, arg: CommandArg::None }));
-								self.process_events(lua);
+								self.run_command(name, |ed| (cmd.as_ref().run)(CommandContext { editor: ed, arg: CommandArg::None }));
 								return;
 						}
 						if let Interactive::Str { prompt } = cmd.interactive {
-								self.set_mode(lua, InputMode::MiniBuffer);
+								self.set_mode(InputMode::MiniBuffer);
 								self.pending_command = Some(name.to_string());
 								let mode = match name {
 										"save-buffer-as" => MiniBufferMode::SaveBuffer,
 										"find-file" => MiniBufferMode::FindFile,
 										"goto-line" => MiniBufferMode::GotoLine,
 										"isearch-forward" => MiniBufferMode::ISearchForward,
-										_ => MiniBufferMode::Command,
+										"execute-command" => MiniBufferMode::Command,
+										_ => MiniBufferMode::Prompt,
 								};
 								self.minibuffer.activate(prompt, mode);
+								if name == "execute-command" {
+										let names = self.command_names();
+										self.minibuffer.set_completions(names);
+								}
+								if name == "compile" {
+										let default = self.user_config.borrow().compile_command.clone();
+										self.minibuffer.push_str(&default);
+								}
 								return;
 						}
 						let arg = if cmd.modifies_prefix() {
@@ -818,70 +1172,134 @@ impl Editor {
 								self.prefix.consume().map_or(CommandArg::None, CommandArg::Int)
 						};
 
-						self.run_command(lua, name, |ed| (cmd.as_ref().run)(CommandContext { editor: ed, arg }));
+						if self.runs_at_each_cursor(name) {
+								self.run_command(name, |ed| ed.at_each_cursor(|ed| {
+										(cmd.as_ref().run)(CommandContext { editor: ed, arg: arg.clone() })
+								}));
+						} else {
+								self.run_command(name, |ed| (cmd.as_ref().run)(CommandContext { editor: ed, arg }));
+						}
+						self.ensure_cursor_visible();
+				} else if let Some(keys) = self.named_macros.get(name).cloned() {
+						let count = self.prefix.consume().unwrap_or(1).max(1) as usize;
+						self.run_macro(keys, count);
+				} else {
+						self.minibuffer.message(&format!("Unknown command: {name}"));
+				}
+		}
+
+		/// Run a command with an explicit argument, skipping its interactive spec.
+		pub fn execute_with_arg(&mut self, name: &str, arg: CommandArg) {
+				if let Some(cmd) = self.commands.get(name) {
+						self.run_command(name, |ed| (cmd.as_ref().run)(CommandContext { editor: ed, arg }));
 						self.ensure_cursor_visible();
 				} else {
 						self.minibuffer.message(&format!("Unknown command: {name}"));
 				}
 		}
 
+		/// Prompt in the minibuffer and pass the submitted input to `then`.
+		/// `initial` pre-fills the input.
+		pub fn read_from_minibuffer<F>(&mut self, prompt: &str, initial: &str, then: F)
+		where F: FnOnce(&mut Editor, String) + 'static
+		{
+				self.set_mode(InputMode::MiniBuffer);
+				self.minibuffer.activate(prompt, MiniBufferMode::Prompt);
+				self.minibuffer.push_str(initial);
+				self.minibuffer_action = MiniBufferAction::Read(Box::new(then));
+		}
+
+		/// Like `read_from_minibuffer`, with fuzzy completion over
+		/// `candidates`. Submitting input that is not a candidate picks the
+		/// best match.
+		pub fn completing_read<F>(&mut self, prompt: &str, candidates: Vec<String>, then: F)
+		where F: FnOnce(&mut Editor, String) + 'static
+		{
+				self.read_from_minibuffer(prompt, "", then);
+				self.minibuffer.set_completions(candidates);
+		}
+
+		/// Ask a y-or-n question; `then` runs only on "y" / "yes".
+		pub fn confirm<F>(&mut self, question: &str, then: F)
+		where F: FnOnce(&mut Editor) + 'static
+		{
+				self.read_from_minibuffer(&format!("{question} (y or n) "), "", move |ed, answer| {
+						match answer.trim() {
+								"y" | "yes" => then(ed),
+								_ => ed.minibuffer.message("Cancelled"),
+						}
+				});
+		}
+
+		/// Leave the minibuffer without submitting.
+		pub fn abort_minibuffer(&mut self) {
+				self.minibuffer.deactivate();
+				self.minibuffer_action = MiniBufferAction::None;
+				self.pending_command = None;
+				self.set_mode(InputMode::Normal);
+		}
+
 		pub fn push_kill(&mut self, text: String) {
 				self.kill_buffer = Some(text);
 		}
 
-		pub fn execute_minibuffer(&mut self, lua: &Lua) {
-				// let input = self.minibuffer.get().to_string();
-				// self.minibuffer.deactivate();
-				// self.set_mode(lua, InputMode::Normal);
-
-				// match std::mem::replace(&mut self.minibuffer_action, MiniBufferAction::None) {
-				// 		MiniBufferAction::Command(cmd) => {
-				// 				self.execute_named(&cmd, lua);
-				// 				return;
-				// 		}
-				// 		MiniBufferAction::BeforeExitConfirm => {
-				// 				match input.as_str() {
-				// 						"y" | "Y" => {
-				// 								let _ = self.buffer.save();
-				// 								self.should_quit = true;
-				// 						}
-				// 						"n" | "N" => {
-				// 								self.should_quit = true;
-				// 						}
-				// 						_ => {
-				// 								self.minibuffer.message("Answer y or n");
-				// 						}
-				// 				}
-				// 				return;
-				// 		}
-						
-				// 		MiniBufferAction::None => {}
-				// }
-				
+		pub fn execute_minibuffer(&mut self) {
 				let mode = self.minibuffer.mode();
 				let input = match mode {
 						MiniBufferMode::Command => self.minibuffer.get().strip_prefix("M-x ").unwrap_or("").trim(),
 						MiniBufferMode::FindFile => self.minibuffer.get().strip_prefix("Find file: ").unwrap_or("").trim(),
 						MiniBufferMode::SaveBuffer => self.minibuffer.get().strip_prefix("Save buffer as: ").unwrap_or("").trim(),
 						MiniBufferMode::GotoLine => self.minibuffer.get().strip_prefix("Goto line: ").unwrap_or("").trim(),
-						
+						MiniBufferMode::Prompt => self.minibuffer.input().trim(),
 						_ => "",
 				}.to_string();
+
+				// A prompt opened from Lua (`minibuffer_prompt`) hands its input
+				// to the requested command.
+				let action = std::mem::replace(&mut self.minibuffer_action, MiniBufferAction::None);
+				let input = match (&action, mode) {
+						(MiniBufferAction::Command(_), MiniBufferMode::Command) => self.minibuffer.input().trim().to_string(),
+						_ => input,
+				};
+				let input = self.minibuffer.completion_choice(&input).unwrap_or(input);
+				if !matches!(mode, MiniBufferMode::ISearchForward | MiniBufferMode::ISearchBackward) {
+						self.minibuffer.add_history(&input);
+				}
+
 				self.minibuffer.deactivate();
-				self.set_mode(lua, InputMode::Normal);
+				self.set_mode(InputMode::Normal);
+				self.emit(EditorEvent::MiniBufferSubmit(input.clone()));
+
+				match action {
+						MiniBufferAction::Command(cmd) => {
+								self.execute_with_arg(&cmd, CommandArg::Str(input));
+								return;
+						}
+						MiniBufferAction::Read(then) => {
+								then(self, input);
+								self.ensure_cursor_visible();
+								return;
+						}
+						_ => {}
+				}
+
 				match mode { 
 						MiniBufferMode::FindFile => {
-								match self.buffer.open_file(input.clone().into()) {
-										Ok(_) => { self.minibuffer.message("Opened file"); self.emit_buffer_changed(lua, "open-file"); }
+								match self.visit_file(input.clone().into()) {
+										Ok(_) => {
+												self.minibuffer.message("Opened file");
+												self.emit_buffer_changed("open-file");
+										}
 										Err(e) => self.minibuffer.message(&format!("Open failed: {e}")),
 								}
 						}
 						MiniBufferMode::SaveBuffer => {
 								if input.is_empty() { self.minibuffer.message("Save failed: empty file name"); return; }
-								self.hooks.run(lua, "before-buffer-write", &input);
+								self.emit(EditorEvent::BeforeBufferWrite { path: input.clone().into() });
 								match self.buffer.save_as(input.clone().into()) {
 										Ok(_) => {
-												self.hooks.run(lua, "buffer-saved", &input);
+												self.rename_after_file(0);
+												self.emit(EditorEvent::BufferSaved { path: input.into() });
 												self.minibuffer.message("Saved buffer!");
 										}
 										Err(e) => self.minibuffer.message(&format!("Save failed: {e}")),
@@ -895,8 +1313,136 @@ impl Editor {
 						MiniBufferMode::ISearchForward => {
 								self.isearch_finish();
 						}
-						MiniBufferMode::Command => self.execute_named(&input, lua),
+						MiniBufferMode::Command => self.execute_named(&input),
+						MiniBufferMode::Prompt => {
+								if let Some(name) = self.pending_command.take() {
+										self.execute_with_arg(&name, CommandArg::Str(input));
+								}
+						}
 						_ => {}
 				}
 		}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::builtins::register_builtins;
+
+    fn editor() -> Editor {
+        let mut registry = CommandRegistry::new();
+        register_builtins(&mut registry);
+        Editor::new(registry, Rc::new(RefCell::new(KeyMap::new())), Rc::new(RefCell::new(UserConfig::default())))
+    }
+
+    fn answer(ed: &mut Editor, input: &str) {
+        assert_eq!(ed.mode, InputMode::MiniBuffer);
+        ed.minibuffer.push_str(input);
+        ed.execute_minibuffer();
+    }
+
+    #[test]
+    fn kill_buffer_asks_before_dropping_changes() {
+        let dir = std::env::temp_dir().join(format!("remux-kill-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "text").unwrap();
+        let mut ed = editor();
+        ed.visit_file(path).unwrap();
+        ed.buffer.insert_text_at(0, 0, "more ");
+
+        ed.execute_named("kill-buffer");
+        answer(&mut ed, "n");
+        assert_eq!(ed.buffer.file_name(), "notes.txt");
+
+        ed.execute_named("kill-buffer");
+        answer(&mut ed, "y");
+        assert_ne!(ed.buffer.file_name(), "notes.txt");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn kill_buffer_asks_after_typed_changes() {
+        let dir = std::env::temp_dir().join(format!("remux-kill-typed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "text").unwrap();
+        let mut ed = editor();
+        ed.visit_file(path).unwrap();
+        ed.handle_key(crate::kmacro::parse_key("x").unwrap());
+
+        ed.execute_named("kill-buffer");
+        answer(&mut ed, "n");
+        assert_eq!(ed.buffer.text(), "xtext");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn kill_buffer_without_changes_does_not_ask() {
+        let mut ed = editor();
+        ed.buffer_mut_or_create("*other*");
+        ed.switch_to_buffer("*other*");
+        ed.buffer.insert_text_at(0, 0, "scratch work");
+        ed.execute_named("kill-buffer");
+        assert_eq!(ed.mode, InputMode::Normal);
+        assert_ne!(ed.buffer.file_name(), "*other*");
+    }
+
+    #[test]
+    fn unique_names_count_from_two() {
+        assert_eq!(unique_name("mod.rs", |_| false), "mod.rs");
+        let taken = ["mod.rs", "mod.rs<2>"];
+        assert_eq!(unique_name("mod.rs", |n| taken.contains(&n)), "mod.rs<3>");
+    }
+
+    /// `a/mod.rs` and `b/mod.rs` in a fresh directory.
+    fn two_mod_rs(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("remux-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (a, b) = (dir.join("a/mod.rs"), dir.join("b/mod.rs"));
+        for (path, text) in [(&a, "first"), (&b, "second")] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        (a, b)
+    }
+
+    #[test]
+    fn files_with_the_same_name_get_their_own_buffers() {
+        let (a, b) = two_mod_rs("names");
+        let mut ed = editor();
+        ed.visit_file(a.clone()).unwrap();
+        ed.visit_file(b.clone()).unwrap();
+        assert_eq!(ed.buffer.file_name(), "mod.rs<2>");
+
+        ed.switch_to_buffer("mod.rs");
+        assert_eq!(ed.buffer.text(), "first");
+        ed.switch_to_buffer("mod.rs<2>");
+        assert_eq!(ed.buffer.text(), "second");
+        assert_eq!(ed.find_buffer("mod.rs").map(Buffer::text).as_deref(), Some("first"));
+
+        // Saved under a name of its own, it is called that.
+        let c = b.with_file_name("lib.rs");
+        ed.execute_named("save-buffer-as");
+        answer(&mut ed, c.to_str().unwrap());
+        assert_eq!(ed.buffer.file_name(), "lib.rs");
+        let _ = std::fs::remove_dir_all(a.parent().unwrap().parent().unwrap());
+    }
+
+    #[test]
+    fn position_register_survives_its_name_being_reused() {
+        let (a, b) = two_mod_rs("registers");
+        let mut ed = editor();
+        ed.visit_file(a.clone()).unwrap();
+        ed.buffer.cursor_x = 3;
+        ed.point_to_register('r');
+        ed.kill_current_buffer();
+        ed.visit_file(b.clone()).unwrap();
+        assert_eq!(ed.buffer.file_name(), "mod.rs");
+
+        ed.jump_to_register('r');
+        assert_eq!(ed.buffer.text(), "first");
+        assert_eq!(ed.buffer.cursor_x, 3);
+        let _ = std::fs::remove_dir_all(a.parent().unwrap().parent().unwrap());
+    }
+}
@@ -1,15 +1,211 @@
 // core/src/editor/events.rs
+//
+// The event catalogue.
+//
+// Every event goes through the `EventBus` (see `bus.rs`). Events are queued
+// when they are emitted and dispatched in emission order by
+// `Editor::process_events`, first to Rust subscribers and then to the Lua
+// hooks registered under the event's hook name(s). The dispatched events are
+// also handed back to the frontend, so frontends and plugins observe exactly
+// the same stream.
+//
+// Requests are handled by the editor itself and are not dispatched to hooks:
+//
+//   ExecuteCommand      run a command by name
+//   Message             show a message in the minibuffer
+//   OpenFile            open a file into the current buffer
+//   AddHook             register a Lua hook
+//   MiniBufferPrompt    open the minibuffer and run a command on submit
+//
+// Notifications:
+//
+//   Event               Hook name(s)                   Lua argument
+//   -----------------   ----------------------------   ---------------------------
+//   Tick                after-init                     ""
+//   InitFinished        after-init-once                ""
+//   BeforeExit          before-exit                    ""
+//   BeforeCommand       before-command                 command name
+//   AfterCommand        after-command                  command name
+//   BufferLoaded        buffer-loaded                  file path
+//   BeforeBufferWrite   before-buffer-write            file path
+//   BufferSaved         buffer-saved                   file path
+//   BufferChanged       buffer-changed                 reason
+//   CursorMoved         cursor-moved                   "x,y"
+//   BeforeInsertChar    before-insert-char             inserted char
+//   AfterInsertChar     after-insert-char              inserted char
+//   SelectionChanged    selection-changed              { start, end } | { cleared }
+//   ModeChanged         mode-changed                   mode name
+//   MiniBufferSubmit    minibuffer-submit              submitted input
+//   ISearchStarted      isearch-started                ""
+//   ISearchUpdate       isearch-update                 { dir, query, found, cursor }
+//   ISearchFinished     isearch-finish, isearch-end    ""
+//   ISearchAborted      isearch-abort, isearch-end     ""
+//   ProcessOutput       process-output                 { id, stream, text }
+//   ProcessExited       process-exit                   { id, code }
+//   Custom              <name>                         <arg>
+
+use std::path::PathBuf;
+use mlua::{Lua, Value, IntoLua};
+use crate::editor::editor::{InputMode, ISearchDir, Selection};
+use crate::process::{ProcessId, ProcessStream};
 
 #[derive(Debug)]
 pub enum EditorEvent {
+    // ---- Requests ----
+    ExecuteCommand(String),
+    Message(String),
+    OpenFile(String),
+    AddHook { name: String, func: mlua::RegistryKey },
+    MiniBufferPrompt { prompt: String, command: String },
+
+    // ---- Lifecycle ----
+    Tick,
+    InitFinished,
+    BeforeExit,
+
+    // ---- Commands ----
     BeforeCommand { name: String },
     AfterCommand { name: String },
-    BufferChanged,
-    MinibufferOpened,
-    MinibufferClosed,
-    Message(String),
-    Custom(String),
+
+    // ---- Buffers ----
+    BufferLoaded { path: PathBuf },
+    BeforeBufferWrite { path: PathBuf },
+    BufferSaved { path: PathBuf },
+    BufferChanged { reason: &'static str },
+
+    // ---- Cursor & input ----
+    CursorMoved { x: usize, y: usize },
+    BeforeInsertChar(char),
+    AfterInsertChar(char),
+    SelectionChanged(Option<Selection>),
+    ModeChanged(InputMode),
+    MiniBufferSubmit(String),
+
+    // ---- Incremental search ----
+    ISearchStarted { dir: ISearchDir },
+    ISearchUpdate {
+        dir: ISearchDir,
+        query: String,
+        found: bool,
+        cursor: (usize, usize),
+    },
+    ISearchFinished,
+    ISearchAborted,
+
+    // ---- External processes ----
+    ProcessOutput { id: ProcessId, stream: ProcessStream, text: String },
+    ProcessExited { id: ProcessId, code: Option<i32> },
+
+    // ---- Plugins ----
+    Custom { name: String, arg: String },
 }
+
+impl EditorEvent {
+    /// Requests are consumed by the editor and never reach hooks.
+    pub fn is_request(&self) -> bool {
+        matches!(
+            self,
+            EditorEvent::ExecuteCommand(_)
+                | EditorEvent::Message(_)
+                | EditorEvent::OpenFile(_)
+                | EditorEvent::AddHook { .. }
+                | EditorEvent::MiniBufferPrompt { .. }
+        )
+    }
+
+    /// Hook names this event is dispatched under, in order.
+    pub fn hook_names(&self) -> Vec<&str> {
+        let names: &[&'static str] = match self {
+            EditorEvent::Custom { name, .. } => return vec![name.as_str()],
+            EditorEvent::Tick => &["after-init"],
+            EditorEvent::InitFinished => &["after-init-once"],
+            EditorEvent::BeforeExit => &["before-exit"],
+            EditorEvent::BeforeCommand { .. } => &["before-command"],
+            EditorEvent::AfterCommand { .. } => &["after-command"],
+            EditorEvent::BufferLoaded { .. } => &["buffer-loaded"],
+            EditorEvent::BeforeBufferWrite { .. } => &["before-buffer-write"],
+            EditorEvent::BufferSaved { .. } => &["buffer-saved"],
+            EditorEvent::BufferChanged { .. } => &["buffer-changed"],
+            EditorEvent::CursorMoved { .. } => &["cursor-moved"],
+            EditorEvent::BeforeInsertChar(_) => &["before-insert-char"],
+            EditorEvent::AfterInsertChar(_) => &["after-insert-char"],
+            EditorEvent::SelectionChanged(_) => &["selection-changed"],
+            EditorEvent::ModeChanged(_) => &["mode-changed"],
+            EditorEvent::MiniBufferSubmit(_) => &["minibuffer-submit"],
+            EditorEvent::ISearchStarted { .. } => &["isearch-started"],
+            EditorEvent::ISearchUpdate { .. } => &["isearch-update"],
+            EditorEvent::ISearchFinished => &["isearch-finish", "isearch-end"],
+            EditorEvent::ISearchAborted => &["isearch-abort", "isearch-end"],
+            EditorEvent::ProcessOutput { .. } => &["process-output"],
+            EditorEvent::ProcessExited { .. } => &["process-exit"],
+            _ => &[],
+        };
+        names.to_vec()
+    }
+
+    /// Argument passed to Lua hooks.
+    pub fn to_lua<'lua>(&self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
+        match self {
+            EditorEvent::BeforeCommand { name } | EditorEvent::AfterCommand { name } =>
+                name.as_str().into_lua(lua),
+            EditorEvent::BufferLoaded { path }
+            | EditorEvent::BeforeBufferWrite { path }
+            | EditorEvent::BufferSaved { path } =>
+                path.to_string_lossy().into_lua(lua),
+            EditorEvent::BufferChanged { reason } => (*reason).into_lua(lua),
+            EditorEvent::CursorMoved { x, y } => format!("{},{}", x, y).into_lua(lua),
+            EditorEvent::BeforeInsertChar(ch) | EditorEvent::AfterInsertChar(ch) =>
+                ch.to_string().into_lua(lua),
+            EditorEvent::ModeChanged(mode) => mode.as_str().into_lua(lua),
+            EditorEvent::MiniBufferSubmit(input) => input.as_str().into_lua(lua),
+            EditorEvent::Custom { arg, .. } => arg.as_str().into_lua(lua),
+            EditorEvent::SelectionChanged(sel) => {
+                let tbl = lua.create_table()?;
+                match sel {
+                    Some(sel) => {
+                        tbl.set("start", point(lua, sel.start)?)?;
+                        tbl.set("end", point(lua, sel.end)?)?;
+                    }
+                    None => tbl.set("cleared", true)?,
+                }
+                Ok(Value::Table(tbl))
+            }
+            EditorEvent::ISearchUpdate { dir, query, found, cursor } => {
+                let tbl = lua.create_table()?;
+                tbl.set("dir", match dir {
+                    ISearchDir::Forward => "forward",
+                    ISearchDir::Backward => "backward",
+                })?;
+                tbl.set("query", query.as_str())?;
+                tbl.set("found", *found)?;
+                tbl.set("cursor", point(lua, *cursor)?)?;
+                Ok(Value::Table(tbl))
+            }
+            EditorEvent::ProcessOutput { id, stream, text } => {
+                let tbl = lua.create_table()?;
+                tbl.set("id", *id)?;
+                tbl.set("stream", stream.as_str())?;
+                tbl.set("text", text.as_str())?;
+                Ok(Value::Table(tbl))
+            }
+            EditorEvent::ProcessExited { id, code } => {
+                let tbl = lua.create_table()?;
+                tbl.set("id", *id)?;
+                tbl.set("code", *code)?;
+                Ok(Value::Table(tbl))
+            }
+            _ => "".into_lua(lua),
+        }
+    }
+}
+
+fn point(lua: &Lua, (x, y): (usize, usize)) -> mlua::Result<mlua::Table<'_>> {
+    let t = lua.create_table()?;
+    t.set("x", x)?;
+    t.set("y", y)?;
+    Ok(t)
+}
+
 pub trait EditorHook {
     fn on_event(&mut self, event: &EditorEvent);
 }
@@ -43,6 +43,24 @@ impl HookRegistry {
 						}
 				}
 		}
+    pub fn has(&self, name: &str) -> bool {
+        self.hooks.get(name).is_some_and(|funcs| !funcs.is_empty())
+    }
+
+    /// Run the hooks of `name`, returning the errors of those that failed.
+    pub fn run_value<'lua>(&self, lua: &'lua Lua, name: &str, arg: mlua::Value<'lua>) -> Vec<mlua::Error> {
+        let mut errors = Vec::new();
+        if let Some(funcs) = self.hooks.get(name) {
+            for key in funcs {
+                if let Ok(func) = lua.registry_value::<Function>(key)
+                    && let Err(e) = func.call::<_, ()>(arg.clone()) {
+                    errors.push(e);
+                }
+            }
+        }
+        errors
+    }
+
     pub fn run(&self, lua: &Lua, name: &str, arg: &str) {
         if let Some(funcs) = self.hooks.get(name) {
             for key in funcs {
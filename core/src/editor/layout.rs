@@ -1,5 +1,151 @@
+// core/src/editor/layout.rs
+//
+// How buffer text maps to screen cells. Everything that turns char indices
+// into columns (wrapping, cursor placement, horizontal scrolling, drawing)
+// goes through these functions, so tabs and wide chars are measured the
+// same way everywhere. A tab reaches the next multiple of the tab width,
+// counted from the start of the line.
+
+use unicode_width::UnicodeWidthChar;
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LineWrapMode {
     Truncate,
     Wrap,
 }
+
+/// How the gutter numbers lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineNumberStyle {
+    Absolute,
+    /// Distance from the cursor line.
+    Relative,
+    /// Relative, with the cursor line absolute.
+    Hybrid,
+}
+
+impl LineNumberStyle {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "absolute" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+}
+
+/// Cells `c` takes when it starts at column `col`. Control and combining
+/// chars take none.
+pub fn char_width(c: char, col: usize, tab_width: usize) -> usize {
+    if c == '\t' {
+        let tab = tab_width.max(1);
+        tab - col % tab
+    } else {
+        UnicodeWidthChar::width(c).unwrap_or(0)
+    }
+}
+
+/// Column where char `x` of `text` starts; past the end, the width of the
+/// whole line.
+pub fn column_of(text: &str, x: usize, tab_width: usize) -> usize {
+    let mut col = 0;
+    for c in text.chars().take(x) {
+        col += char_width(c, col, tab_width);
+    }
+    col
+}
+
+pub fn text_width(text: &str, tab_width: usize) -> usize {
+    column_of(text, usize::MAX, tab_width)
+}
+
+/// Index of the char covering column `col`, or the char count when the
+/// line is shorter.
+pub fn char_at_column(text: &str, col: usize, tab_width: usize) -> usize {
+    let mut cur = 0;
+    for (i, c) in text.chars().enumerate() {
+        cur += char_width(c, cur, tab_width);
+        if cur > col {
+            return i;
+        }
+    }
+    text.chars().count()
+}
+
+/// First char of each screen row when `text` is wrapped at `width`
+/// columns. A char that does not fit in the rest of a row starts the next
+/// one. There is always at least one row.
+pub fn wrap_starts(text: &str, width: usize, tab_width: usize) -> Vec<usize> {
+    let width = width.max(1);
+    let mut starts = vec![0];
+    let (mut col, mut row_col) = (0, 0);
+    for (i, c) in text.chars().enumerate() {
+        let w = char_width(c, col, tab_width);
+        if col + w - row_col > width && col > row_col {
+            starts.push(i);
+            row_col = col;
+        }
+        col += w;
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_reach_the_next_stop() {
+        assert_eq!(char_width('\t', 0, 4), 4);
+        assert_eq!(char_width('\t', 1, 4), 3);
+        assert_eq!(char_width('\t', 3, 8), 5);
+        assert_eq!(char_width('\t', 5, 1), 1);
+        assert_eq!(char_width('\t', 5, 0), 1);
+        assert_eq!(column_of("a\tb", 2, 4), 4);
+        assert_eq!(column_of("a\tb", 2, 8), 8);
+        assert_eq!(column_of("ab\t\tc", 4, 4), 8);
+        assert_eq!(text_width("\t\t", 3), 6);
+    }
+
+    #[test]
+    fn column_inside_a_tab_is_the_tab() {
+        assert_eq!(char_at_column("a\tb", 0, 4), 0);
+        assert_eq!(char_at_column("a\tb", 2, 4), 1);
+        assert_eq!(char_at_column("a\tb", 3, 4), 1);
+        assert_eq!(char_at_column("a\tb", 4, 4), 2);
+        assert_eq!(char_at_column("a\tb", 6, 8), 1);
+        assert_eq!(char_at_column("a\tb", 10, 4), 3);
+    }
+
+    #[test]
+    fn wide_and_zero_width_chars() {
+        assert_eq!(char_width('漢', 0, 4), 2);
+        assert_eq!(column_of("漢字a", 2, 4), 4);
+        assert_eq!(text_width("漢字a", 4), 5);
+        assert_eq!(text_width("e\u{301}x", 4), 2);
+        assert_eq!(char_at_column("漢字a", 1, 4), 0);
+        assert_eq!(char_at_column("漢字a", 2, 4), 1);
+        assert_eq!(char_at_column("漢字a", 4, 4), 2);
+        assert_eq!(char_at_column("漢字a", 5, 4), 3);
+    }
+
+    #[test]
+    fn wrap_rows() {
+        assert_eq!(wrap_starts("", 5, 4), [0]);
+        assert_eq!(wrap_starts("abcdef", 3, 4), [0, 3]);
+        assert_eq!(wrap_starts("ab\tc", 4, 4), [0, 3]);
+        assert_eq!(wrap_starts("ab\tc", 4, 8), [0, 2, 3]);
+    }
+
+    #[test]
+    fn wide_char_at_the_wrap_boundary_moves_to_the_next_row() {
+        assert_eq!(wrap_starts("ab漢c", 3, 4), [0, 2]);
+        assert_eq!(wrap_starts("abc漢d", 4, 4), [0, 3]);
+        assert_eq!(wrap_starts("漢字漢", 4, 4), [0, 2]);
+        // Too wide for any row: each still gets one of its own.
+        assert_eq!(wrap_starts("漢字", 1, 4), [0, 1]);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod editor;
 pub mod events;
+pub mod bus;
 pub mod hooks;
 pub mod layout;
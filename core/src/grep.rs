@@ -0,0 +1,424 @@
+// core/src/grep.rs
+//
+// Project-wide search. The search runs on a worker thread, either with the
+// built-in walker or by shelling out to ripgrep, and sends one message per
+// file with matches; the main loop drains them with `Editor::poll_grep` into
+// the `*grep*` buffer. Patterns are fixed strings with smart case: a pattern
+// without capitals matches case-insensitively (like `rg -F -S`).
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use crate::compile::{Location, LocationList};
+use crate::editor::editor::{Editor, KeyMap, Modifiers};
+use crate::walk::walk_files;
+
+pub const GREP_BUFFER: &str = "*grep*";
+pub const GREP_MODE: &str = "grep";
+
+/// Lines longer than this are cut in the results buffer.
+const MAX_LINE_CHARS: usize = 300;
+/// Files that look binary (a NUL in this prefix) are skipped.
+const BINARY_PROBE: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrepProgram {
+    /// ripgrep when it is on `PATH`, the built-in search otherwise.
+    Auto,
+    Builtin,
+    Ripgrep,
+}
+
+impl GrepProgram {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(GrepProgram::Auto),
+            "builtin" => Some(GrepProgram::Builtin),
+            "rg" | "ripgrep" => Some(GrepProgram::Ripgrep),
+            _ => None,
+        }
+    }
+}
+
+pub struct GrepQuery {
+    pub pattern: String,
+    pub dir: PathBuf,
+    /// Lines of context around every match.
+    pub context: usize,
+    pub program: GrepProgram,
+}
+
+pub struct GrepLine {
+    /// 1-based.
+    pub line: usize,
+    /// 1-based column of the match, `None` for context lines.
+    pub col: Option<usize>,
+    pub text: String,
+}
+
+pub enum GrepMsg {
+    /// All matches of one file, in line order, with their context.
+    File { path: String, lines: Vec<GrepLine> },
+    Done { error: Option<String> },
+}
+
+/// A running search. Dropping it stops the worker.
+pub struct GrepSearch {
+    rx: Receiver<GrepMsg>,
+    cancel: Arc<AtomicBool>,
+    pub files: usize,
+    pub matches: usize,
+}
+
+impl GrepSearch {
+    pub fn start(query: GrepQuery) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+        thread::spawn(move || {
+            let result = match query.program {
+                GrepProgram::Ripgrep => search_rg(&query, &tx, &flag),
+                GrepProgram::Builtin => search_builtin(&query, &tx, &flag),
+                GrepProgram::Auto if rg_available() => search_rg(&query, &tx, &flag),
+                GrepProgram::Auto => search_builtin(&query, &tx, &flag),
+            };
+            let _ = tx.send(GrepMsg::Done { error: result.err() });
+        });
+        Self { rx, cancel, files: 0, matches: 0 }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Messages received since the last call. Never blocks.
+    pub fn poll(&mut self) -> Vec<GrepMsg> {
+        self.rx.try_iter().collect()
+    }
+}
+
+impl Drop for GrepSearch {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// ---- Matching ----
+struct Matcher {
+    needle: String,
+    ignore_case: bool,
+}
+
+impl Matcher {
+    fn new(pattern: &str) -> Self {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        let needle = if ignore_case { pattern.to_lowercase() } else { pattern.to_string() };
+        Self { needle, ignore_case }
+    }
+
+    /// 0-based char column of the first match.
+    fn find(&self, line: &str) -> Option<usize> {
+        if self.ignore_case {
+            let lower = line.to_lowercase();
+            lower.find(&self.needle).map(|b| lower[..b].chars().count())
+        } else {
+            line.find(&self.needle).map(|b| line[..b].chars().count())
+        }
+    }
+}
+
+fn clip(text: &str) -> String {
+    match text.char_indices().nth(MAX_LINE_CHARS) {
+        Some((b, _)) => format!("{} ...", &text[..b]),
+        None => text.to_string(),
+    }
+}
+
+/// Matching lines of `text` plus `context` lines around each of them.
+fn match_lines(text: &str, matcher: &Matcher, context: usize) -> Vec<GrepLine> {
+    let lines: Vec<&str> = text.lines().collect();
+    let hits: Vec<(usize, usize)> = lines.iter().enumerate()
+        .filter_map(|(i, l)| matcher.find(l).map(|c| (i, c)))
+        .collect();
+
+    let mut out = Vec::new();
+    let mut next = 0; // first line not yet emitted
+    for (idx, &(i, _)) in hits.iter().enumerate() {
+        let from = i.saturating_sub(context).max(next);
+        let to = (i + context).min(lines.len() - 1);
+        for (y, text) in lines.iter().enumerate().take(to + 1).skip(from) {
+            let col = hits[idx..].iter().find(|(h, _)| *h == y).map(|(_, c)| c + 1);
+            out.push(GrepLine { line: y + 1, col, text: clip(text) });
+            next = y + 1;
+        }
+    }
+    out
+}
+
+
+// ---- Built-in search ----
+fn search_builtin(query: &GrepQuery, tx: &Sender<GrepMsg>, cancel: &AtomicBool) -> Result<(), String> {
+    if !query.dir.is_dir() {
+        return Err(format!("{} is not a directory", query.dir.display()));
+    }
+    let matcher = Matcher::new(&query.pattern);
+    walk_files(&query.dir, cancel, &mut |path| {
+        let Ok(bytes) = fs::read(path) else { return true };
+        if bytes[..bytes.len().min(BINARY_PROBE)].contains(&0) {
+            return true;
+        }
+        let text = String::from_utf8_lossy(&bytes);
+        let lines = match_lines(&text, &matcher, query.context);
+        if lines.is_empty() {
+            return true;
+        }
+        let rel = path.strip_prefix(&query.dir).unwrap_or(path);
+        let msg = GrepMsg::File { path: rel.to_string_lossy().into_owned(), lines };
+        tx.send(msg).is_ok()
+    });
+    Ok(())
+}
+
+// ---- ripgrep ----
+fn rg_available() -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| dir.join("rg").is_file())
+    })
+}
+
+/// Runs `rg -F -S --null -n --column -C <n>` and regroups its output per
+/// file. Lines look like `path\0LINE:COL:text` for matches and
+/// `path\0LINE-text` for context. An error is returned after whatever
+/// matches were found: rg reports unreadable files but searches the rest.
+fn search_rg(query: &GrepQuery, tx: &Sender<GrepMsg>, cancel: &AtomicBool) -> Result<(), String> {
+    let mut child = Command::new("rg")
+        .args(["--fixed-strings", "--smart-case", "--null", "--line-number", "--column"])
+        .args(["--no-heading", "--with-filename", "--color", "never"])
+        .arg("--context").arg(query.context.to_string())
+        .arg("--").arg(&query.pattern).arg(".")
+        .current_dir(&query.dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run rg: {e}"))?;
+
+    let stdout = child.stdout.take().ok_or("rg: no output")?;
+    // Read on its own so that rg never waits on a full stderr pipe.
+    let stderr = child.stderr.take();
+    let errors = thread::spawn(move || {
+        let mut err = String::new();
+        if let Some(mut e) = stderr {
+            let _ = e.read_to_string(&mut err);
+        }
+        err
+    });
+    let mut current: Option<(String, Vec<GrepLine>)> = None;
+
+    for raw in BufReader::new(stdout).split(b'\n') {
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            break;
+        }
+        let Ok(raw) = raw else { break };
+        let line = String::from_utf8_lossy(&raw);
+        let Some((path, rest)) = line.split_once('\0') else { continue };
+        let path = path.strip_prefix("./").unwrap_or(path);
+        let Some(entry) = parse_rg_line(rest) else { continue };
+
+        match &mut current {
+            Some((p, lines)) if p == path => lines.push(entry),
+            _ => {
+                if let Some((p, lines)) = current.take() {
+                    let _ = tx.send(GrepMsg::File { path: p, lines });
+                }
+                current = Some((path.to_string(), vec![entry]));
+            }
+        }
+    }
+    if let Some((path, lines)) = current {
+        let _ = tx.send(GrepMsg::File { path, lines });
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    let err = errors.join().unwrap_or_default();
+    // 1 means "no matches", 2 an error, with or without matches.
+    if status.code() == Some(2) {
+        return Err(err.lines().next().unwrap_or("rg failed").to_string());
+    }
+    Ok(())
+}
+
+fn parse_rg_line(rest: &str) -> Option<GrepLine> {
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let line: usize = rest[..digits].parse().ok()?;
+    let after = &rest[digits..];
+    if let Some(m) = after.strip_prefix(':') {
+        let (col, text) = m.split_once(':')?;
+        // rg counts bytes; locations count chars.
+        let col = col.parse::<usize>().ok()
+            .map(|b| text.char_indices().take_while(|(i, _)| *i + 1 < b).count() + 1);
+        return Some(GrepLine { line, col, text: clip(text) });
+    }
+    let text = after.strip_prefix('-')?;
+    Some(GrepLine { line, col: None, text: clip(text) })
+}
+
+/// Default bindings for grep result buffers.
+pub fn grep_keymap() -> KeyMap {
+    let mut map = KeyMap::new();
+    map.bind(Modifiers::none(), '\n', "compile-goto-error".into());
+    map.bind(Modifiers::none(), 'n', "next-error".into());
+    map.bind(Modifiers::none(), 'p', "previous-error".into());
+    map
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Search `dir` for `pattern` in the background; results stream into
+    /// `*grep*`.
+    pub fn grep(&mut self, pattern: &str, dir: PathBuf) {
+        if pattern.is_empty() {
+            self.minibuffer.message("Empty search pattern");
+            return;
+        }
+        let dir = dir.canonicalize().unwrap_or(dir);
+        let (program, context) = {
+            let cfg = self.user_config.borrow();
+            (cfg.grep_program, cfg.grep_context)
+        };
+        // Replacing the search drops (and so cancels) the previous one.
+        self.grep_search = Some(GrepSearch::start(GrepQuery {
+            pattern: pattern.to_string(),
+            dir: dir.clone(),
+            context,
+            program,
+        }));
+
+        let header = format!("Grep for \"{pattern}\" in {}\n\n", dir.display());
+        let buf = self.buffer_mut_or_create(GREP_BUFFER);
+        buf.set_text_raw(&header);
+        buf.major_mode = GREP_MODE.to_string();
+
+        self.locations.insert(GREP_BUFFER.to_string(), LocationList::new(dir, pattern));
+        self.next_error_buffer = Some(GREP_BUFFER.to_string());
+        self.switch_to_buffer(GREP_BUFFER);
+    }
+
+    /// Move results of the running search into `*grep*`.
+    pub fn poll_grep(&mut self) {
+        let Some(search) = self.grep_search.as_mut() else { return };
+        let msgs = search.poll();
+        if msgs.is_empty() {
+            return;
+        }
+
+        let mut text = String::new();
+        let mut found = Vec::new();
+        let mut files = 0;
+        let mut done = None;
+        // Appended text starts on the (empty) last line.
+        let mut y = self.find_buffer_mut(GREP_BUFFER).map_or(0, |b| b.lines.len() - 1);
+
+        for msg in msgs {
+            match msg {
+                GrepMsg::File { path, lines } => {
+                    text.push_str(&path);
+                    text.push('\n');
+                    y += 1;
+                    let mut prev: Option<usize> = None;
+                    for l in lines {
+                        if prev.is_some_and(|p| l.line > p + 1) {
+                            text.push_str("--\n");
+                            y += 1;
+                        }
+                        prev = Some(l.line);
+                        let num = l.line.to_string();
+                        let sep = match l.col {
+                            Some(col) => {
+                                found.push(Location {
+                                    file: path.clone(),
+                                    line: l.line,
+                                    col: Some(col),
+                                    y,
+                                    x: 0,
+                                    len: num.len(),
+                                });
+                                ':'
+                            }
+                            None => '-',
+                        };
+                        text.push_str(&format!("{num}{sep}{}\n", l.text));
+                        y += 1;
+                    }
+                    text.push('\n');
+                    y += 1;
+                    files += 1;
+                }
+                GrepMsg::Done { error } => done = Some(error),
+            }
+        }
+
+        let Some(search) = self.grep_search.as_mut() else { return };
+        search.files += files;
+        search.matches += found.len();
+        let (files, matches) = (search.files, search.matches);
+
+        if let Some(error) = done {
+            let summary = match error {
+                Some(e) if matches > 0 => format!("Grep finished: {matches} matches in {files} files; {e}"),
+                Some(e) => format!("Grep failed: {e}"),
+                None if matches == 0 => "Grep finished with no matches".to_string(),
+                None => format!("Grep finished: {matches} matches in {files} files"),
+            };
+            text.push_str(&summary);
+            text.push('\n');
+            self.minibuffer.message(&summary);
+            self.grep_search = None;
+        }
+
+        self.buffer_mut_or_create(GREP_BUFFER).append_raw(&text);
+        if let Some(list) = self.locations.get_mut(GREP_BUFFER) {
+            for loc in found {
+                list.push(loc);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rg_columns_become_char_columns() {
+        let hit = parse_rg_line("3:10:let ö = äb;").unwrap();
+        assert_eq!((hit.line, hit.col), (3, Some(9)));
+        assert_eq!(hit.text, "let ö = äb;");
+        let hit = parse_rg_line("12:1:x").unwrap();
+        assert_eq!((hit.line, hit.col), (12, Some(1)));
+    }
+
+    #[test]
+    fn rg_context_lines_have_no_column() {
+        let hit = parse_rg_line("4-fn main() {").unwrap();
+        assert_eq!((hit.line, hit.col), (4, None));
+        assert_eq!(hit.text, "fn main() {");
+        assert!(parse_rg_line("not a line").is_none());
+    }
+
+    #[test]
+    fn builtin_columns_count_chars() {
+        let lines = match_lines("héllo\nsay Hello\nbye", &Matcher::new("hello"), 0);
+        let hits: Vec<_> = lines.iter().map(|l| (l.line, l.col)).collect();
+        assert_eq!(hits, [(2, Some(5))]);
+        let lines = match_lines("äb\nhäb", &Matcher::new("b"), 1);
+        let hits: Vec<_> = lines.iter().map(|l| (l.line, l.col)).collect();
+        assert_eq!(hits, [(1, Some(2)), (2, Some(3))]);
+    }
+}
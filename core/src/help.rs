@@ -0,0 +1,255 @@
+// core/src/help.rs
+//
+// Self-documentation. Every command carries a one-line doc (`Command::doc`
+// for the built-in ones, the third argument of `define_macro` for those
+// defined in Lua); `describe-key`, `describe-command` and
+// `describe-bindings` show it in `*Help*`, and `where-is` says which keys
+// run a command.
+//
+// Keymaps bind logical modifiers (`mod0`..`mod2`), so keys are described
+// through `UserConfig`: a modifier held with the key becomes `C-`, `M-`,
+// ..., a prefix modifier becomes the prefix key, as in `C-x C-f`.
+//
+// `*Help*` is read-only and in view mode: SPC pages, `q` goes back.
+
+use crate::config::UserConfig;
+use crate::editor::editor::{Editor, KeyMap, Modifiers, PhysicalModifiers};
+use crate::input::{KeyCode, KeyEvent};
+use crate::kmacro::key_name;
+use crate::view_mode::VIEW_MODE;
+
+pub const HELP_BUFFER: &str = "*Help*";
+pub const HELP_MODE: &str = "help";
+
+fn physical_prefix(mods: PhysicalModifiers) -> String {
+    let mut out = String::new();
+    for (flag, prefix) in [
+        (PhysicalModifiers::CTRL, "C-"),
+        (PhysicalModifiers::ALT, "M-"),
+        (PhysicalModifiers::SUPER, "s-"),
+        (PhysicalModifiers::SHIFT, "S-"),
+    ] {
+        if mods.contains(flag) {
+            out.push_str(prefix);
+        }
+    }
+    out
+}
+
+fn char_name(key: char) -> String {
+    match key {
+        '\n' => "RET".to_string(),
+        '\t' => "TAB".to_string(),
+        ' ' => "SPC".to_string(),
+        '\x7f' => "DEL".to_string(),
+        c => c.to_string(),
+    }
+}
+
+/// How to type `key` with the logical modifiers `mods`, e.g. `C-x C-f`.
+pub fn key_description(config: &UserConfig, mods: Modifiers, key: char) -> String {
+    let mut prefix = String::new();
+    let mut held = String::new();
+    for i in 0..3 {
+        if !mods.contains(Modifiers::from_bits_truncate(1 << i)) {
+            continue;
+        }
+        match config.prefix_keys[i] {
+            Some(pk) => prefix = format!("{}{pk} ", physical_prefix(config.prefix_masks[i])),
+            None => held.push_str(&physical_prefix(config.mod_masks[i])),
+        }
+    }
+    format!("{prefix}{held}{}", char_name(key))
+}
+
+/// The command a key runs when no keymap binds it.
+fn unbound_command(editor: &Editor, key: &KeyEvent) -> Option<&'static str> {
+    Some(match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(PhysicalModifiers::CTRL) => "kill-remux",
+        KeyCode::Enter if editor.user_config.borrow().electric_indent => "newline-and-indent",
+        KeyCode::Enter => "newline",
+        KeyCode::Tab => "indent-for-tab-command",
+        KeyCode::Backspace => "backward-delete-char",
+        KeyCode::Delete => "delete-char",
+        KeyCode::Left => "move-left",
+        KeyCode::Right => "move-right",
+        KeyCode::Up => "move-up",
+        KeyCode::Down => "move-down",
+        _ => return None,
+    })
+}
+
+/// `keymap`'s bindings as (key, command), sorted by key.
+fn bindings_of(config: &UserConfig, keymap: &KeyMap) -> Vec<(String, String)> {
+    let mut list: Vec<(String, String)> = keymap.bindings()
+        .map(|(mods, key, cmd)| (key_description(config, mods, key), cmd.clone()))
+        .collect();
+    list.sort();
+    list
+}
+
+fn format_bindings(title: &str, bindings: &[(String, String)]) -> String {
+    let width = bindings.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0).max(3);
+    let mut out = format!("{title}\n\n{:<width$}  binding\n{:<width$}  -------\n", "key", "---");
+    for (key, cmd) in bindings {
+        out.push_str(&format!("{key:<width$}  {cmd}\n"));
+    }
+    out
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// The doc of command `name`: its own, the one given in Lua, or what a
+    /// keyboard macro types.
+    pub fn command_doc(&self, name: &str) -> Option<String> {
+        if let Some(cmd) = self.commands.get(name) {
+            return Some(cmd.doc.to_string());
+        }
+        if let Some(doc) = self.command_docs.get(name) {
+            return Some(doc.clone());
+        }
+        let keys = self.named_macros.get(name)?;
+        let keys: Vec<String> = keys.iter().map(key_name).collect();
+        Some(format!("Keyboard macro: {}", keys.join(" ")))
+    }
+
+    /// Every command, Lua-defined ones included, sorted.
+    pub fn command_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.commands.names().cloned()
+            .chain(self.named_macros.keys().cloned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Keys that run `name`, global ones first; keys of mode keymaps say
+    /// which mode.
+    pub fn keys_for(&self, name: &str) -> Vec<String> {
+        let config = self.user_config.borrow();
+        let mut keys: Vec<String> = bindings_of(&config, &self.keymap.borrow()).into_iter()
+            .filter(|(_, cmd)| cmd == name)
+            .map(|(key, _)| key)
+            .collect();
+        let mut modes: Vec<&String> = self.mode_keymaps.keys().collect();
+        modes.sort();
+        for mode in modes {
+            keys.extend(bindings_of(&config, &self.mode_keymaps[mode]).into_iter()
+                .filter(|(_, cmd)| cmd == name)
+                .map(|(key, _)| format!("{key} (in {mode})")));
+        }
+        keys
+    }
+
+    /// Show `text` in `*Help*`, from the top.
+    pub fn show_help(&mut self, text: &str) {
+        let buf = self.buffer_mut_or_create(HELP_BUFFER);
+        buf.read_only = false;
+        buf.set_text_raw(text.trim_end_matches('\n'));
+        (buf.cursor_x, buf.cursor_y) = (0, 0);
+        buf.saved_scroll = (0, 0);
+        buf.major_mode = HELP_MODE.to_string();
+        buf.read_only = true;
+        buf.view_mode = Some(true);
+        if self.buffer.file_name() == HELP_BUFFER {
+            (self.scroll_x, self.scroll_y) = (0, 0);
+        }
+        self.switch_to_buffer(HELP_BUFFER);
+    }
+
+    fn command_help(&self, name: &str, doc: &str) -> String {
+        let keys = self.keys_for(name);
+        let keys = match keys.is_empty() {
+            true => "It is not bound to any key.".to_string(),
+            false => format!("It is bound to {}.", keys.join(", ")),
+        };
+        format!("{name}\n\n{doc}\n\n{keys}\n")
+    }
+
+    /// `describe-command`.
+    pub fn describe_command(&mut self, name: &str) {
+        let name = name.trim();
+        let Some(doc) = self.command_doc(name) else {
+            self.minibuffer.message(&format!("No command named {name}"));
+            return;
+        };
+        let text = self.command_help(name, &doc);
+        self.show_help(&text);
+    }
+
+    /// `where-is`: say in the echo area which keys run `name`.
+    pub fn where_is(&mut self, name: &str) {
+        let name = name.trim();
+        if self.command_doc(name).is_none() {
+            self.minibuffer.message(&format!("No command named {name}"));
+            return;
+        }
+        let keys = self.keys_for(name);
+        let msg = match keys.is_empty() {
+            true => format!("{name} is not on any key"),
+            false => format!("{name} is on {}", keys.join(", ")),
+        };
+        self.minibuffer.message(&msg);
+    }
+
+    /// `describe-key`: the next key pressed is described instead of run.
+    pub fn describe_key_start(&mut self) {
+        self.describing_key = true;
+        self.minibuffer.message("Describe key: ");
+    }
+
+    /// Describe `key`, pressed after `describe-key`. A prefix key waits for
+    /// the key after it.
+    pub(crate) fn describe_key_press(&mut self, key: KeyEvent) {
+        let mods = self.logical_modifiers(key.modifiers, key.code);
+        if self.pending_prefix.is_some() {
+            let shown = key_name(&key);
+            self.minibuffer.message(&format!("Describe key: {shown} "));
+            return;
+        }
+        self.describing_key = false;
+
+        let ch = match key.code {
+            KeyCode::Char(c) => Some(c),
+            KeyCode::Enter => Some('\n'),
+            KeyCode::Tab => Some('\t'),
+            KeyCode::Backspace => Some('\x7f'),
+            _ => None,
+        };
+        let shown = match ch {
+            Some(c) => key_description(&self.user_config.borrow(), mods, c),
+            None => key_name(&key),
+        };
+        let bound = ch.and_then(|c| self.lookup_key(mods, c));
+        let Some(name) = bound.or_else(|| unbound_command(self, &key).map(str::to_string)) else {
+            let msg = match (ch, mods.is_empty()) {
+                (Some(_), true) => format!("{shown} inserts itself"),
+                _ => format!("{shown} is undefined"),
+            };
+            self.minibuffer.message(&msg);
+            return;
+        };
+        let doc = self.command_doc(&name).unwrap_or_default();
+        let text = format!("{shown} runs the command {}", self.command_help(&name, &doc));
+        self.show_help(&text);
+    }
+
+    /// `describe-bindings`: the keymaps in effect in the current buffer,
+    /// in the order they are tried.
+    pub fn describe_bindings(&mut self) {
+        let config = self.user_config.clone();
+        let config = config.borrow();
+        let mut sections = Vec::new();
+        if self.buffer.view_mode.is_some()
+            && let Some(map) = self.mode_keymaps.get(VIEW_MODE) {
+            sections.push(format_bindings("View mode bindings:", &bindings_of(&config, map)));
+        }
+        let major = &self.buffer.major_mode;
+        if let Some(map) = self.mode_keymaps.get(major) {
+            let title = format!("Major mode bindings ({major}):");
+            sections.push(format_bindings(&title, &bindings_of(&config, map)));
+        }
+        sections.push(format_bindings("Global bindings:", &bindings_of(&config, &self.keymap.borrow())));
+        let title = format!("Key bindings in {}\n\n", self.buffer.file_name());
+        self.show_help(&(title + &sections.join("\n")));
+    }
+}
@@ -0,0 +1,545 @@
+// core/src/indent.rs
+//
+// Indentation. Columns are display columns (see `editor::layout`), so tabs
+// count to the next tab stop; whitespace is written with tabs or spaces
+// according to the buffer's `indent_tabs_mode`.
+//
+// How deep a line goes is decided by the `IndentRule` of its major mode
+// (`UserConfig::indent_rules`, falling back to the file extension). Rules
+// are data rather than code, so new languages are added from Lua with
+// `set_indent_rule`:
+//
+// - `Block`: one level deeper after a line that opens more than it closes
+//   (`{`, `then`, `do`, ...), one level shallower for a line starting with a
+//   closer (`}`, `end`).
+// - `Offside`: one level deeper after a line ending with an opener (`:`),
+//   shallower after a block-ending statement (`return`) or for a line
+//   starting with a closer (`else`).
+// - `Copy`: the previous line's indentation.
+
+use std::collections::HashMap;
+use crate::buffer::Buffer;
+use crate::editor::editor::Editor;
+use crate::editor::layout::column_of;
+use crate::lsp::language_id;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndentStyle {
+    #[default]
+    Copy,
+    Block,
+    Offside,
+}
+
+impl IndentStyle {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "copy" => Some(Self::Copy),
+            "block" => Some(Self::Block),
+            "offside" => Some(Self::Offside),
+            _ => None,
+        }
+    }
+}
+
+/// Tokens are words (`end`, `then`) or single punctuation characters.
+#[derive(Debug, Clone, Default)]
+pub struct IndentRule {
+    pub style: IndentStyle,
+    pub open: Vec<String>,
+    pub close: Vec<String>,
+    /// Close a level and open the next one (Lua's `else`).
+    pub middle: Vec<String>,
+    /// Offside only: first words of the last statement of a block.
+    pub dedent_after: Vec<String>,
+    /// Line comment prefix; the rest of the line is ignored.
+    pub comment: Option<String>,
+    /// `'` only quotes a char literal (`'{'`), and is left alone otherwise
+    /// (Rust's `'a`). When false it quotes strings, like `"`.
+    pub char_literals: bool,
+    /// Columns per level; the tab width when unset.
+    pub offset: Option<usize>,
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+impl IndentRule {
+    pub fn braces(comment: &str) -> Self {
+        Self {
+            style: IndentStyle::Block,
+            open: strings(&["{", "(", "["]),
+            close: strings(&["}", ")", "]"]),
+            comment: Some(comment.to_string()),
+            char_literals: true,
+            ..Self::default()
+        }
+    }
+
+    fn lua() -> Self {
+        Self {
+            style: IndentStyle::Block,
+            open: strings(&["{", "(", "[", "function", "then", "do", "repeat"]),
+            close: strings(&["}", ")", "]", "end", "until", "elseif"]),
+            middle: strings(&["else"]),
+            comment: Some("--".to_string()),
+            ..Self::default()
+        }
+    }
+
+    fn python() -> Self {
+        Self {
+            style: IndentStyle::Offside,
+            open: strings(&[":"]),
+            close: strings(&["else", "elif", "except", "finally"]),
+            dedent_after: strings(&["return", "pass", "break", "continue", "raise"]),
+            comment: Some("#".to_string()),
+            offset: Some(4),
+            ..Self::default()
+        }
+    }
+
+    fn yaml() -> Self {
+        Self {
+            style: IndentStyle::Offside,
+            open: strings(&[":"]),
+            comment: Some("#".to_string()),
+            offset: Some(2),
+            ..Self::default()
+        }
+    }
+
+    /// Whether a line starting with `tok` belongs one level out.
+    fn is_closer(&self, tok: &str) -> bool {
+        self.close.iter().chain(&self.middle).any(|t| t == tok)
+    }
+
+    /// Levels the line `text` opens for the lines after it.
+    fn opens(&self, tokens: &[&str]) -> isize {
+        let has = |list: &[String], tok: &str| list.iter().any(|t| t == tok);
+        match self.style {
+            IndentStyle::Copy => 0,
+            IndentStyle::Block => {
+                // Leading closers were accounted for by the line's own
+                // indentation.
+                let lead = tokens.iter().take_while(|t| self.is_closer(t)).count();
+                let mut net = tokens[..lead].iter().filter(|t| has(&self.middle, t)).count() as isize;
+                for tok in &tokens[lead..] {
+                    if has(&self.open, tok) {
+                        net += 1;
+                    } else if has(&self.close, tok) {
+                        net -= 1;
+                    }
+                }
+                (net > 0) as isize
+            }
+            IndentStyle::Offside => match (tokens.first(), tokens.last()) {
+                (_, Some(last)) if has(&self.open, last) => 1,
+                (Some(first), _) if has(&self.dedent_after, first) => -1,
+                _ => 0,
+            },
+        }
+    }
+}
+
+/// Built-in rules, by major mode.
+pub fn default_rules() -> HashMap<String, IndentRule> {
+    let mut rules = HashMap::new();
+    for mode in ["rust", "c", "cpp", "go", "javascript", "typescript", "zig", "java"] {
+        rules.insert(mode.to_string(), IndentRule::braces("//"));
+    }
+    rules.insert("lua".to_string(), IndentRule::lua());
+    rules.insert("python".to_string(), IndentRule::python());
+    rules.insert("yaml".to_string(), IndentRule::yaml());
+    rules
+}
+
+/// Length in bytes of the char literal starting `text`: `'x'`, `'\n'`,
+/// `'\u{7f}'`. `None` for a lifetime (`'a`).
+fn char_literal_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+    let (_, c) = chars.next()?;
+    let (end, close) = if c == '\\' {
+        chars.skip(1).find(|&(_, d)| d == '\'' || d.is_whitespace())?
+    } else {
+        chars.next()?
+    };
+    (close == '\'').then_some(end + 1)
+}
+
+/// Split `line` into indentation tokens, skipping quoted strings and char
+/// literals and stopping at the rule's comment.
+fn tokens<'a>(line: &'a str, rule: &IndentRule) -> Vec<&'a str> {
+    let comment = rule.comment.as_deref();
+    let mut out = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if comment.is_some_and(|p| !p.is_empty() && line[i..].starts_with(p)) {
+            break;
+        }
+        if c == '\'' && rule.char_literals {
+            if let Some(len) = char_literal_len(&line[i..]) {
+                while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+            }
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            let mut escaped = false;
+            for (_, d) in chars.by_ref() {
+                match d {
+                    '\\' if !escaped => escaped = true,
+                    d if d == c && !escaped => break,
+                    _ => escaped = false,
+                }
+            }
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, d)) = chars.peek() {
+                if !(d.is_alphanumeric() || d == '_') {
+                    break;
+                }
+                end = j + d.len_utf8();
+                chars.next();
+            }
+            out.push(&line[i..end]);
+        } else {
+            out.push(&line[i..i + c.len_utf8()]);
+        }
+    }
+    out
+}
+
+/// Chars of leading whitespace.
+pub fn indentation_len(text: &str) -> usize {
+    text.chars().take_while(|c| *c == ' ' || *c == '\t').count()
+}
+
+/// Column line `y` of `buffer` should be indented to.
+pub fn compute_indent(rule: &IndentRule, buffer: &Buffer, y: usize, tab_width: usize) -> usize {
+    let Some(prev) = (0..y).rev().find(|&p| !buffer.lines[p].text.trim().is_empty()) else {
+        return 0;
+    };
+    let prev_text = &buffer.lines[prev].text;
+    let base = column_of(prev_text, indentation_len(prev_text), tab_width);
+    let mut level = rule.opens(&tokens(prev_text, rule));
+
+    let starts_closed = tokens(&buffer.lines[y].text, rule).first().is_some_and(|t| rule.is_closer(t));
+    if starts_closed {
+        // An offside closer lines up with the statement that opened the
+        // block, whatever the previous line did.
+        level = if rule.style == IndentStyle::Offside { -1 } else { level - 1 };
+    }
+    let offset = rule.offset.unwrap_or(tab_width) as isize;
+    (base as isize + level * offset).max(0) as usize
+}
+
+/// Whitespace reaching column `to` from column `from`: as many tabs as fit
+/// followed by spaces when `use_tabs`, spaces only otherwise.
+pub fn indent_string(from: usize, to: usize, tab_width: usize, use_tabs: bool) -> String {
+    let mut out = String::new();
+    let mut col = from;
+    if use_tabs {
+        let tab = tab_width.max(1);
+        while (col / tab + 1) * tab <= to {
+            out.push('\t');
+            col = (col / tab + 1) * tab;
+        }
+    }
+    out.extend(std::iter::repeat_n(' ', to.saturating_sub(col)));
+    out
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Rule for the current buffer: that of its major mode, else of its
+    /// file extension, else `Copy`.
+    pub fn indent_rule(&self) -> IndentRule {
+        let config = self.user_config.borrow();
+        let by_extension = || {
+            let path = self.buffer.file_path.as_deref()?;
+            match path.extension()?.to_str()? {
+                "yaml" | "yml" => Some("yaml"),
+                _ => language_id(path),
+            }
+        };
+        config.indent_rules.get(&self.buffer.major_mode)
+            .or_else(|| config.indent_rules.get(by_extension()?))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Reindent line `y` by the buffer's rule. A cursor inside the old
+    /// indentation ends up after the new one. Returns whether anything
+    /// changed.
+    pub fn indent_line(&mut self, y: usize) -> bool {
+        let tab = self.tab_width();
+        let target = compute_indent(&self.indent_rule(), &self.buffer, y, tab);
+        self.indent_line_to(y, target)
+    }
+
+    fn indent_line_to(&mut self, y: usize, col: usize) -> bool {
+        let tab = self.tab_width();
+        let text = &self.buffer.lines[y].text;
+        let ws = indentation_len(text);
+        let new = indent_string(0, col, tab, self.indent_tabs_mode());
+        if text.chars().take(ws).eq(new.chars()) {
+            return false;
+        }
+
+        let (cx, cy) = (self.buffer.cursor_x, self.buffer.cursor_y);
+        let undo_from = self.buffer.undo_len();
+        if ws > 0 {
+            self.buffer.delete_range(0, y, ws, y);
+        }
+        if !new.is_empty() {
+            self.buffer.insert_text_at(0, y, &new);
+        }
+        self.buffer.group_undo(undo_from);
+        let added = new.chars().count();
+        self.buffer.cursor_y = cy;
+        self.buffer.cursor_x = if cy != y { cx } else if cx <= ws { added } else { cx - ws + added };
+        self.buffer.visual.dirty = true;
+        true
+    }
+
+    /// `newline-and-indent`: break the line and indent the new one. The line
+    /// left behind is reindented when it starts with a closer, and emptied
+    /// when only whitespace remains on it.
+    pub fn newline_and_indent(&mut self) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let undo_from = self.buffer.undo_len();
+        self.electric_reindent();
+        self.buffer.insert_newline_raw();
+        let y = self.buffer.cursor_y;
+        let left = &self.buffer.lines[y - 1];
+        if !left.text.is_empty() && left.text.trim().is_empty() {
+            let len = left.char_len;
+            self.buffer.delete_range(0, y - 1, len, y - 1);
+            self.buffer.cursor_x = 0;
+            self.buffer.cursor_y = y;
+        }
+        // What followed the cursor keeps none of its old indentation.
+        let ws = indentation_len(&self.buffer.lines[y].text);
+        if ws > 0 {
+            self.buffer.delete_range(0, y, ws, y);
+        }
+        self.indent_line(y);
+        self.buffer.group_undo(undo_from);
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("newline");
+    }
+
+    /// Reindent the current line if it starts with a closer. Runs after
+    /// self-inserting a non-word character and before `newline-and-indent`,
+    /// so `}` and `end` move out as they are typed.
+    pub fn electric_reindent(&mut self) {
+        if !self.user_config.borrow().electric_indent {
+            return;
+        }
+        let rule = self.indent_rule();
+        if rule.style == IndentStyle::Copy {
+            return;
+        }
+        let y = self.buffer.cursor_y;
+        let starts_closed = tokens(&self.buffer.lines[y].text, &rule)
+            .first()
+            .is_some_and(|t| rule.is_closer(t));
+        if starts_closed {
+            self.indent_line(y);
+        }
+    }
+
+    /// `indent-region`: reindent every line of the region; blank lines lose
+    /// their whitespace.
+    pub fn indent_region(&mut self) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let Some(sel) = self.buffer.selection() else {
+            self.minibuffer.message("The mark is not set now, so there is no region");
+            return;
+        };
+        // A region ending at the start of a line does not include it.
+        let last = if sel.end.x == 0 && sel.end.y > sel.start.y { sel.end.y - 1 } else { sel.end.y };
+        let mut changed = 0;
+        let undo_from = self.buffer.undo_len();
+        for y in sel.start.y..=last {
+            let blank = self.buffer.lines[y].text.trim().is_empty();
+            if if blank { self.indent_line_to(y, 0) } else { self.indent_line(y) } {
+                changed += 1;
+            }
+        }
+        self.buffer.group_undo(undo_from);
+        self.buffer.clear_mark();
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("indent");
+        self.minibuffer.message(&format!("Indented {changed} line{}", if changed == 1 { "" } else { "s" }));
+    }
+
+    /// `indent-for-tab-command`: with a language rule and the cursor inside
+    /// the indentation, reindent the line (or move past a correct
+    /// indentation); otherwise insert whitespace up to the `n`th next tab
+    /// stop.
+    pub fn indent_for_tab(&mut self, n: usize) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let tab = self.tab_width();
+        let y = self.buffer.cursor_y;
+        let ws = indentation_len(&self.buffer.lines[y].text);
+        if self.indent_rule().style != IndentStyle::Copy && self.buffer.cursor_x <= ws {
+            if !self.indent_line(y) {
+                self.buffer.cursor_x = ws;
+            }
+            self.ensure_cursor_visible();
+            self.emit_buffer_changed("indent");
+            return;
+        }
+
+        let line = &self.buffer.lines[y].text;
+        let col = column_of(line, self.buffer.cursor_x, tab);
+        let target = (col / tab + n.max(1)) * tab;
+        let text = indent_string(col, target, tab, self.indent_tabs_mode());
+        self.buffer.yank(&text);
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("indent");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::command::CommandRegistry;
+    use crate::commands::builtins::register_builtins;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+
+    fn editor(mode: &str, text: &str) -> Editor {
+        let mut registry = CommandRegistry::new();
+        register_builtins(&mut registry);
+        let mut ed = Editor::new(registry, Rc::new(RefCell::new(KeyMap::new())), Rc::new(RefCell::new(UserConfig::default())));
+        ed.buffer.major_mode = mode.to_string();
+        ed.buffer.set_text_raw(text);
+        ed
+    }
+
+    /// Check that every line of `text`, indented by hand, is indented as
+    /// the built-in rule of `mode` would indent it.
+    fn assert_indented(mode: &str, text: &str, tab_width: usize) {
+        let rule = &default_rules()[mode];
+        let mut buffer = Buffer::new();
+        buffer.set_text_raw(text);
+        for (y, line) in text.lines().enumerate() {
+            let want = column_of(line, indentation_len(line), tab_width);
+            assert_eq!(compute_indent(rule, &buffer, y, tab_width), want, "{mode} line {}: {line:?}", y + 1);
+        }
+    }
+
+    #[test]
+    fn rust_braces() {
+        assert_indented("rust", "\
+fn main<'a>(x: &'a str) {
+    let c = '{';
+    let s = \"}\";
+    if x {
+        y('\\'');
+    } else {
+        z();
+    }
+}", 4);
+    }
+
+    #[test]
+    fn c_braces_and_comments() {
+        assert_indented("c", "\
+int main(void) {
+\tint a[] = {
+\t\t1, 2,
+\t}; // }
+\treturn 0;
+}", 8);
+    }
+
+    #[test]
+    fn lua_end_and_elseif() {
+        assert_indented("lua", "\
+local function f(x)
+  if x == '{' then
+    return 1
+  elseif x then
+    return 2
+  else
+    return 3
+  end
+end", 2);
+    }
+
+    #[test]
+    fn python_offside() {
+        assert_indented("python", "\
+def f(c):
+    if c == '#':
+        return 1
+    else:
+        y = 2", 8);
+    }
+
+    #[test]
+    fn yaml_offside() {
+        assert_indented("yaml", "\
+a:
+  b: 1
+  c:
+    - 'x # y'", 8);
+    }
+
+    #[test]
+    fn tokens_skip_strings_and_comments() {
+        let rust = &default_rules()["rust"];
+        assert_eq!(tokens("let c = '{'; // {", rust), ["let", "c", "=", ";"]);
+        assert_eq!(tokens("f(\"\\\"{\", 'a')", rust), ["f", "(", ",", ")"]);
+        assert_eq!(tokens("&'a str {", rust), ["&", "a", "str", "{"]);
+        let lua = &default_rules()["lua"];
+        assert_eq!(tokens("x = '{' -- then", lua), ["x", "="]);
+        assert_eq!(tokens("s = 'it''s'", lua), ["s", "="]);
+    }
+
+    #[test]
+    fn indent_strings() {
+        assert_eq!(indent_string(0, 6, 4, false), "      ");
+        assert_eq!(indent_string(0, 10, 4, true), "\t\t  ");
+        assert_eq!(indent_string(2, 8, 4, true), "\t\t");
+        assert_eq!(indent_string(5, 3, 4, true), "");
+    }
+
+    #[test]
+    fn indent_region_undoes_in_one_step() {
+        let mut ed = editor("rust", "fn f() {\nlet a;\n  let b;\n}");
+        ed.buffer.set_mark();
+        ed.buffer.cursor_y = 3;
+        ed.indent_region();
+        assert_eq!(ed.buffer.text(), "fn f() {\n    let a;\n    let b;\n}");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), "fn f() {\nlet a;\n  let b;\n}");
+    }
+
+    #[test]
+    fn newline_and_indent_undoes_in_one_step() {
+        let mut ed = editor("rust", "fn f() {  let a;");
+        ed.buffer.cursor_x = 8;
+        ed.newline_and_indent();
+        assert_eq!(ed.buffer.text(), "fn f() {\n    let a;");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), "fn f() {  let a;");
+    }
+}
@@ -0,0 +1,219 @@
+// core/src/input.rs
+//
+// Key dispatch, shared by every front end. A front end reads a key press
+// from wherever it gets them, records it for keyboard macros and hands it
+// to `Editor::handle_key`, as a `KeyEvent` of this module; the TUI converts
+// the terminal's events, the headless one builds them itself.
+// Physical modifiers are turned into the logical `mod1`..`mod3` of
+// `UserConfig`, prefix keys (`C-x`) included.
+
+use crate::buffer::Motion;
+use crate::editor::editor::{Editor, InputMode, Modifiers, PhysicalModifiers};
+use crate::minibuffer::MiniBufferMode;
+
+/// A key the editor understands. Front ends translate theirs into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Esc,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    F(u8),
+}
+
+/// A key press: the key and the physical modifiers held with it. Shift is
+/// already in the case of a char.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: PhysicalModifiers,
+}
+
+impl KeyEvent {
+    pub fn new(code: KeyCode, modifiers: PhysicalModifiers) -> Self {
+        Self { code, modifiers }
+    }
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Logical modifiers of a key. A prefix key only arms the prefix and
+    /// yields none; the next key gets the prefix's modifier.
+    pub fn logical_modifiers(&mut self, physical: PhysicalModifiers, key: KeyCode) -> Modifiers {
+        let mut mods = Modifiers::empty();
+        let config = self.user_config.clone();
+        let config = config.borrow();
+
+        for i in 0..3 {
+            if let Some(pk) = config.prefix_keys[i]
+                && key == KeyCode::Char(pk)
+                && physical.intersects(config.prefix_masks[i]) {
+                self.pending_prefix = Some(i);
+                return Modifiers::empty();
+            }
+            if physical.intersects(config.mod_masks[i]) {
+                mods.insert(Modifiers::from_bits_truncate(1 << i));
+            }
+        }
+
+        if let Some(p) = self.pending_prefix.take() {
+            mods.insert(Modifiers::from_bits_truncate(1 << p));
+        }
+        mods
+    }
+
+    /// Handle one key press, read by a front end or replayed from a macro.
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if self.describing_key {
+            self.describe_key_press(key);
+            return;
+        }
+        match self.mode {
+            InputMode::Normal => self.handle_normal_key(key),
+            InputMode::MiniBuffer => self.handle_minibuffer_key(key),
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) {
+        let mods = self.logical_modifiers(key.modifiers, key.code);
+        // A prefix key (`C-x`) only waits for the key after it.
+        if self.pending_prefix.is_some() {
+            return;
+        }
+
+        // Ctrl-c for exit
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(PhysicalModifiers::CTRL) {
+            self.should_quit = true;
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char(c) => {
+                if let Some(cmd) = self.lookup_key(mods, c) {
+                    self.execute_named(&cmd);
+                } else if mods.is_empty() {
+                    self.at_each_cursor(|ed| ed.insert_char(c));
+                }
+            }
+            KeyCode::Left => self.at_each_cursor(|ed| ed.buffer.move_cursor(Motion::Left)),
+            KeyCode::Right => self.at_each_cursor(|ed| ed.buffer.move_cursor(Motion::Right)),
+            KeyCode::Up => self.at_each_cursor(|ed| ed.buffer.move_cursor(Motion::Up)),
+            KeyCode::Down => self.at_each_cursor(|ed| ed.buffer.move_cursor(Motion::Down)),
+            KeyCode::Backspace => match self.lookup_key(mods, '\x7f') {
+                Some(cmd) => self.execute_named(&cmd),
+                None => self.at_each_cursor(|ed| ed.delete_backward_char()),
+            },
+            KeyCode::Enter => match self.lookup_key(mods, '\n') {
+                Some(cmd) => self.execute_named(&cmd),
+                None if self.user_config.borrow().electric_indent => self.execute_named("newline-and-indent"),
+                None => self.at_each_cursor(|ed| ed.insert_newline()),
+            },
+            KeyCode::Tab => match self.lookup_key(mods, '\t') {
+                Some(cmd) => self.execute_named(&cmd),
+                None => self.execute_named("indent-for-tab-command"),
+            },
+            KeyCode::Delete if !self.barf_if_read_only() => self.at_each_cursor(|ed| { _ = ed.buffer.delete(Motion::Right); }),
+            _ => {}
+        }
+
+        self.ensure_cursor_visible();
+        self.clamp_scroll();
+    }
+
+    fn handle_minibuffer_key(&mut self, key: KeyEvent) {
+        let mods = self.logical_modifiers(key.modifiers, key.code);
+        // A prefix key (`C-x`) only waits for the key after it.
+        if self.pending_prefix.is_some() {
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char(c) => {
+                let cmd = self.keymap.borrow().lookup(mods, c).cloned();
+                if let Some(cmd) = cmd {
+                    self.execute_named(&cmd);
+                } else if mods.is_empty() {
+                    self.minibuffer.push(c);
+                    if self.minibuffer.mode() == MiniBufferMode::ISearchForward {
+                        self.isearch_update();
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                self.minibuffer.pop();
+                if self.minibuffer.mode() == MiniBufferMode::ISearchForward {
+                    self.isearch_update();
+                }
+            }
+            KeyCode::Enter => self.execute_minibuffer(),
+            KeyCode::Esc => self.abort_minibuffer(),
+            // Completing prompts cycle through matches, others through history
+            KeyCode::Tab => self.minibuffer.complete(),
+            KeyCode::Down | KeyCode::Up => {
+                let delta = if key.code == KeyCode::Down { 1 } else { -1 };
+                if self.minibuffer.completions().is_some() {
+                    self.minibuffer.cycle_completion(delta);
+                } else {
+                    self.minibuffer.history_step(delta);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Feed the keys of queued keyboard macro runs through `handle_key`.
+    pub fn replay_macros(&mut self) {
+        while let Some(keys) = self.next_macro_run() {
+            for key in keys {
+                if self.should_quit {
+                    break;
+                }
+                self.handle_key(key);
+            }
+        }
+        self.finish_macro_runs();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::command::CommandRegistry;
+    use crate::commands::builtins::register_builtins;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+    use crate::kmacro::parse_key;
+
+    fn press(ed: &mut Editor, keys: &str) {
+        for name in keys.split_whitespace() {
+            ed.handle_key(parse_key(name).unwrap());
+        }
+    }
+
+    #[test]
+    fn prefix_key_waits_for_the_next_key() {
+        let mut registry = CommandRegistry::new();
+        register_builtins(&mut registry);
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        keymap.borrow_mut().bind(Modifiers::MOD2, 'a', "move-beginning-of-line".into());
+        let mut ed = Editor::new(registry, keymap, Rc::new(RefCell::new(UserConfig::default())));
+
+        press(&mut ed, "a b C-x a");
+        assert_eq!(ed.buffer.text(), "ab");
+        assert_eq!(ed.cursor_pos(), (0, 0));
+        press(&mut ed, "x");
+        assert_eq!(ed.buffer.text(), "xab");
+    }
+}
@@ -0,0 +1,305 @@
+// core/src/kmacro.rs
+//
+// Keyboard macros. While a macro is being defined the front end hands every
+// key it reads to `record_macro_key`; replaying queues the keys
+// (`MacroRun`) and the front end feeds them back through its key handler,
+// so a macro does exactly what typing it did, minibuffer input included.
+//
+// Keys are written the Emacs way (`C-a`, `M-f`, `RET`, `<left>`) when a
+// macro is saved as Lua or defined with `define_macro`. Named macros run
+// like commands: `execute_named` falls back to them, so `bind` and
+// `execute-command` work with their names.
+
+use std::collections::VecDeque;
+use crate::editor::editor::{Editor, InputMode, PhysicalModifiers};
+use crate::input::{KeyCode, KeyEvent};
+
+/// Macro runs started by one key, counting nested calls, before giving up
+/// on what is probably a macro calling itself.
+const MAX_RUNS: usize = 10_000;
+
+/// Keys to replay, starting at the beginning of `line` when set.
+pub struct MacroRun {
+    pub keys: Vec<KeyEvent>,
+    pub line: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct MacroState {
+    /// Keys of the macro being defined.
+    pub recording: Option<Vec<KeyEvent>>,
+    /// Where the key sequence being handled starts in `recording`; ending
+    /// the definition drops it.
+    sequence_start: usize,
+    pub last: Option<Vec<KeyEvent>>,
+    pub queue: VecDeque<MacroRun>,
+    runs: usize,
+}
+
+/// `key` in Emacs notation.
+pub fn key_name(key: &KeyEvent) -> String {
+    let mut name = String::new();
+    let named = !matches!(key.code, KeyCode::Char(c) if c != ' ');
+    for (flag, prefix) in [
+        (PhysicalModifiers::CTRL, "C-"),
+        (PhysicalModifiers::ALT, "M-"),
+        (PhysicalModifiers::SUPER, "s-"),
+    ] {
+        if key.modifiers.contains(flag) {
+            name.push_str(prefix);
+        }
+    }
+    // Shift is already in the case of a char.
+    if named && key.modifiers.contains(PhysicalModifiers::SHIFT) {
+        name.push_str("S-");
+    }
+    match key.code {
+        KeyCode::Char(' ') => name.push_str("SPC"),
+        KeyCode::Char(c) => name.push(c),
+        KeyCode::Enter => name.push_str("RET"),
+        KeyCode::Tab => name.push_str("TAB"),
+        KeyCode::Backspace => name.push_str("DEL"),
+        KeyCode::Esc => name.push_str("ESC"),
+        KeyCode::Delete => name.push_str("<delete>"),
+        KeyCode::Left => name.push_str("<left>"),
+        KeyCode::Right => name.push_str("<right>"),
+        KeyCode::Up => name.push_str("<up>"),
+        KeyCode::Down => name.push_str("<down>"),
+        KeyCode::Home => name.push_str("<home>"),
+        KeyCode::End => name.push_str("<end>"),
+        KeyCode::PageUp => name.push_str("<prior>"),
+        KeyCode::PageDown => name.push_str("<next>"),
+        KeyCode::F(n) => name.push_str(&format!("<f{n}>")),
+    }
+    name
+}
+
+/// Inverse of `key_name`.
+pub fn parse_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = PhysicalModifiers::empty();
+    let mut rest = name;
+    loop {
+        let flag = match rest.get(..2) {
+            Some("C-") => PhysicalModifiers::CTRL,
+            Some("M-") => PhysicalModifiers::ALT,
+            Some("s-") => PhysicalModifiers::SUPER,
+            Some("S-") => PhysicalModifiers::SHIFT,
+            _ => break,
+        };
+        // `C--` is control and minus.
+        if rest.len() == 2 {
+            break;
+        }
+        modifiers |= flag;
+        rest = &rest[2..];
+    }
+    let code = match rest {
+        "SPC" => KeyCode::Char(' '),
+        "RET" => KeyCode::Enter,
+        "TAB" => KeyCode::Tab,
+        "DEL" => KeyCode::Backspace,
+        "ESC" => KeyCode::Esc,
+        "<delete>" => KeyCode::Delete,
+        "<left>" => KeyCode::Left,
+        "<right>" => KeyCode::Right,
+        "<up>" => KeyCode::Up,
+        "<down>" => KeyCode::Down,
+        "<home>" => KeyCode::Home,
+        "<end>" => KeyCode::End,
+        "<prior>" => KeyCode::PageUp,
+        "<next>" => KeyCode::PageDown,
+        _ => {
+            if let Some(n) = rest.strip_prefix("<f").and_then(|r| r.strip_suffix('>')) {
+                KeyCode::F(n.parse().ok()?)
+            } else {
+                let mut chars = rest.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                if c.is_uppercase() {
+                    modifiers |= PhysicalModifiers::SHIFT;
+                }
+                KeyCode::Char(c)
+            }
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// Lua that defines `keys` as the macro `name`.
+pub fn macro_to_lua(name: &str, keys: &[KeyEvent]) -> String {
+    let keys: Vec<String> = keys.iter()
+        .map(|k| format!("{:?}", key_name(k)))
+        .collect();
+    format!("define_macro({name:?}, {{ {} }})\n", keys.join(", "))
+}
+
+// ---- Editor integration ----
+impl Editor {
+    pub fn defining_macro(&self) -> bool {
+        self.macros.recording.is_some()
+    }
+
+    /// Called by the front end with every key it reads from the terminal.
+    pub fn record_macro_key(&mut self, key: KeyEvent) {
+        let starts_sequence = self.pending_prefix.is_none() && self.mode == InputMode::Normal;
+        let Some(keys) = &mut self.macros.recording else { return };
+        if starts_sequence {
+            self.macros.sequence_start = keys.len();
+        }
+        keys.push(key);
+    }
+
+    /// `start-kbd-macro`.
+    pub fn start_kbd_macro(&mut self) {
+        if self.defining_macro() {
+            self.minibuffer.message("Already defining a keyboard macro");
+            return;
+        }
+        self.macros.recording = Some(Vec::new());
+        self.macros.sequence_start = 0;
+        self.minibuffer.message("Defining keyboard macro...");
+    }
+
+    /// `end-kbd-macro`: keep what was recorded, without the keys that ran
+    /// this command.
+    pub fn end_kbd_macro(&mut self) -> bool {
+        let Some(mut keys) = self.macros.recording.take() else {
+            self.minibuffer.message("Not defining a keyboard macro");
+            return false;
+        };
+        keys.truncate(self.macros.sequence_start);
+        if keys.is_empty() {
+            self.minibuffer.message("Ignoring empty keyboard macro");
+            return false;
+        }
+        self.macros.last = Some(keys);
+        self.minibuffer.message("Keyboard macro defined");
+        true
+    }
+
+    /// Queue `keys` to run `count` times.
+    pub fn run_macro(&mut self, keys: Vec<KeyEvent>, count: usize) {
+        for _ in 0..count.max(1) {
+            self.macros.queue.push_back(MacroRun { keys: keys.clone(), line: None });
+        }
+    }
+
+    /// `call-last-kbd-macro`. While defining one, end it first.
+    pub fn call_last_kbd_macro(&mut self, count: usize) {
+        if self.defining_macro() && !self.end_kbd_macro() {
+            return;
+        }
+        match self.macros.last.clone() {
+            Some(keys) => self.run_macro(keys, count),
+            None => self.minibuffer.message("No keyboard macro defined"),
+        }
+    }
+
+    /// `name-last-kbd-macro`.
+    pub fn name_last_kbd_macro(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if self.commands.get(name).is_some() {
+            self.minibuffer.message(&format!("{name} is already a command"));
+            return;
+        }
+        let Some(keys) = self.macros.last.clone() else {
+            self.minibuffer.message("No keyboard macro defined");
+            return;
+        };
+        self.named_macros.insert(name.to_string(), keys);
+        self.command_docs.remove(name);
+        self.minibuffer.message(&format!("Named the last keyboard macro {name}"));
+    }
+
+    /// `insert-kbd-macro`: insert Lua defining the named macro (the last
+    /// one when `name` is empty), to be kept in `init.lua`.
+    pub fn insert_kbd_macro(&mut self, name: &str) {
+        let name = name.trim();
+        let keys = if name.is_empty() {
+            self.macros.last.clone()
+        } else {
+            self.named_macros.get(name).cloned()
+        };
+        let Some(keys) = keys else {
+            self.minibuffer.message(&format!("No keyboard macro named {name}"));
+            return;
+        };
+        let name = if name.is_empty() { "last-kbd-macro" } else { name };
+        if self.barf_if_read_only() {
+            return;
+        }
+        self.buffer.yank(&macro_to_lua(name, &keys));
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("insert-kbd-macro");
+    }
+
+    /// `apply-macro-to-region-lines`: run the last macro at the start of
+    /// each line of the region. Lines are done from the last up, so a macro
+    /// that adds or removes lines does not shift the ones still to come.
+    pub fn apply_macro_to_region_lines(&mut self) {
+        let Some(keys) = self.macros.last.clone() else {
+            self.minibuffer.message("No keyboard macro defined");
+            return;
+        };
+        let Some(sel) = self.buffer.selection() else {
+            self.minibuffer.message("The mark is not set now, so there is no region");
+            return;
+        };
+        let last = if sel.end.x == 0 && sel.end.y > sel.start.y { sel.end.y - 1 } else { sel.end.y };
+        self.buffer.clear_mark();
+        for y in (sel.start.y..=last).rev() {
+            self.macros.queue.push_back(MacroRun { keys: keys.clone(), line: Some(y) });
+        }
+    }
+
+    /// Next keys the front end should replay. Runs queued while replaying
+    /// come after the current ones.
+    pub fn next_macro_run(&mut self) -> Option<Vec<KeyEvent>> {
+        let run = self.macros.queue.pop_front()?;
+        self.macros.runs += 1;
+        if self.macros.runs > MAX_RUNS {
+            self.macros.queue.clear();
+            self.macros.runs = 0;
+            self.minibuffer.message("Keyboard macro stopped: too many runs");
+            return None;
+        }
+        if let Some(y) = run.line {
+            self.buffer.cursor_y = y.min(self.buffer.lines.len() - 1);
+            self.buffer.cursor_x = 0;
+        }
+        Some(run.keys)
+    }
+
+    /// The front end is done replaying.
+    pub fn finish_macro_runs(&mut self) {
+        self.macros.runs = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for name in ["a", "A", "C-a", "M-x", "C-M-%", "SPC", "RET", "S-TAB", "DEL", "<f5>", "C-<left>", "s-<next>", "C--"] {
+            let key = parse_key(name).unwrap_or_else(|| panic!("{name} did not parse"));
+            assert_eq!(key_name(&key), name);
+        }
+    }
+
+    #[test]
+    fn parse_key_reads_modifiers_and_case() {
+        assert_eq!(parse_key("C-x"), Some(KeyEvent::new(KeyCode::Char('x'), PhysicalModifiers::CTRL)));
+        assert_eq!(parse_key("X"), Some(KeyEvent::new(KeyCode::Char('X'), PhysicalModifiers::SHIFT)));
+        assert_eq!(parse_key("M-RET"), Some(KeyEvent::new(KeyCode::Enter, PhysicalModifiers::ALT)));
+        assert_eq!(parse_key("<f12>"), Some(KeyEvent::new(KeyCode::F(12), PhysicalModifiers::empty())));
+        assert_eq!(parse_key("ab"), None);
+        assert_eq!(parse_key("<fx>"), None);
+    }
+}
@@ -5,3 +5,26 @@ pub mod minibuffer;
 pub mod command;
 pub mod commands;
 pub mod status;
+pub mod timer;
+pub mod process;
+pub mod compile;
+pub mod grep;
+pub mod dired;
+pub mod util;
+pub mod walk;
+pub mod completion;
+pub mod project;
+pub mod lsp;
+pub mod diagnostics;
+pub mod indent;
+pub mod pairs;
+pub mod rectangle;
+pub mod cursors;
+pub mod kmacro;
+pub mod registers;
+pub mod bookmarks;
+pub mod desktop;
+pub mod input;
+pub mod view_mode;
+pub mod help;
+pub mod log;
@@ -0,0 +1,192 @@
+// core/src/log.rs
+//
+// The message log. Everything shown in the echo area is recorded, with the
+// time, and so is anything logged with a level, from Rust (`Log::log`) or
+// from Lua (`log.info`, `log.warn`, `log.error`, `log.debug`). Errors of
+// Lua hooks, timers and process callbacks, which have nobody to return
+// them to, are logged as errors.
+//
+// The log is shared (`SharedLog`) rather than owned by the editor, so that
+// Lua can write to it while the editor is borrowed. The editor copies new
+// entries into `*Messages*` on every tick. `*Messages*` gets every entry;
+// with a log file set, those at or above `level` are also appended to it.
+// Times are UTC.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::editor::editor::Editor;
+use crate::util::DateTime;
+
+pub const MESSAGES_BUFFER: &str = "*Messages*";
+
+/// Entries kept; the oldest go first.
+const MESSAGE_LOG_MAX: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+pub struct LogEntry {
+    /// Seconds since the epoch.
+    pub time: u64,
+    pub level: LogLevel,
+    pub text: String,
+}
+
+impl LogEntry {
+    /// The line shown in `*Messages*`: time of day, and the level unless
+    /// it is a plain message.
+    pub fn display(&self) -> String {
+        let time = DateTime::from_unix(self.time as i64).format_time();
+        match self.level {
+            LogLevel::Info => format!("{time} {}", self.text),
+            level => format!("{time} {} {}", level.as_str(), self.text),
+        }
+    }
+}
+
+pub struct Log {
+    entries: VecDeque<LogEntry>,
+    /// Entries not yet copied into `*Messages*`.
+    unshown: usize,
+    /// Entries were dropped since `*Messages*` was last written.
+    truncated: bool,
+    /// Entries below this level are not written to `file`; `*Messages*`
+    /// still gets them.
+    pub level: LogLevel,
+    pub file: Option<PathBuf>,
+}
+
+pub type SharedLog = Rc<RefCell<Log>>;
+
+impl Default for Log {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            unshown: 0,
+            truncated: false,
+            level: LogLevel::Info,
+            file: None,
+        }
+    }
+}
+
+impl Log {
+    pub fn log(&mut self, level: LogLevel, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let entry = LogEntry { time, level, text: text.to_string() };
+        if level >= self.level
+            && let Some(path) = self.file.clone() {
+            let line = format!("{} {:<5} {}\n", DateTime::from_unix(time as i64).format_seconds(), level.as_str(), entry.text);
+            let written = OpenOptions::new().create(true).append(true).open(&path)
+                .and_then(|mut f| f.write_all(line.as_bytes()));
+            if let Err(e) = written {
+                // Stop trying, and say so where it can still be seen.
+                self.file = None;
+                self.log(LogLevel::Error, &format!("Cannot write log file {}: {e}", path.display()));
+            }
+        }
+        self.entries.push_back(entry);
+        self.unshown += 1;
+        if self.entries.len() > MESSAGE_LOG_MAX {
+            self.entries.pop_front();
+            self.unshown = self.unshown.min(self.entries.len());
+            self.truncated = true;
+        }
+    }
+
+    pub fn info(&mut self, text: &str) {
+        self.log(LogLevel::Info, text);
+    }
+
+    pub fn warn(&mut self, text: &str) {
+        self.log(LogLevel::Warn, text);
+    }
+
+    pub fn error(&mut self, text: &str) {
+        self.log(LogLevel::Error, text);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+}
+
+/// Log the error of a Lua callback, if it failed.
+pub fn log_lua_error<T>(log: &SharedLog, what: &str, result: mlua::Result<T>) {
+    if let Err(e) = result {
+        log.borrow_mut().error(&format!("Error in {what}: {e}"));
+    }
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Copy entries logged since the last call into `*Messages*`. The whole
+    /// log is written again when the buffer is new or old entries were
+    /// dropped.
+    pub fn flush_messages(&mut self) {
+        let log = self.log.clone();
+        let mut log = log.borrow_mut();
+        if log.unshown == 0 {
+            return;
+        }
+        let rewrite = log.truncated || self.find_buffer(MESSAGES_BUFFER)
+            .is_none_or(|b| b.lines.len() == 1 && b.lines[0].char_len == 0);
+        let skip = if rewrite { 0 } else { log.entries.len() - log.unshown };
+        let lines: Vec<String> = log.entries.iter().skip(skip).map(LogEntry::display).collect();
+        let buf = self.buffer_mut_or_create(MESSAGES_BUFFER);
+        buf.read_only = true;
+        if rewrite {
+            buf.set_text_raw(&lines.join("\n"));
+            buf.cursor_y = buf.lines.len() - 1;
+            buf.cursor_x = buf.lines[buf.cursor_y].char_len;
+        } else {
+            buf.append_raw(&format!("\n{}", lines.join("\n")));
+        }
+        log.unshown = 0;
+        log.truncated = false;
+    }
+
+    /// `view-echo-area-messages`: show `*Messages*`, at its end.
+    pub fn view_echo_area_messages(&mut self) {
+        self.flush_messages();
+        self.switch_to_buffer(MESSAGES_BUFFER);
+        let buf = &mut self.buffer;
+        buf.cursor_y = buf.lines.len() - 1;
+        buf.cursor_x = buf.lines[buf.cursor_y].char_len;
+        self.ensure_cursor_visible();
+    }
+}
@@ -0,0 +1,223 @@
+// core/src/lsp/client.rs
+//
+// One running language server: the initialize handshake, request ids and
+// what each pending request was for, and the server-to-client messages the
+// editor cares about. Messages sent before the server answered `initialize`
+// are queued and flushed once it has.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use serde_json::{json, Value};
+use super::transport::Transport;
+use crate::diagnostics::{Diagnostic, Severity};
+use super::{path_to_uri, uri_to_path};
+
+/// What a pending request was sent for, so its response can be routed.
+#[derive(Debug, Clone)]
+pub enum RequestKind {
+    Initialize,
+    Definition,
+    References,
+    Hover,
+    Rename,
+    Completion,
+}
+
+pub enum LspEvent {
+    Diagnostics { path: PathBuf, items: Vec<Diagnostic> },
+    Response { kind: RequestKind, result: Value },
+    Error { kind: RequestKind, message: String },
+    /// `window/showMessage`
+    Message(String),
+}
+
+pub struct LspClient {
+    pub language: String,
+    pub root: PathBuf,
+    transport: Box<dyn Transport>,
+    next_id: i64,
+    pending: HashMap<i64, RequestKind>,
+    ready: bool,
+    queue: Vec<Value>,
+    pub capabilities: Value,
+    /// Documents opened with `didOpen`.
+    pub documents: Vec<PathBuf>,
+}
+
+impl LspClient {
+    /// Take over `transport` and start the initialize handshake.
+    pub fn start(language: &str, root: PathBuf, transport: Box<dyn Transport>) -> Self {
+        let mut client = Self {
+            language: language.to_string(),
+            root,
+            transport,
+            next_id: 1,
+            pending: HashMap::new(),
+            ready: false,
+            queue: Vec::new(),
+            capabilities: Value::Null,
+            documents: Vec::new(),
+        };
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": path_to_uri(&client.root),
+            "workspaceFolders": [{ "uri": path_to_uri(&client.root), "name": client.root.display().to_string() }],
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "didSave": true },
+                    "publishDiagnostics": {},
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "definition": {},
+                    "references": {},
+                    "rename": {},
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                },
+                "workspace": { "workspaceEdit": { "documentChanges": true } },
+            },
+        });
+        let id = client.next_id();
+        client.pending.insert(id, RequestKind::Initialize);
+        client.write(json!({ "jsonrpc": "2.0", "id": id, "method": "initialize", "params": params }));
+        client
+    }
+
+    fn next_id(&mut self) -> i64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn write(&mut self, msg: Value) {
+        // A dead server is noticed by `is_alive`; nothing to do here.
+        let _ = self.transport.send(&msg);
+    }
+
+    fn send(&mut self, msg: Value) {
+        if self.ready {
+            self.write(msg);
+        } else {
+            self.queue.push(msg);
+        }
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    pub fn request(&mut self, method: &str, params: Value, kind: RequestKind) {
+        let id = self.next_id();
+        self.pending.insert(id, kind);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        self.transport.is_alive()
+    }
+
+    /// Requests still waiting for an answer, the handshake included.
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Whether the server wants whole documents on every change
+    /// (`TextDocumentSyncKind.Full`) instead of edits.
+    pub fn wants_full_sync(&self) -> bool {
+        let sync = &self.capabilities["textDocumentSync"];
+        sync.as_i64().or_else(|| sync["change"].as_i64()) == Some(1)
+    }
+
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+        for msg in self.transport.poll() {
+            self.handle(msg, &mut events);
+        }
+        events
+    }
+
+    fn handle(&mut self, msg: Value, events: &mut Vec<LspEvent>) {
+        let method = msg["method"].as_str().map(str::to_string);
+        match (method, msg.get("id").cloned()) {
+            // Response to one of our requests.
+            (None, Some(id)) => {
+                let Some(kind) = id.as_i64().and_then(|id| self.pending.remove(&id)) else { return };
+                if let Some(err) = msg.get("error") {
+                    let message = err["message"].as_str().unwrap_or("request failed").to_string();
+                    events.push(LspEvent::Error { kind, message });
+                    return;
+                }
+                let result = msg.get("result").cloned().unwrap_or(Value::Null);
+                if let RequestKind::Initialize = kind {
+                    self.capabilities = result["capabilities"].clone();
+                    self.ready = true;
+                    self.write(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+                    for queued in std::mem::take(&mut self.queue) {
+                        self.write(queued);
+                    }
+                    return;
+                }
+                events.push(LspEvent::Response { kind, result });
+            }
+            // Request from the server.
+            (Some(method), Some(id)) => {
+                let reply = match method.as_str() {
+                    "workspace/configuration" => {
+                        let n = msg["params"]["items"].as_array().map_or(0, |a| a.len());
+                        json!({ "jsonrpc": "2.0", "id": id, "result": vec![Value::Null; n] })
+                    }
+                    "window/workDoneProgress/create" | "client/registerCapability"
+                    | "client/unregisterCapability" => {
+                        json!({ "jsonrpc": "2.0", "id": id, "result": null })
+                    }
+                    _ => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": format!("unsupported: {method}") },
+                    }),
+                };
+                self.write(reply);
+            }
+            // Notification.
+            (Some(method), None) => match method.as_str() {
+                "textDocument/publishDiagnostics" => {
+                    let params = &msg["params"];
+                    let Some(path) = params["uri"].as_str().and_then(uri_to_path) else { return };
+                    let items = params["diagnostics"].as_array()
+                        .map(|a| a.iter().filter_map(parse_diagnostic).collect())
+                        .unwrap_or_default();
+                    events.push(LspEvent::Diagnostics { path, items });
+                }
+                "window/showMessage" => {
+                    if let Some(text) = msg["params"]["message"].as_str() {
+                        events.push(LspEvent::Message(text.to_string()));
+                    }
+                }
+                _ => {}
+            },
+            (None, None) => {}
+        }
+    }
+}
+
+/// Positions stay in UTF-16 columns here; the editor converts them against
+/// the buffer text.
+fn parse_diagnostic(d: &Value) -> Option<Diagnostic> {
+    let range = &d["range"];
+    let pos = |p: &Value| Some((p["line"].as_u64()? as usize, p["character"].as_u64()? as usize));
+    let (line, col) = pos(&range["start"])?;
+    let (end_line, end_col) = pos(&range["end"])?;
+    let severity = match d["severity"].as_i64() {
+        Some(2) => Severity::Warning,
+        Some(3) => Severity::Info,
+        Some(4) => Severity::Hint,
+        _ => Severity::Error,
+    };
+    Some(Diagnostic {
+        line,
+        col,
+        end_line,
+        end_col,
+        severity,
+        message: d["message"].as_str().unwrap_or("").to_string(),
+        source: d["source"].as_str().map(str::to_string),
+    })
+}
@@ -0,0 +1,762 @@
+// core/src/lsp/mod.rs
+//
+// Language Server Protocol client. One server runs per (language, project
+// root); buffers visiting files of a configured language are opened in it
+// and their edits are sent incrementally on every tick (`Editor::lsp_sync`).
+// Responses arrive through `Editor::poll_lsp`, which routes them by the kind
+// of request that is waiting for them. Positions on the wire are UTF-16
+// columns, positions in buffers are char columns; `Buffer::utf16_col` and
+// `Buffer::char_col` convert between the two.
+
+pub mod client;
+pub mod transport;
+
+use std::collections::hash_map::{Entry, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+use crate::buffer::{utf16_to_char_col, Buffer};
+use crate::compile::LocationList;
+use crate::diagnostics::{Diagnostic, LSP_PROVIDER};
+use crate::editor::editor::Editor;
+use crate::grep::GREP_MODE;
+use client::{LspClient, LspEvent, RequestKind};
+use transport::{ScriptedTransport, StdioTransport, Transport};
+
+pub const REFERENCES_BUFFER: &str = "*references*";
+pub const HOVER_BUFFER: &str = "*hover*";
+
+#[derive(Debug, Clone)]
+pub enum LspServer {
+    /// A program speaking LSP on stdin/stdout.
+    Stdio { command: String, args: Vec<String> },
+    /// A `ScriptedTransport` script, to try the client without a server.
+    Scripted(PathBuf),
+}
+
+pub fn default_servers() -> HashMap<String, LspServer> {
+    let mut servers = HashMap::new();
+    servers.insert("rust".to_string(), LspServer::Stdio { command: "rust-analyzer".to_string(), args: Vec::new() });
+    servers
+}
+
+/// LSP `languageId` of a file, from its extension.
+pub fn language_id(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?;
+    Some(match ext {
+        "rs" => "rust",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "go" => "go",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "lua" => "lua",
+        "zig" => "zig",
+        _ => return None,
+    })
+}
+
+// ---- URIs ----
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(b as char),
+            _ => uri.push_str(&format!("%{b:02X}")),
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let bytes = rest.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(b) = rest.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&out).into_owned()))
+}
+
+fn position(buf: &Buffer) -> Value {
+    json!({ "line": buf.cursor_y, "character": buf.utf16_col(buf.cursor_x, buf.cursor_y) })
+}
+
+type Range = ((usize, usize), (usize, usize));
+
+/// `((line, col), (line, col))`, UTF-16 columns.
+fn parse_range(range: &Value) -> Option<Range> {
+    let pos = |p: &Value| Some((p["line"].as_u64()? as usize, p["character"].as_u64()? as usize));
+    Some((pos(&range["start"])?, pos(&range["end"])?))
+}
+
+/// `Location` or `LocationLink` as (file, line, UTF-16 column).
+fn parse_location(v: &Value) -> Option<(PathBuf, usize, usize)> {
+    let (uri, range) = match v.get("targetUri") {
+        Some(uri) => (uri, &v["targetSelectionRange"]),
+        None => (&v["uri"], &v["range"]),
+    };
+    let ((line, col), _) = parse_range(range)?;
+    Some((uri_to_path(uri.as_str()?)?, line, col))
+}
+
+/// Plain text of hover `contents`: a string, `MarkupContent`, a
+/// `MarkedString` or a list of those. Markdown code fences are dropped.
+fn hover_text(contents: &Value) -> String {
+    let raw = match contents {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(hover_text).collect::<Vec<_>>().join("\n\n"),
+        Value::Object(_) => contents["value"].as_str().unwrap_or("").to_string(),
+        _ => String::new(),
+    };
+    raw.lines()
+        .filter(|l| !l.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+// ---- Editor integration ----
+impl Editor {
+    fn buffer_visiting(&self, path: &Path) -> Option<&Buffer> {
+        std::iter::once(&self.buffer).chain(self.buffers.iter())
+            .find(|b| b.file_path.is_some() && b.absolute_path().as_deref() == Some(path))
+    }
+
+    fn buffer_visiting_mut(&mut self, path: &Path) -> Option<&mut Buffer> {
+        std::iter::once(&mut self.buffer).chain(self.buffers.iter_mut())
+            .find(|b| b.file_path.is_some() && b.absolute_path().as_deref() == Some(path))
+    }
+
+    /// Index of the client serving `path`, started on first use.
+    fn lsp_client_index(&mut self, path: &Path) -> Option<usize> {
+        let language = language_id(path)?;
+        let server = self.user_config.borrow().lsp_servers.get(language).cloned()?;
+        let dir = path.parent()?.to_path_buf();
+        let root = self.project_root_of(&dir).unwrap_or(dir);
+
+        if let Some(i) = self.lsp_clients.iter().position(|c| c.language == language && c.root == root) {
+            return Some(i);
+        }
+        let key = (language.to_string(), root.clone());
+        if self.lsp_failed.contains(&key) {
+            return None;
+        }
+        let transport: io::Result<Box<dyn Transport>> = match &server {
+            LspServer::Stdio { command, args } => {
+                StdioTransport::spawn(command, args, &root).map(|t| Box::new(t) as Box<dyn Transport>)
+            }
+            LspServer::Scripted(script) => ScriptedTransport::load(script).map(|t| Box::new(t) as Box<dyn Transport>),
+        };
+        match transport {
+            Ok(t) => {
+                self.lsp_clients.push(LspClient::start(language, root, t));
+                Some(self.lsp_clients.len() - 1)
+            }
+            Err(e) => {
+                self.minibuffer.message(&format!("Cannot start {language} server: {e}"));
+                self.lsp_failed.insert(key);
+                None
+            }
+        }
+    }
+
+    /// Open visited files in their servers and send the edits made since
+    /// the last sync. Runs on every tick.
+    pub fn lsp_sync(&mut self) {
+        let docs: Vec<(usize, PathBuf)> = std::iter::once(&self.buffer).chain(self.buffers.iter())
+            .enumerate()
+            .filter(|(_, b)| b.file_path.as_deref().and_then(language_id).is_some())
+            .filter_map(|(i, b)| Some((i, b.absolute_path()?)))
+            .collect();
+
+        for (i, path) in docs {
+            let Some(ci) = self.lsp_client_index(&path) else { continue };
+            let buf = if i == 0 { &mut self.buffer } else { &mut self.buffers[i - 1] };
+            let client = &mut self.lsp_clients[ci];
+            let uri = path_to_uri(&path);
+
+            if !client.documents.contains(&path) {
+                buf.track_changes = true;
+                buf.take_changes();
+                client.documents.push(path);
+                let doc = json!({ "uri": uri, "languageId": client.language, "version": buf.version, "text": buf.text() });
+                client.notify("textDocument/didOpen", json!({ "textDocument": doc }));
+                continue;
+            }
+
+            // Open already, but through a buffer that has not tracked its
+            // edits (visited again, say): send it whole.
+            let resync = !buf.track_changes;
+            if resync {
+                buf.track_changes = true;
+                buf.take_changes();
+                buf.version += 1;
+            }
+            let changes = buf.take_changes();
+            if changes.is_empty() && !resync {
+                continue;
+            }
+            let content: Vec<Value> = if resync || client.wants_full_sync() || changes.iter().any(|c| c.range.is_none()) {
+                vec![json!({ "text": buf.text() })]
+            } else {
+                changes.into_iter().filter_map(|c| {
+                    let ((sl, sc), (el, ec)) = c.range?;
+                    Some(json!({
+                        "range": {
+                            "start": { "line": sl, "character": sc },
+                            "end": { "line": el, "character": ec },
+                        },
+                        "text": c.text,
+                    }))
+                }).collect()
+            };
+            client.notify("textDocument/didChange", json!({
+                "textDocument": { "uri": uri, "version": buf.version },
+                "contentChanges": content,
+            }));
+        }
+    }
+
+    pub fn lsp_did_save(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.lsp_sync();
+        if let Some(client) = self.lsp_clients.iter_mut().find(|c| c.documents.contains(&path)) {
+            client.notify("textDocument/didSave", json!({ "textDocument": { "uri": path_to_uri(&path) } }));
+        }
+    }
+
+    /// Tell the server a file's buffer is gone.
+    pub fn lsp_did_close(&mut self, path: &Path) {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if let Some(client) = self.lsp_clients.iter_mut().find(|c| c.documents.contains(&path)) {
+            client.documents.retain(|p| p != &path);
+            client.notify("textDocument/didClose", json!({ "textDocument": { "uri": path_to_uri(&path) } }));
+        }
+    }
+
+    /// Whether a server still owes an answer; the main loop polls faster
+    /// meanwhile.
+    pub fn lsp_busy(&self) -> bool {
+        self.lsp_clients.iter().any(|c| c.is_busy())
+    }
+
+    /// Handle what the servers sent since the last tick.
+    pub fn poll_lsp(&mut self) {
+        let mut events = Vec::new();
+        let mut dead = Vec::new();
+        for (i, client) in self.lsp_clients.iter_mut().enumerate() {
+            events.extend(client.poll());
+            if !client.is_alive() {
+                dead.push(i);
+            }
+        }
+        for i in dead.into_iter().rev() {
+            let client = self.lsp_clients.remove(i);
+            self.minibuffer.message(&format!("{} language server exited", client.language));
+            self.lsp_failed.insert((client.language, client.root));
+        }
+
+        for ev in events {
+            match ev {
+                LspEvent::Diagnostics { path, items } => self.lsp_store_diagnostics(path, items),
+                LspEvent::Message(text) => self.minibuffer.message(&text),
+                LspEvent::Error { message, .. } => self.minibuffer.message(&format!("LSP: {message}")),
+                LspEvent::Response { kind, result } => match kind {
+                    RequestKind::Definition => self.lsp_show_definition(result),
+                    RequestKind::References => self.lsp_show_references(result),
+                    RequestKind::Hover => self.lsp_show_hover(result),
+                    RequestKind::Rename => self.lsp_apply_workspace_edit(&result),
+                    RequestKind::Completion => self.lsp_show_completions(result),
+                    RequestKind::Initialize => {}
+                },
+            }
+        }
+    }
+
+    fn lsp_store_diagnostics(&mut self, path: PathBuf, mut items: Vec<Diagnostic>) {
+        if let Some(buf) = self.buffer_visiting(&path) {
+            for d in &mut items {
+                d.col = buf.char_col(d.col, d.line);
+                d.end_col = buf.char_col(d.end_col, d.end_line);
+            }
+        }
+        self.diagnostics.set(path, LSP_PROVIDER, items);
+    }
+
+    // ---- Requests ----
+    fn lsp_request_at_point(&mut self, method: &str, kind: RequestKind, extra: Value) {
+        self.lsp_sync();
+        let Some(path) = self.buffer.absolute_path() else {
+            self.minibuffer.message("Buffer is not visiting a file");
+            return;
+        };
+        let Some(client) = self.lsp_clients.iter_mut().find(|c| c.documents.contains(&path)) else {
+            self.minibuffer.message("No language server for this buffer");
+            return;
+        };
+        let mut params = json!({
+            "textDocument": { "uri": path_to_uri(&path) },
+            "position": position(&self.buffer),
+        });
+        if let (Some(p), Value::Object(extra)) = (params.as_object_mut(), extra) {
+            p.extend(extra);
+        }
+        client.request(method, params, kind);
+    }
+
+    pub fn lsp_goto_definition(&mut self) {
+        self.lsp_request_at_point("textDocument/definition", RequestKind::Definition, Value::Null);
+    }
+
+    pub fn lsp_find_references(&mut self) {
+        let extra = json!({ "context": { "includeDeclaration": true } });
+        self.lsp_request_at_point("textDocument/references", RequestKind::References, extra);
+    }
+
+    pub fn lsp_hover(&mut self) {
+        self.lsp_request_at_point("textDocument/hover", RequestKind::Hover, Value::Null);
+    }
+
+    pub fn lsp_rename(&mut self, new_name: &str) {
+        let extra = json!({ "newName": new_name });
+        self.lsp_request_at_point("textDocument/rename", RequestKind::Rename, extra);
+    }
+
+    pub fn lsp_complete(&mut self) {
+        self.lsp_request_at_point("textDocument/completion", RequestKind::Completion, Value::Null);
+    }
+
+    /// Stop the server of the current buffer; it is started again on the
+    /// next sync. Also retries servers that failed to start.
+    pub fn lsp_restart(&mut self) {
+        self.lsp_failed.clear();
+        let Some(path) = self.buffer.absolute_path() else { return };
+        let before = self.lsp_clients.len();
+        self.lsp_clients.retain(|c| !c.documents.contains(&path));
+        if self.lsp_clients.len() < before {
+            self.minibuffer.message("Restarting language server");
+        }
+    }
+
+    // ---- Responses ----
+    fn lsp_goto(&mut self, path: PathBuf, line: usize, col: usize) {
+        if let Err(e) = self.visit_file(path.clone()) {
+            self.minibuffer.message(&format!("Cannot open {}: {e}", path.display()));
+            return;
+        }
+        self.goto_line(line + 1);
+        self.buffer.cursor_x = self.buffer.char_col(col, self.buffer.cursor_y);
+        self.ensure_cursor_visible();
+    }
+
+    fn lsp_show_definition(&mut self, result: Value) {
+        let target = match &result {
+            Value::Array(items) => items.first().and_then(parse_location),
+            other => parse_location(other),
+        };
+        match target {
+            Some((path, line, col)) => self.lsp_goto(path, line, col),
+            None => self.minibuffer.message("No definition found"),
+        }
+    }
+
+    /// List references in `*references*`, which navigates like `*grep*`.
+    fn lsp_show_references(&mut self, result: Value) {
+        let locs: Vec<_> = result.as_array().map(|a| a.iter().filter_map(parse_location).collect()).unwrap_or_default();
+        if locs.is_empty() {
+            self.minibuffer.message("No references found");
+            return;
+        }
+
+        let root = self.project_directory();
+        let mut text = format!("References in {}\n\n", root.display());
+        let mut files: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        for (path, line, col) in &locs {
+            let lines = files.entry(path.clone()).or_insert_with(|| match self.buffer_visiting(path) {
+                Some(buf) => buf.lines.iter().map(|l| l.text.clone()).collect(),
+                None => std::fs::read_to_string(path).unwrap_or_default().lines().map(str::to_string).collect(),
+            });
+            let line_text = lines.get(*line).map_or("", |l| l.as_str());
+            let rel = path.strip_prefix(&root).unwrap_or(path);
+            text.push_str(&format!("{}:{}:{}: {}\n", rel.display(), line + 1, utf16_to_char_col(line_text, *col) + 1, line_text.trim()));
+        }
+
+        let buf = self.buffer_mut_or_create(REFERENCES_BUFFER);
+        buf.set_text_raw(&text);
+        buf.major_mode = GREP_MODE.to_string();
+        let mut list = LocationList::new(root, "references");
+        list.scan(buf, true);
+
+        self.locations.insert(REFERENCES_BUFFER.to_string(), list);
+        self.next_error_buffer = Some(REFERENCES_BUFFER.to_string());
+        self.switch_to_buffer(REFERENCES_BUFFER);
+        self.minibuffer.message(&format!("{} references", locs.len()));
+    }
+
+    /// The first line goes to the minibuffer; longer documentation is
+    /// kept in `*hover*`.
+    fn lsp_show_hover(&mut self, result: Value) {
+        let text = hover_text(&result["contents"]);
+        let Some(first) = text.lines().find(|l| !l.trim().is_empty()) else {
+            self.minibuffer.message("No documentation");
+            return;
+        };
+        let message = if text.lines().count() > 1 {
+            format!("{first}  [more in {HOVER_BUFFER}]")
+        } else {
+            first.to_string()
+        };
+        let buf = self.buffer_mut_or_create(HOVER_BUFFER);
+        buf.set_text_raw(&text);
+        buf.read_only = true;
+        self.minibuffer.message(&message);
+    }
+
+    fn lsp_apply_workspace_edit(&mut self, edit: &Value) {
+        let mut per_file: Vec<(PathBuf, Vec<Value>)> = Vec::new();
+        if let Some(changes) = edit["changes"].as_object() {
+            for (uri, edits) in changes {
+                if let (Some(path), Some(edits)) = (uri_to_path(uri), edits.as_array()) {
+                    per_file.push((path, edits.clone()));
+                }
+            }
+        }
+        if let Some(doc_changes) = edit["documentChanges"].as_array() {
+            // File creations, renames and deletions are not supported.
+            for change in doc_changes {
+                let uri = change["textDocument"]["uri"].as_str().and_then(uri_to_path);
+                if let (Some(path), Some(edits)) = (uri, change["edits"].as_array()) {
+                    per_file.push((path, edits.clone()));
+                }
+            }
+        }
+        if per_file.is_empty() {
+            self.minibuffer.message("Nothing to change");
+            return;
+        }
+
+        // All or nothing: a rename applied to some of the files would
+        // leave the others referring to the old name.
+        for (path, _) in &per_file {
+            if let Err(e) = self.lsp_edit_target(path) {
+                self.minibuffer.message(&format!("Cannot edit {}: {e}; nothing changed", path.display()));
+                return;
+            }
+        }
+        for (path, edits) in &per_file {
+            self.lsp_apply_text_edits(path, edits);
+        }
+        self.ensure_cursor_visible();
+        self.minibuffer.message(&format!("Changed {} file(s); save to keep the changes", per_file.len()));
+    }
+
+    /// Make sure a buffer visits `path`, reading the file into a new
+    /// (hidden) one if none does, and that it can be edited.
+    fn lsp_edit_target(&mut self, path: &Path) -> io::Result<()> {
+        if self.buffer_visiting(path).is_none() {
+            let mut buf = Buffer::new();
+            buf.open_file(path.to_path_buf())?;
+            self.name_new_buffer(&mut buf);
+            self.buffers.push(buf);
+        }
+        match self.buffer_visiting(path) {
+            Some(buf) if buf.read_only => Err(io::Error::new(io::ErrorKind::PermissionDenied, "buffer is read-only")),
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply `TextEdit`s to the buffer visiting `path`; see
+    /// `lsp_edit_target`.
+    fn lsp_apply_text_edits(&mut self, path: &Path, edits: &[Value]) {
+        let Some(buf) = self.buffer_visiting_mut(path) else { return };
+
+        let mut edits: Vec<(Range, String)> = edits.iter()
+            .filter_map(|e| Some((parse_range(&e["range"])?, e["newText"].as_str()?.to_string())))
+            .collect();
+        // Bottom-up, so the ranges still to apply stay valid.
+        edits.sort_by_key(|e| std::cmp::Reverse(e.0.0));
+
+        let cursor = (buf.cursor_x, buf.cursor_y);
+        let undo_from = buf.undo_len();
+        for (((sl, sc), (el, ec)), text) in edits {
+            let start = (buf.char_col(sc, sl), sl);
+            let end = (buf.char_col(ec, el), el);
+            buf.replace_range(start, end, &text);
+        }
+        buf.group_undo(undo_from);
+        buf.cursor_y = cursor.1.min(buf.lines.len() - 1);
+        buf.cursor_x = cursor.0.min(buf.lines[buf.cursor_y].char_len);
+    }
+
+    fn lsp_show_completions(&mut self, result: Value) {
+        let items = match &result {
+            Value::Array(items) => items.clone(),
+            other => other["items"].as_array().cloned().unwrap_or_default(),
+        };
+        let mut labels = Vec::new();
+        let mut by_label: HashMap<String, Value> = HashMap::new();
+        for item in items {
+            let Some(label) = item["label"].as_str().map(str::to_string) else { continue };
+            if let Entry::Vacant(slot) = by_label.entry(label.clone()) {
+                labels.push(label);
+                slot.insert(item);
+            }
+        }
+        if labels.is_empty() {
+            self.minibuffer.message("No completions");
+            return;
+        }
+        self.completing_read("Complete: ", labels, move |ed, label| {
+            if let Some(item) = by_label.get(&label) {
+                ed.lsp_insert_completion(item);
+            }
+        });
+    }
+
+    fn lsp_insert_completion(&mut self, item: &Value) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let buf = &mut self.buffer;
+        let edit = &item["textEdit"];
+        if let Some(text) = edit["newText"].as_str() {
+            // `TextEdit` or `InsertReplaceEdit`.
+            let range = if edit["range"].is_object() { &edit["range"] } else { &edit["replace"] };
+            if let Some(((sl, sc), (el, ec))) = parse_range(range) {
+                let start = (buf.char_col(sc, sl), sl);
+                let end = (buf.char_col(ec, el), el);
+                buf.replace_range(start, end, text);
+                self.ensure_cursor_visible();
+                return;
+            }
+        }
+
+        let text = item["insertText"].as_str().or(item["label"].as_str()).unwrap_or("");
+        // Replace the identifier the server completed.
+        let (x, y) = (buf.cursor_x, buf.cursor_y);
+        let chars: Vec<char> = buf.lines[y].text.chars().collect();
+        let mut start = x;
+        while start > 0 && (chars[start - 1].is_alphanumeric() || chars[start - 1] == '_') {
+            start -= 1;
+        }
+        buf.replace_range((start, y), (x, y), text);
+        self.ensure_cursor_visible();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::command::CommandRegistry;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+
+    type Sent = Rc<RefCell<Vec<Value>>>;
+
+    /// An editor visiting `main.rs` holding `text`, in a fresh directory.
+    fn visiting(name: &str, text: &str) -> (Editor, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("remux-lsp-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.rs");
+        std::fs::write(&path, text).unwrap();
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let mut ed = Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(UserConfig::default())));
+        ed.visit_file(path.clone()).unwrap();
+        (ed, path.canonicalize().unwrap())
+    }
+
+    /// Serve rust files under `path`'s directory from `script`.
+    fn serve(ed: &mut Editor, path: &Path, script: Value) -> Sent {
+        let transport = ScriptedTransport::new(script);
+        let sent = transport.sent.clone();
+        let dir = path.parent().unwrap().to_path_buf();
+        let root = ed.project_root_of(&dir).unwrap_or(dir);
+        ed.lsp_clients.push(LspClient::start("rust", root, Box::new(transport)));
+        sent
+    }
+
+    fn methods(sent: &Sent) -> Vec<String> {
+        sent.borrow().iter().filter_map(|m| m["method"].as_str().map(str::to_string)).collect()
+    }
+
+    fn last(sent: &Sent) -> Value {
+        sent.borrow().last().cloned().unwrap()
+    }
+
+    fn cleanup(path: &Path) {
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn documents_open_once_then_change() {
+        let (mut ed, path) = visiting("sync", "fn main() {}");
+        let sent = serve(&mut ed, &path, json!({
+            "initialize": { "result": { "capabilities": { "textDocumentSync": 2 } } },
+        }));
+
+        // Held back until the server has answered `initialize`.
+        ed.lsp_sync();
+        assert_eq!(methods(&sent), ["initialize"]);
+        ed.poll_lsp();
+        assert_eq!(methods(&sent), ["initialize", "initialized", "textDocument/didOpen"]);
+        let open = last(&sent);
+        assert_eq!(open["params"]["textDocument"]["uri"], path_to_uri(&path));
+        assert_eq!(open["params"]["textDocument"]["text"], "fn main() {}");
+
+        ed.buffer.insert_text_at(0, 0, "pub ");
+        ed.lsp_sync();
+        let change = last(&sent);
+        assert_eq!(change["method"], "textDocument/didChange");
+        assert_eq!(change["params"]["contentChanges"], json!([{
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+            "text": "pub ",
+        }]));
+
+        // Nothing changed, nothing sent.
+        let before = sent.borrow().len();
+        ed.lsp_sync();
+        assert_eq!(sent.borrow().len(), before);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn open_document_in_a_new_buffer_is_sent_whole() {
+        let (mut ed, path) = visiting("resync", "fn main() {}");
+        let sent = serve(&mut ed, &path, json!({ "initialize": { "result": { "capabilities": {} } } }));
+        ed.lsp_sync();
+        ed.poll_lsp();
+        let version = last(&sent)["params"]["textDocument"]["version"].as_i64().unwrap();
+
+        let mut again = Buffer::new();
+        again.open_file(path.clone()).unwrap();
+        again.insert_text_at(0, 0, "// x\n");
+        ed.buffer = again;
+        ed.lsp_sync();
+
+        assert_eq!(methods(&sent).iter().filter(|m| *m == "textDocument/didOpen").count(), 1);
+        let change = last(&sent);
+        assert_eq!(change["method"], "textDocument/didChange");
+        assert_eq!(change["params"]["contentChanges"], json!([{ "text": "// x\nfn main() {}" }]));
+        assert!(change["params"]["textDocument"]["version"].as_i64().unwrap() > version);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn definition_moves_to_the_answer() {
+        let (mut ed, path) = visiting("definition", "fn main() { helper() }\nfn helper() {}");
+        let sent = serve(&mut ed, &path, json!({
+            "initialize": { "result": { "capabilities": {} } },
+            "textDocument/definition": { "result": [{
+                "uri": path_to_uri(&path),
+                "range": { "start": { "line": 1, "character": 3 }, "end": { "line": 1, "character": 9 } },
+            }] },
+        }));
+        ed.lsp_sync();
+        ed.poll_lsp();
+
+        ed.buffer.cursor_x = 13;
+        ed.lsp_goto_definition();
+        let request = last(&sent);
+        assert_eq!(request["method"], "textDocument/definition");
+        assert_eq!(request["params"]["position"], json!({ "line": 0, "character": 13 }));
+        assert!(ed.lsp_busy());
+
+        ed.poll_lsp();
+        assert!(!ed.lsp_busy());
+        assert_eq!((ed.buffer.cursor_x, ed.buffer.cursor_y), (3, 1));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn rename_leaves_read_only_buffers_alone() {
+        let (mut ed, path) = visiting("rename", "fn main() {}");
+        let edit = json!({ "changes": { path_to_uri(&path): [{
+            "range": { "start": { "line": 0, "character": 3 }, "end": { "line": 0, "character": 7 } },
+            "newText": "start",
+        }] } });
+        serve(&mut ed, &path, json!({
+            "initialize": { "result": { "capabilities": {} } },
+            "textDocument/rename": { "result": edit },
+        }));
+        ed.lsp_sync();
+        ed.poll_lsp();
+
+        ed.buffer.read_only = true;
+        ed.lsp_rename("start");
+        ed.poll_lsp();
+        assert_eq!(ed.buffer.text(), "fn main() {}");
+        assert!(!ed.buffer.is_modified());
+
+        ed.buffer.read_only = false;
+        ed.lsp_rename("start");
+        ed.poll_lsp();
+        assert_eq!(ed.buffer.text(), "fn start() {}");
+        cleanup(&path);
+    }
+
+    #[test]
+    fn rename_with_a_read_only_target_changes_nothing() {
+        let (mut ed, path) = visiting("rename-all", "fn main() { helper() }");
+        let lib = path.with_file_name("lib.rs");
+        std::fs::write(&lib, "fn helper() {}").unwrap();
+        let range = |a, b| json!({ "start": { "line": 0, "character": a }, "end": { "line": 0, "character": b } });
+        let edit = json!({ "changes": {
+            path_to_uri(&path): [{ "range": range(12, 18), "newText": "assist" }],
+            path_to_uri(&lib): [{ "range": range(3, 9), "newText": "assist" }],
+        } });
+        serve(&mut ed, &path, json!({
+            "initialize": { "result": { "capabilities": {} } },
+            "textDocument/rename": { "result": edit },
+        }));
+        ed.lsp_sync();
+        ed.poll_lsp();
+
+        ed.visit_file(lib.clone()).unwrap();
+        ed.buffer.read_only = true;
+        ed.switch_to_buffer("main.rs");
+        ed.buffer.cursor_x = 12;
+        ed.lsp_rename("assist");
+        ed.poll_lsp();
+        assert_eq!(ed.buffer.text(), "fn main() { helper() }");
+        assert!(!ed.buffer.is_modified());
+        assert!(ed.minibuffer.get().contains("lib.rs: buffer is read-only; nothing changed"));
+        cleanup(&path);
+    }
+
+    #[test]
+    fn rename_undoes_in_one_step() {
+        let (mut ed, path) = visiting("rename-undo", "fn main() { main() }");
+        let range = |a, b| json!({ "start": { "line": 0, "character": a }, "end": { "line": 0, "character": b } });
+        let edit = json!({ "changes": { path_to_uri(&path): [
+            { "range": range(3, 7), "newText": "start" },
+            { "range": range(12, 16), "newText": "start" },
+        ] } });
+        serve(&mut ed, &path, json!({
+            "initialize": { "result": { "capabilities": {} } },
+            "textDocument/rename": { "result": edit },
+        }));
+        ed.lsp_sync();
+        ed.poll_lsp();
+
+        ed.lsp_rename("start");
+        ed.poll_lsp();
+        assert_eq!(ed.buffer.text(), "fn start() { start() }");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), "fn main() { main() }");
+        cleanup(&path);
+    }
+}
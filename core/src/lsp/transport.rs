@@ -0,0 +1,223 @@
+// core/src/lsp/transport.rs
+//
+// How JSON-RPC messages reach a server. `StdioTransport` talks to a child
+// process with `Content-Length` framing and reads and writes on threads of
+// its own;
+// `ScriptedTransport` is an in-process fake that answers from a JSON script,
+// so the client can be exercised without a real server.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use serde_json::{json, Value};
+use crate::process::feed;
+
+pub trait Transport {
+    fn send(&mut self, msg: &Value) -> io::Result<()>;
+    /// Messages received since the last call. Never blocks.
+    fn poll(&mut self) -> Vec<Value>;
+    fn is_alive(&mut self) -> bool;
+}
+
+/// `Content-Length: N\r\n\r\n<json>`
+pub fn encode_message(msg: &Value) -> Vec<u8> {
+    let body = msg.to_string();
+    let mut out = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    out.extend_from_slice(body.as_bytes());
+    out
+}
+
+/// Read one framed message; `None` at end of stream.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length") {
+            len = value.trim().parse::<usize>().ok();
+        }
+    }
+    let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// ---- Child process ----
+pub struct StdioTransport {
+    child: Child,
+    /// Feeds the writer thread, so a server slow to read never blocks the
+    /// editor.
+    stdin: Sender<Vec<u8>>,
+    rx: Receiver<Value>,
+}
+
+impl StdioTransport {
+    pub fn spawn(program: &str, args: &[String], cwd: &Path) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::other("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::other("no stdout"))?;
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(msg)) = read_message(&mut reader) {
+                if tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { child, stdin: feed(stdin), rx })
+    }
+}
+
+impl Transport for StdioTransport {
+    fn send(&mut self, msg: &Value) -> io::Result<()> {
+        self.stdin.send(encode_message(msg))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "server stdin is closed"))
+    }
+
+    fn poll(&mut self) -> Vec<Value> {
+        self.rx.try_iter().collect()
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        let _ = self.send(&json!({ "jsonrpc": "2.0", "id": 0, "method": "shutdown" }));
+        let _ = self.send(&json!({ "jsonrpc": "2.0", "method": "exit" }));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ---- Scripted fake server ----
+
+/// A fake server driven by a JSON object keyed by method name:
+///
+/// ```json
+/// {
+///   "initialize": { "result": { "capabilities": { "hoverProvider": true } } },
+///   "textDocument/hover": { "result": { "contents": "fn main()" } },
+///   "textDocument/didOpen": { "notify": [
+///     { "method": "textDocument/publishDiagnostics",
+///       "params": { "uri": "${uri}", "diagnostics": [] } } ] }
+/// }
+/// ```
+///
+/// Requests are answered with `result` (or `error`), `null` when the method
+/// is not in the script. `notify` messages are sent after any message with
+/// that method; `"${uri}"` stands for the document the message was about.
+/// Everything the client sends is kept in `sent`, which is shared so it can
+/// still be read once the transport belongs to a client.
+pub struct ScriptedTransport {
+    script: Value,
+    outbox: VecDeque<Value>,
+    pub sent: Rc<RefCell<Vec<Value>>>,
+}
+
+impl ScriptedTransport {
+    pub fn new(script: Value) -> Self {
+        Self { script, outbox: VecDeque::new(), sent: Rc::new(RefCell::new(Vec::new())) }
+    }
+
+    pub fn load(path: &PathBuf) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let script = serde_json::from_str(&text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::new(script))
+    }
+}
+
+fn substitute(value: &Value, uri: &str) -> Value {
+    match value {
+        Value::String(s) if s == "${uri}" => Value::String(uri.to_string()),
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, uri)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), substitute(v, uri))).collect()),
+        other => other.clone(),
+    }
+}
+
+impl Transport for ScriptedTransport {
+    fn send(&mut self, msg: &Value) -> io::Result<()> {
+        self.sent.borrow_mut().push(msg.clone());
+        let Some(method) = msg["method"].as_str() else {
+            // A reply to one of our own requests; the script has none.
+            return Ok(());
+        };
+        let entry = self.script.get(method).cloned().unwrap_or(Value::Null);
+        let uri = msg["params"]["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+
+        if let Some(id) = msg.get("id") {
+            let reply = match entry.get("error") {
+                Some(err) => json!({ "jsonrpc": "2.0", "id": id, "error": err }),
+                None => json!({ "jsonrpc": "2.0", "id": id, "result": entry.get("result").cloned().unwrap_or(Value::Null) }),
+            };
+            self.outbox.push_back(reply);
+        }
+        if let Some(Value::Array(notes)) = entry.get("notify") {
+            for note in notes {
+                let mut note = substitute(note, &uri);
+                note["jsonrpc"] = json!("2.0");
+                self.outbox.push_back(note);
+            }
+        }
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Vec<Value> {
+        self.outbox.drain(..).collect()
+    }
+
+    fn is_alive(&mut self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn sending_to_a_server_not_reading_does_not_block() {
+        let mut transport = StdioTransport::spawn("sleep", &["10".to_string()], Path::new(".")).unwrap();
+        let start = Instant::now();
+        // Far more than a pipe holds.
+        let text = "x".repeat(1 << 20);
+        transport.send(&json!({ "jsonrpc": "2.0", "method": "x", "params": text })).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let msg = json!({ "jsonrpc": "2.0", "id": 1, "result": "ünïcode" });
+        let mut reader = io::Cursor::new(encode_message(&msg));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(msg));
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
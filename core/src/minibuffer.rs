@@ -1,3 +1,10 @@
+use std::collections::HashMap;
+use crate::completion::Completions;
+use crate::log::SharedLog;
+
+/// Entries kept per history.
+const HISTORY_LEN: usize = 100;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MiniBufferMode {
     Inactive,
@@ -5,6 +12,7 @@ pub enum MiniBufferMode {
     FindFile,   // waiting path
     SaveBuffer,
     GotoLine,
+    Prompt,     // string argument for `pending_command`
     ISearchForward,
     ISearchBackward,
     Message { ttl: u8 },    // just a message
@@ -12,47 +20,72 @@ pub enum MiniBufferMode {
 
 pub struct MiniBuffer {
     text: String,
+    prompt_len: usize,
     active: bool,
     mode: MiniBufferMode,
+    /// Set while a completing prompt is open.
+    completions: Option<Completions>,
+    /// Past inputs of each prompt, oldest first.
+    history: HashMap<String, Vec<String>>,
+    /// Entry of the current prompt's history being shown, while stepping.
+    history_pos: Option<usize>,
+    /// Messages are recorded here (see `log.rs`).
+    log: SharedLog,
 }
 
 impl Default for MiniBuffer {
     fn default() -> Self {
+        Self::new(SharedLog::default())
+    }
+}
+
+impl MiniBuffer {
+    pub fn new(log: SharedLog) -> Self {
         Self {
             text: String::new(),
+            prompt_len: 0,
             active: false,
             mode: MiniBufferMode::Message { ttl: 0 },
+            completions: None,
+            history: HashMap::new(),
+            history_pos: None,
+            log,
         }
     }
-}
 
-impl MiniBuffer {
-    
     pub fn activate(&mut self, prompt: &str, mode: MiniBufferMode) {
         self.text.clear();
         self.text.push_str(prompt);
 	self.text = self.text.to_string();
+        self.prompt_len = self.text.len();
         self.active = true;
         self.mode = mode;
+        self.completions = None;
+        self.history_pos = None;
     }
 
     pub fn deactivate(&mut self) {
         self.text.clear();
+        self.prompt_len = 0;
         self.active = false;
         self.mode = MiniBufferMode::Message { ttl: 0 };
+        self.completions = None;
     }
     
    pub fn set_text<S: Into<String>>(&mut self, text: S) {
         self.text = text.into();
+        self.prompt_len = self.prompt_len.min(self.text.len());
    }
 
      pub fn message(&mut self, text: &str) {
+        self.log.borrow_mut().info(text);
         self.activate(text, MiniBufferMode::Message { ttl: 2 });
      }
 
     
     pub fn clear(&mut self) {
         self.text.clear();
+        self.prompt_len = 0;
         self.mode = MiniBufferMode::Inactive;
     }
 
@@ -69,16 +102,122 @@ impl MiniBuffer {
     
     pub fn push(&mut self, c: char) {
         self.text.push(c);
+        self.refresh_completions();
+    }
+
+    /// Pre-fill the input after the prompt, e.g. with a default value.
+    pub fn push_str(&mut self, s: &str) {
+        self.text.push_str(s);
+        self.refresh_completions();
     }
 
     pub fn pop(&mut self) {
-        self.text.pop();
+        if self.text.len() > self.prompt_len {
+            self.text.pop();
+            self.refresh_completions();
+        }
+    }
+
+    // ---- Completion ----
+    pub fn set_completions(&mut self, candidates: Vec<String>) {
+        self.completions = Some(Completions::new(candidates));
+        self.refresh_completions();
+    }
+
+    pub fn completions(&self) -> Option<&Completions> {
+        self.completions.as_ref()
+    }
+
+    fn refresh_completions(&mut self) {
+        let input = self.input().to_string();
+        if let Some(c) = self.completions.as_mut() {
+            c.update(&input);
+        }
+    }
+
+    pub fn cycle_completion(&mut self, delta: isize) {
+        if let Some(c) = self.completions.as_mut() {
+            c.cycle(delta);
+        }
+    }
+
+    /// Replace the input with the selected candidate.
+    pub fn complete(&mut self) {
+        let Some(choice) = self.completions.as_ref().and_then(|c| c.selected()) else { return };
+        let choice = choice.to_string();
+        self.text.truncate(self.prompt_len);
+        self.text.push_str(&choice);
+        self.refresh_completions();
+    }
+
+    /// What submitting `input` means: the input itself when it names a
+    /// candidate (or is empty), else the selected match.
+    pub fn completion_choice(&self, input: &str) -> Option<String> {
+        let c = self.completions.as_ref()?;
+        if input.is_empty() || c.is_candidate(input) {
+            return None;
+        }
+        c.selected().map(str::to_string)
+    }
+
+    // ---- History ----
+    /// The prompt of the open minibuffer, which names its history.
+    pub fn prompt(&self) -> &str {
+        self.text.get(..self.prompt_len).unwrap_or("")
+    }
+
+    /// Remember `input` in the history of the open prompt.
+    pub fn add_history(&mut self, input: &str) {
+        if input.is_empty() {
+            return;
+        }
+        let prompt = self.prompt().to_string();
+        let list = self.history.entry(prompt).or_default();
+        list.retain(|h| h != input);
+        list.push(input.to_string());
+        if list.len() > HISTORY_LEN {
+            list.remove(0);
+        }
+    }
+
+    /// Replace the input with an older (`delta < 0`) or newer entry of the
+    /// prompt's history. Stepping past the newest one clears the input.
+    pub fn history_step(&mut self, delta: isize) {
+        let Some(list) = self.history.get(self.prompt()) else { return };
+        let len = list.len() as isize;
+        let pos = self.history_pos.map_or(len, |p| p as isize) + delta;
+        if pos < 0 || len == 0 {
+            return;
+        }
+        let entry = if pos >= len {
+            self.history_pos = None;
+            String::new()
+        } else {
+            self.history_pos = Some(pos as usize);
+            list[pos as usize].clone()
+        };
+        self.text.truncate(self.prompt_len);
+        self.text.push_str(&entry);
+        self.refresh_completions();
+    }
+
+    pub fn histories(&self) -> &HashMap<String, Vec<String>> {
+        &self.history
+    }
+
+    pub fn set_histories(&mut self, history: HashMap<String, Vec<String>>) {
+        self.history = history;
     }
 
     pub fn get(&self) -> &str {
         &self.text
     }
 
+    /// Text typed after the prompt.
+    pub fn input(&self) -> &str {
+        self.text.get(self.prompt_len..).unwrap_or("")
+    }
+
     pub fn mode(&self) -> MiniBufferMode {
         self.mode
     }
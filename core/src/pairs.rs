@@ -0,0 +1,375 @@
+// core/src/pairs.rs
+//
+// Brackets and quotes: `electric-pair-mode` (typing an opener inserts its
+// closer, typing a closer steps over an existing one, deleting an empty pair
+// removes both halves, an opener typed with a region wraps it),
+// `show-paren-mode` (the front end highlights the bracket matching the one
+// at the cursor) and the `forward-sexp` / `backward-sexp` motions.
+//
+// Pairs come from `UserConfig::pairs`. Pairs whose halves differ are
+// brackets and nest; quotes are symmetric and do not. Matching does not
+// know about strings or comments.
+
+use crate::buffer::{Buffer, Position};
+use crate::editor::editor::Editor;
+
+/// Lines searched for a match before giving up.
+const MATCH_LIMIT: usize = 5000;
+
+pub fn default_pairs() -> Vec<(char, char)> {
+    vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')]
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn char_at(buffer: &Buffer, x: usize, y: usize) -> Option<char> {
+    buffer.lines.get(y)?.text.chars().nth(x)
+}
+
+/// Position of the bracket closing the one at `from`, or `None` when it is
+/// missing or a different closer comes first.
+pub fn match_forward(buffer: &Buffer, from: Position, pairs: &[(char, char)]) -> Option<Position> {
+    let mut expected = Vec::new();
+    for y in from.y..buffer.lines.len().min(from.y + MATCH_LIMIT) {
+        let skip = if y == from.y { from.x } else { 0 };
+        for (x, c) in buffer.lines[y].text.chars().enumerate().skip(skip) {
+            if let Some(&(_, close)) = pairs.iter().find(|(o, cl)| *o == c && o != cl) {
+                expected.push(close);
+            } else if pairs.iter().any(|(o, cl)| *cl == c && o != cl) {
+                if expected.pop() != Some(c) {
+                    return None;
+                }
+                if expected.is_empty() {
+                    return Some(Position { x, y });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Position of the bracket opening the one at `from`; see `match_forward`.
+pub fn match_backward(buffer: &Buffer, from: Position, pairs: &[(char, char)]) -> Option<Position> {
+    let mut expected = Vec::new();
+    for y in (from.y.saturating_sub(MATCH_LIMIT)..=from.y).rev() {
+        let chars: Vec<char> = buffer.lines[y].text.chars().collect();
+        let end = if y == from.y { from.x + 1 } else { chars.len() };
+        for x in (0..end.min(chars.len())).rev() {
+            let c = chars[x];
+            if let Some(&(open, _)) = pairs.iter().find(|(o, cl)| *cl == c && o != cl) {
+                expected.push(open);
+            } else if pairs.iter().any(|(o, cl)| *o == c && o != cl) {
+                if expected.pop() != Some(c) {
+                    return None;
+                }
+                if expected.is_empty() {
+                    return Some(Position { x, y });
+                }
+            }
+        }
+    }
+    None
+}
+
+// ---- Editor integration ----
+impl Editor {
+    fn pairs(&self) -> Vec<(char, char)> {
+        self.user_config.borrow().pairs.clone()
+    }
+
+    /// Self-insert `ch` as `electric-pair-mode` would. Returns false when
+    /// the mode is off or `ch` is not part of a pair, leaving the insertion
+    /// to the caller.
+    pub fn electric_pair(&mut self, ch: char) -> bool {
+        if !self.user_config.borrow().electric_pair {
+            return false;
+        }
+        let pairs = self.pairs();
+        let (x, y) = (self.buffer.cursor_x, self.buffer.cursor_y);
+        let next = char_at(&self.buffer, x, y);
+        let prev = x.checked_sub(1).and_then(|px| char_at(&self.buffer, px, y));
+
+        // Step over the closer already there.
+        if pairs.iter().any(|(_, close)| *close == ch) && next == Some(ch) {
+            self.buffer.cursor_x += 1;
+            return true;
+        }
+        let Some(&(open, close)) = pairs.iter().find(|(o, _)| *o == ch) else {
+            return false;
+        };
+
+        if let Some(sel) = self.buffer.selection().filter(|s| s.start != s.end) {
+            let at_end = (y, x) == (sel.end.y, sel.end.x);
+            let undo_from = self.buffer.undo_len();
+            self.buffer.insert_text_at(sel.end.x, sel.end.y, &close.to_string());
+            self.buffer.insert_text_at(sel.start.x, sel.start.y, &open.to_string());
+            self.buffer.group_undo(undo_from);
+            // The cursor keeps its side of the region, outside the pair.
+            if at_end {
+                let shift = (sel.start.y == sel.end.y) as usize;
+                self.buffer.cursor_x = sel.end.x + 1 + shift;
+                self.buffer.cursor_y = sel.end.y;
+            } else {
+                self.buffer.cursor_x = sel.start.x;
+                self.buffer.cursor_y = sel.start.y;
+            }
+            self.buffer.clear_mark();
+            self.buffer.visual.dirty = true;
+            return true;
+        }
+
+        // Only pair before whitespace, a closer or the end of the line, and
+        // never open a quote right after a word (`don't`).
+        let before_ok = next.is_none_or(|c| c.is_whitespace() || pairs.iter().any(|(_, cl)| *cl == c));
+        let quote = open == close;
+        if !before_ok || (quote && prev.is_some_and(is_word)) {
+            return false;
+        }
+        self.buffer.insert_text_at(x, y, &format!("{open}{close}"));
+        self.buffer.cursor_x = x + 1;
+        self.buffer.visual.dirty = true;
+        true
+    }
+
+    /// Delete the pair around the cursor when it is empty. Returns false
+    /// when there is none, or the mode is off.
+    pub fn electric_pair_delete(&mut self) -> bool {
+        if !self.user_config.borrow().electric_pair {
+            return false;
+        }
+        let (x, y) = (self.buffer.cursor_x, self.buffer.cursor_y);
+        let Some(px) = x.checked_sub(1) else { return false };
+        let (prev, next) = (char_at(&self.buffer, px, y), char_at(&self.buffer, x, y));
+        let empty_pair = self.pairs().iter().any(|&(o, c)| prev == Some(o) && next == Some(c));
+        if empty_pair {
+            self.buffer.delete_range(px, y, x + 1, y);
+        }
+        empty_pair
+    }
+
+    /// With `show-paren-mode` on, the bracket at the cursor (or just before
+    /// it, when that is a closer) and its match.
+    pub fn matching_paren(&self) -> Option<(Position, Position)> {
+        if !self.user_config.borrow().show_paren {
+            return None;
+        }
+        let pairs = self.pairs();
+        let is_open = |c: char| pairs.iter().any(|(o, cl)| *o == c && o != cl);
+        let is_close = |c: char| pairs.iter().any(|(o, cl)| *cl == c && o != cl);
+        let (x, y) = (self.buffer.cursor_x, self.buffer.cursor_y);
+
+        if char_at(&self.buffer, x, y).is_some_and(is_open) {
+            let at = Position { x, y };
+            return Some((at, match_forward(&self.buffer, at, &pairs)?));
+        }
+        let px = x.checked_sub(1)?;
+        if char_at(&self.buffer, px, y).is_some_and(is_close) {
+            let at = Position { x: px, y };
+            return Some((at, match_backward(&self.buffer, at, &pairs)?));
+        }
+        None
+    }
+
+    /// Move over `n` balanced expressions (backwards when negative): a
+    /// bracketed group, a quoted string, a word, or a single character.
+    pub fn forward_sexp(&mut self, n: isize) {
+        let pairs = self.pairs();
+        for _ in 0..n.unsigned_abs() {
+            let moved = if n > 0 {
+                self.sexp_forward_once(&pairs)
+            } else {
+                self.sexp_backward_once(&pairs)
+            };
+            if let Err(msg) = moved {
+                self.minibuffer.message(msg);
+                break;
+            }
+        }
+        self.ensure_cursor_visible();
+    }
+
+    fn sexp_forward_once(&mut self, pairs: &[(char, char)]) -> Result<(), &'static str> {
+        let buf = &mut self.buffer;
+        // Skip whitespace, across lines.
+        let c = loop {
+            match char_at(buf, buf.cursor_x, buf.cursor_y) {
+                Some(c) if c.is_whitespace() => buf.cursor_x += 1,
+                Some(c) => break c,
+                None if buf.cursor_y + 1 < buf.lines.len() => {
+                    buf.cursor_y += 1;
+                    buf.cursor_x = 0;
+                }
+                None => return Err("End of buffer"),
+            }
+        };
+        let here = Position { x: buf.cursor_x, y: buf.cursor_y };
+        if pairs.iter().any(|(o, cl)| *cl == c && o != cl) {
+            return Err("Containing expression ends prematurely");
+        }
+        let end = if pairs.iter().any(|(o, cl)| *o == c && o != cl) {
+            match_forward(buf, here, pairs).ok_or("Unbalanced parentheses")?
+        } else if pairs.iter().any(|(o, cl)| *o == c && o == cl) {
+            let line: Vec<char> = buf.lines[here.y].text.chars().collect();
+            let close = (here.x + 1..line.len()).find(|&i| line[i] == c && line[i - 1] != '\\');
+            Position { x: close.ok_or("Unbalanced quotes")?, y: here.y }
+        } else if is_word(c) {
+            let len = buf.lines[here.y].text.chars().skip(here.x).take_while(|c| is_word(*c)).count();
+            Position { x: here.x + len - 1, y: here.y }
+        } else {
+            here
+        };
+        buf.cursor_x = end.x + 1;
+        buf.cursor_y = end.y;
+        Ok(())
+    }
+
+    fn sexp_backward_once(&mut self, pairs: &[(char, char)]) -> Result<(), &'static str> {
+        let buf = &mut self.buffer;
+        // Skip whitespace before the cursor, across lines.
+        let c = loop {
+            if buf.cursor_x == 0 {
+                if buf.cursor_y == 0 {
+                    return Err("Beginning of buffer");
+                }
+                buf.cursor_y -= 1;
+                buf.cursor_x = buf.lines[buf.cursor_y].char_len;
+                continue;
+            }
+            match char_at(buf, buf.cursor_x - 1, buf.cursor_y) {
+                Some(c) if c.is_whitespace() => buf.cursor_x -= 1,
+                Some(c) => break c,
+                None => buf.cursor_x -= 1,
+            }
+        };
+        let here = Position { x: buf.cursor_x - 1, y: buf.cursor_y };
+        if pairs.iter().any(|(o, cl)| *o == c && o != cl) {
+            return Err("Containing expression ends prematurely");
+        }
+        let start = if pairs.iter().any(|(o, cl)| *cl == c && o != cl) {
+            match_backward(buf, here, pairs).ok_or("Unbalanced parentheses")?
+        } else if pairs.iter().any(|(o, cl)| *o == c && o == cl) {
+            let line: Vec<char> = buf.lines[here.y].text.chars().collect();
+            let open = (0..here.x).rev().find(|&i| line[i] == c && (i == 0 || line[i - 1] != '\\'));
+            Position { x: open.ok_or("Unbalanced quotes")?, y: here.y }
+        } else if is_word(c) {
+            let line: Vec<char> = buf.lines[here.y].text.chars().collect();
+            let len = line[..=here.x].iter().rev().take_while(|c| is_word(**c)).count();
+            Position { x: here.x + 1 - len, y: here.y }
+        } else {
+            here
+        };
+        buf.cursor_x = start.x;
+        buf.cursor_y = start.y;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::command::CommandRegistry;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+
+    fn editor(text: &str) -> Editor {
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let config = UserConfig { electric_pair: true, ..UserConfig::default() };
+        let mut ed = Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(config)));
+        ed.buffer.set_text_raw(text);
+        ed
+    }
+
+    fn buffer(text: &str) -> Buffer {
+        let mut buf = Buffer::new();
+        buf.set_text_raw(text);
+        buf
+    }
+
+    fn at(x: usize, y: usize) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn matches_skip_nested_pairs() {
+        let pairs = default_pairs();
+        let buf = buffer("f(a[0], (b))\n{\n  g([x])\n}");
+        assert!(match_forward(&buf, at(1, 0), &pairs) == Some(at(11, 0)));
+        assert!(match_backward(&buf, at(11, 0), &pairs) == Some(at(1, 0)));
+        assert!(match_forward(&buf, at(8, 0), &pairs) == Some(at(10, 0)));
+        // Across lines, past a line that nests its own pairs.
+        assert!(match_forward(&buf, at(0, 1), &pairs) == Some(at(0, 3)));
+        assert!(match_backward(&buf, at(0, 3), &pairs) == Some(at(0, 1)));
+    }
+
+    #[test]
+    fn mismatched_closer_has_no_match() {
+        let pairs = default_pairs();
+        let buf = buffer("(a]) [b");
+        assert!(match_forward(&buf, at(0, 0), &pairs).is_none());
+        assert!(match_backward(&buf, at(3, 0), &pairs).is_none());
+        assert!(match_forward(&buf, at(5, 0), &pairs).is_none());
+    }
+
+    #[test]
+    fn sexps_are_groups_strings_and_words() {
+        let mut ed = editor("(a b) \"c d\"e fg_h");
+        ed.forward_sexp(1);
+        assert_eq!(ed.buffer.cursor_x, 5);
+        ed.forward_sexp(1);
+        assert_eq!(ed.buffer.cursor_x, 11);
+        ed.forward_sexp(2);
+        assert_eq!(ed.buffer.cursor_x, 17);
+        ed.forward_sexp(-3);
+        assert_eq!(ed.buffer.cursor_x, 6);
+        ed.forward_sexp(-1);
+        assert_eq!(ed.buffer.cursor_x, 0);
+    }
+
+    #[test]
+    fn unbalanced_sexp_stops_with_a_message() {
+        let mut ed = editor("(a b");
+        ed.forward_sexp(1);
+        assert_eq!(ed.buffer.cursor_x, 0);
+        assert_eq!(ed.minibuffer.get(), "Unbalanced parentheses");
+        let mut ed = editor("a) b");
+        ed.buffer.cursor_x = 1;
+        ed.forward_sexp(1);
+        assert_eq!(ed.minibuffer.get(), "Containing expression ends prematurely");
+    }
+
+    #[test]
+    fn quotes_pair_only_away_from_words() {
+        let mut ed = editor("say ");
+        ed.buffer.cursor_x = 4;
+        assert!(ed.electric_pair('"'));
+        assert_eq!(ed.buffer.text(), "say \"\"");
+        // Typing the closer steps over it.
+        assert!(ed.electric_pair('"'));
+        assert_eq!((ed.buffer.text().as_str(), ed.buffer.cursor_x), ("say \"\"", 6));
+
+        let mut ed = editor("don");
+        ed.buffer.cursor_x = 3;
+        ed.user_config.borrow_mut().pairs.push(('\'', '\''));
+        assert!(!ed.electric_pair('\''));
+        assert_eq!(ed.buffer.text(), "don");
+        // Nor before a word.
+        let mut ed = editor("word");
+        assert!(!ed.electric_pair('('));
+    }
+
+    #[test]
+    fn wrapping_a_region_undoes_in_one_step() {
+        let mut ed = editor("say hi");
+        ed.buffer.cursor_x = 4;
+        ed.buffer.set_mark();
+        ed.buffer.cursor_x = 6;
+        assert!(ed.electric_pair('('));
+        assert_eq!(ed.buffer.text(), "say (hi)");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), "say hi");
+    }
+}
@@ -0,0 +1,631 @@
+// core/src/process.rs
+//
+// Asynchronous external processes. Every child gets one reader thread per
+// output stream; the threads only forward raw chunks over a channel, and the
+// main loop drains it with `ProcessManager::poll` on every tick. Input goes
+// the other way, over a channel to a writer thread. So a slow or chatty
+// process never blocks the TUI.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use mlua::{Lua, Function, RegistryKey};
+use crate::editor::editor::Editor;
+use crate::editor::events::EditorEvent;
+use crate::log::{SharedLog, log_lua_error};
+
+pub type ProcessId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessStream {
+    Stdout,
+    Stderr,
+}
+
+impl ProcessStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessStream::Stdout => "stdout",
+            ProcessStream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Where the output of a process ends up.
+#[derive(Debug, Clone)]
+pub enum ProcessSink {
+    /// Streamed into the named buffer as it arrives.
+    Buffer(String),
+    /// Collected; shown in the echo area when it is a single line, otherwise
+    /// in `*Shell Command Output*` once the process exits.
+    Echo,
+    /// Collected; replaces the region it was fed from on successful exit.
+    ReplaceRegion {
+        buffer: String,
+        start: (usize, usize),
+        end: (usize, usize),
+        undo_depth: usize,
+    },
+    /// Output is only delivered to callbacks and hooks.
+    Discard,
+}
+
+pub struct ProcessSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    /// Written to stdin, which is then closed.
+    pub input: Option<String>,
+    pub sink: ProcessSink,
+    pub on_output: Option<RegistryKey>,
+    pub on_exit: Option<RegistryKey>,
+}
+
+impl ProcessSpec {
+    pub fn new(program: &str, args: Vec<String>, sink: ProcessSink) -> Self {
+        Self {
+            program: program.to_string(),
+            args,
+            cwd: None,
+            input: None,
+            sink,
+            on_output: None,
+            on_exit: None,
+        }
+    }
+
+    /// `sh -c <command>`
+    pub fn shell(command: &str, sink: ProcessSink) -> Self {
+        Self::new("sh", vec!["-c".to_string(), command.to_string()], sink)
+    }
+
+    pub fn command_line(&self) -> String {
+        if self.program == "sh" && self.args.first().map(String::as_str) == Some("-c") {
+            return self.args[1..].join(" ");
+        }
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+enum RawMsg {
+    Chunk { id: ProcessId, stream: ProcessStream, bytes: Vec<u8> },
+    Closed { id: ProcessId },
+}
+
+struct Process {
+    command: String,
+    child: Child,
+    /// Feeds the writer thread; dropping it closes stdin once everything
+    /// sent is written.
+    stdin: Option<Sender<Vec<u8>>>,
+    sink: ProcessSink,
+    collected: String,
+    open_streams: u8,
+    /// Bytes of an incomplete UTF-8 sequence, per stream.
+    carry: [Vec<u8>; 2],
+    on_output: Option<Rc<RegistryKey>>,
+    on_exit: Option<Rc<RegistryKey>>,
+}
+
+/// Write what arrives on the returned channel to `pipe`, from a thread: a
+/// filter may fill its stdout pipe before it has read all its input, and a
+/// busy language server may not read for a while. The pipe is closed when
+/// the channel is, or once a write fails.
+pub(crate) fn feed<W: Write + Send + 'static>(mut pipe: W) -> Sender<Vec<u8>> {
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    thread::spawn(move || {
+        for bytes in rx {
+            if pipe.write_all(&bytes).and_then(|_| pipe.flush()).is_err() {
+                break;
+            }
+        }
+    });
+    tx
+}
+
+/// Something that happened to a process since the last poll.
+pub enum ProcessUpdate {
+    Started {
+        id: ProcessId,
+        command: String,
+        sink: ProcessSink,
+    },
+    Output {
+        id: ProcessId,
+        stream: ProcessStream,
+        text: String,
+        sink: ProcessSink,
+        callback: Option<Rc<RegistryKey>>,
+    },
+    Exited {
+        id: ProcessId,
+        command: String,
+        code: Option<i32>,
+        sink: ProcessSink,
+        output: String,
+        callback: Option<Rc<RegistryKey>>,
+    },
+}
+
+pub struct ProcessManager {
+    next_id: ProcessId,
+    procs: HashMap<ProcessId, Process>,
+    started: Vec<ProcessId>,
+    tx: Sender<RawMsg>,
+    rx: Receiver<RawMsg>,
+}
+
+pub type SharedProcesses = Rc<RefCell<ProcessManager>>;
+
+impl Default for ProcessManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessManager {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            next_id: 1,
+            procs: HashMap::new(),
+            started: Vec::new(),
+            tx,
+            rx,
+        }
+    }
+
+    pub fn spawn(&mut self, spec: ProcessSpec) -> io::Result<ProcessId> {
+        let command = spec.command_line();
+        let mut cmd = Command::new(&spec.program);
+        cmd.args(&spec.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &spec.cwd {
+            cmd.current_dir(dir);
+        }
+        let mut child = cmd.spawn()?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let mut open_streams = 0;
+        if let Some(out) = stdout {
+            self.pump(id, ProcessStream::Stdout, out);
+            open_streams += 1;
+        }
+        if let Some(err) = stderr {
+            self.pump(id, ProcessStream::Stderr, err);
+            open_streams += 1;
+        }
+
+        let mut stdin = child.stdin.take().map(feed);
+        if let Some(input) = spec.input
+            && let Some(tx) = stdin.take() {
+            let _ = tx.send(input.into_bytes());
+        }
+
+        self.procs.insert(id, Process {
+            command,
+            child,
+            stdin,
+            sink: spec.sink,
+            collected: String::new(),
+            open_streams,
+            carry: [Vec::new(), Vec::new()],
+            on_output: spec.on_output.map(Rc::new),
+            on_exit: spec.on_exit.map(Rc::new),
+        });
+        self.started.push(id);
+        Ok(id)
+    }
+
+    fn pump<R: Read + Send + 'static>(&self, id: ProcessId, stream: ProcessStream, mut reader: R) {
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let msg = RawMsg::Chunk { id, stream, bytes: buf[..n].to_vec() };
+                        if tx.send(msg).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            let _ = tx.send(RawMsg::Closed { id });
+        });
+    }
+
+    /// Queue `text` for the process's stdin. Never blocks; fails once the
+    /// process stopped reading.
+    pub fn send_input(&mut self, id: ProcessId, text: &str) -> io::Result<()> {
+        let proc = self.procs.get_mut(&id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such process"))?;
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "stdin is closed");
+        let stdin = proc.stdin.as_ref().ok_or_else(closed)?;
+        stdin.send(text.as_bytes().to_vec()).map_err(|_| closed())
+    }
+
+    pub fn close_stdin(&mut self, id: ProcessId) {
+        if let Some(proc) = self.procs.get_mut(&id) {
+            proc.stdin = None;
+        }
+    }
+
+    pub fn kill(&mut self, id: ProcessId) -> io::Result<()> {
+        match self.procs.get_mut(&id) {
+            Some(proc) => proc.child.kill(),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "no such process")),
+        }
+    }
+
+    /// Kill a process and forget it: no further output or exit is reported.
+    pub fn discard(&mut self, id: ProcessId) {
+        if let Some(mut p) = self.procs.remove(&id) {
+            let _ = p.child.kill();
+            let _ = p.child.wait();
+        }
+        self.started.retain(|s| *s != id);
+    }
+
+    /// Kill the processes streaming into buffer `name`, which is going
+    /// away. Their exit is still reported, but nothing more is written.
+    pub fn detach_buffer(&mut self, name: &str) {
+        for p in self.procs.values_mut() {
+            if matches!(&p.sink, ProcessSink::Buffer(b) if b == name) {
+                p.sink = ProcessSink::Discard;
+                let _ = p.child.kill();
+            }
+        }
+    }
+
+    pub fn has_running(&self) -> bool {
+        !self.procs.is_empty()
+    }
+
+    pub fn is_running(&self, id: ProcessId) -> bool {
+        self.procs.contains_key(&id)
+    }
+
+    pub fn running(&self) -> impl Iterator<Item = (ProcessId, &str)> {
+        self.procs.iter().map(|(id, p)| (*id, p.command.as_str()))
+    }
+
+    /// Drain everything that happened since the last call. Never blocks.
+    pub fn poll(&mut self) -> Vec<ProcessUpdate> {
+        let mut updates = Vec::new();
+
+        for id in self.started.drain(..) {
+            if let Some(p) = self.procs.get(&id) {
+                updates.push(ProcessUpdate::Started { id, command: p.command.clone(), sink: p.sink.clone() });
+            }
+        }
+
+        while let Ok(msg) = self.rx.try_recv() {
+            match msg {
+                RawMsg::Chunk { id, stream, bytes } => {
+                    let Some(p) = self.procs.get_mut(&id) else { continue };
+                    let text = decode(&mut p.carry[stream as usize], bytes);
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if !matches!(p.sink, ProcessSink::Buffer(_)) {
+                        p.collected.push_str(&text);
+                    }
+                    updates.push(ProcessUpdate::Output {
+                        id,
+                        stream,
+                        text,
+                        sink: p.sink.clone(),
+                        callback: p.on_output.clone(),
+                    });
+                }
+                RawMsg::Closed { id } => {
+                    if let Some(p) = self.procs.get_mut(&id) {
+                        p.open_streams = p.open_streams.saturating_sub(1);
+                    }
+                }
+            }
+        }
+
+        let mut exited = Vec::new();
+        for (id, p) in self.procs.iter_mut() {
+            if p.open_streams > 0 {
+                continue;
+            }
+            if let Ok(Some(status)) = p.child.try_wait() {
+                exited.push((*id, status.code()));
+            }
+        }
+        exited.sort_by_key(|(id, _)| *id);
+        for (id, code) in exited {
+            if let Some(p) = self.procs.remove(&id) {
+                updates.push(ProcessUpdate::Exited {
+                    id,
+                    command: p.command,
+                    code,
+                    sink: p.sink,
+                    output: p.collected,
+                    callback: p.on_exit,
+                });
+            }
+        }
+
+        updates
+    }
+}
+
+impl Drop for ProcessManager {
+    fn drop(&mut self) {
+        for p in self.procs.values_mut() {
+            let _ = p.child.kill();
+        }
+    }
+}
+
+/// Decode a chunk, keeping a trailing incomplete UTF-8 sequence for later.
+fn decode(carry: &mut Vec<u8>, bytes: Vec<u8>) -> String {
+    carry.extend(bytes);
+    let valid = match std::str::from_utf8(carry) {
+        Ok(_) => carry.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => carry.len(),
+    };
+    let rest = carry.split_off(valid);
+    let text = String::from_utf8_lossy(carry).into_owned();
+    *carry = rest;
+    text
+}
+
+// ---- Lua callbacks ----
+pub enum ProcessCallback {
+    Output { func: Rc<RegistryKey>, id: ProcessId, text: String, stream: ProcessStream },
+    Exit { func: Rc<RegistryKey>, id: ProcessId, code: Option<i32> },
+}
+
+/// Run the per-process Lua callbacks collected by `Editor::poll_processes`,
+/// logging the ones that fail. Must be called while the editor is not
+/// borrowed.
+pub fn run_process_callbacks(lua: &Lua, callbacks: Vec<ProcessCallback>, log: &SharedLog) {
+    for cb in callbacks {
+        match cb {
+            ProcessCallback::Output { func, id, text, stream } => {
+                if let Ok(f) = lua.registry_value::<Function>(&func) {
+                    log_lua_error(log, "process output callback", f.call::<_, ()>((text, stream.as_str(), id)));
+                }
+            }
+            ProcessCallback::Exit { func, id, code } => {
+                if let Ok(f) = lua.registry_value::<Function>(&func) {
+                    log_lua_error(log, "process exit callback", f.call::<_, ()>((code, id)));
+                }
+            }
+        }
+    }
+}
+
+// ---- Editor integration ----
+pub const SHELL_OUTPUT_BUFFER: &str = "*Shell Command Output*";
+
+impl Editor {
+    pub fn start_process(&mut self, spec: ProcessSpec) -> Option<ProcessId> {
+        let command = spec.command_line();
+        match self.processes.borrow_mut().spawn(spec) {
+            Ok(id) => Some(id),
+            Err(e) => {
+                self.minibuffer.message(&format!("Failed to run {command}: {e}"));
+                None
+            }
+        }
+    }
+
+    /// Apply process output to buffers and emit process events. Returns the
+    /// Lua callbacks to run once the editor is no longer borrowed.
+    pub fn poll_processes(&mut self) -> Vec<ProcessCallback> {
+        let updates = self.processes.borrow_mut().poll();
+        let mut callbacks = Vec::new();
+
+        for update in updates {
+            match update {
+                ProcessUpdate::Started { sink, .. } => {
+                    if let ProcessSink::Buffer(name) = sink {
+                        self.buffer_mut_or_create(&name).set_text_raw("");
+                    }
+                }
+                ProcessUpdate::Output { id, stream, text, sink, callback } => {
+                    if let ProcessSink::Buffer(name) = &sink {
+                        self.buffer_mut_or_create(name).append_raw(&text);
+                        self.scan_locations(name, false);
+                        self.ensure_cursor_visible();
+                    }
+                    self.emit(EditorEvent::ProcessOutput { id, stream, text: text.clone() });
+                    if let Some(func) = callback {
+                        callbacks.push(ProcessCallback::Output { func, id, text, stream });
+                    }
+                }
+                ProcessUpdate::Exited { id, command, code, sink, output, callback } => {
+                    let target = match &sink {
+                        ProcessSink::Buffer(name) => Some(name.clone()),
+                        _ => None,
+                    };
+                    self.finish_process(&command, code, sink, output);
+                    if let Some(name) = target {
+                        self.scan_locations(&name, true);
+                    }
+                    self.emit(EditorEvent::ProcessExited { id, code });
+                    if let Some(func) = callback {
+                        callbacks.push(ProcessCallback::Exit { func, id, code });
+                    }
+                }
+            }
+        }
+
+        callbacks
+    }
+
+    fn finish_process(&mut self, command: &str, code: Option<i32>, sink: ProcessSink, output: String) {
+        let status = match code {
+            Some(0) => "finished".to_string(),
+            Some(c) => format!("exited abnormally with code {c}"),
+            None => "killed".to_string(),
+        };
+
+        match sink {
+            ProcessSink::Buffer(name) => {
+                let buf = self.buffer_mut_or_create(&name);
+                let sep = if buf.lines.last().is_some_and(|l| l.char_len > 0) { "\n" } else { "" };
+                buf.append_raw(&format!("{sep}\nProcess \"{command}\" {status}\n"));
+                self.minibuffer.message(&format!("{command}: {status}"));
+            }
+            ProcessSink::Echo => self.show_shell_output(&output, code),
+            ProcessSink::ReplaceRegion { buffer, start, end, undo_depth } => {
+                if code != Some(0) {
+                    self.show_shell_output(&output, code);
+                    return;
+                }
+                let unchanged = self.buffer.file_name() == buffer
+                    && self.buffer.undo_depth() == undo_depth;
+                if !unchanged {
+                    self.show_shell_output(&output, code);
+                    self.minibuffer.message("Buffer changed while the filter ran; output not inserted");
+                    return;
+                }
+                if self.buffer.read_only {
+                    self.show_shell_output(&output, code);
+                    self.minibuffer.message("Buffer is read-only; output not inserted");
+                    return;
+                }
+                let output = output.strip_suffix('\n').unwrap_or(&output).to_string();
+                self.buffer.clear_mark();
+                self.buffer.replace_range(start, end, &output);
+                self.ensure_cursor_visible();
+                self.emit(EditorEvent::BufferChanged { reason: "shell-command-on-region" });
+            }
+            ProcessSink::Discard => {}
+        }
+    }
+
+    fn show_shell_output(&mut self, output: &str, code: Option<i32>) {
+        let trimmed = output.trim_end_matches('\n');
+        if trimmed.is_empty() {
+            let msg = match code {
+                Some(0) => "(Shell command succeeded with no output)".to_string(),
+                Some(c) => format!("(Shell command failed with code {c} and no output)"),
+                None => "(Shell command killed)".to_string(),
+            };
+            self.minibuffer.message(&msg);
+        } else if !trimmed.contains('\n') {
+            self.minibuffer.message(trimmed);
+        } else {
+            self.buffer_mut_or_create(SHELL_OUTPUT_BUFFER).set_text_raw(trimmed);
+            self.switch_to_buffer(SHELL_OUTPUT_BUFFER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use crate::command::CommandRegistry;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+
+    /// Poll until `id` exits, returning its code and collected output.
+    fn wait(procs: &mut ProcessManager, id: ProcessId) -> (Option<i32>, String) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            for update in procs.poll() {
+                if let ProcessUpdate::Exited { id: done, code, output, .. } = update
+                    && done == id {
+                    return (code, output);
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("process {id} did not exit");
+    }
+
+    #[test]
+    fn input_is_written_in_order_then_closed() {
+        let mut procs = ProcessManager::new();
+        let id = procs.spawn(ProcessSpec::new("cat", Vec::new(), ProcessSink::Echo)).unwrap();
+        procs.send_input(id, "one\n").unwrap();
+        procs.send_input(id, "two\n").unwrap();
+        procs.close_stdin(id);
+        assert_eq!(procs.send_input(id, "three\n").unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(wait(&mut procs, id), (Some(0), "one\ntwo\n".to_string()));
+    }
+
+    #[test]
+    fn input_to_a_process_not_reading_does_not_block() {
+        let mut procs = ProcessManager::new();
+        let id = procs.spawn(ProcessSpec::shell("sleep 10", ProcessSink::Discard)).unwrap();
+        let start = Instant::now();
+        // Far more than a pipe holds.
+        procs.send_input(id, &"x".repeat(1 << 20)).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        procs.discard(id);
+    }
+
+    #[test]
+    fn spec_input_feeds_a_filter() {
+        let mut procs = ProcessManager::new();
+        let mut spec = ProcessSpec::shell("tr a-z A-Z", ProcessSink::Echo);
+        spec.input = Some("shout".to_string());
+        let id = procs.spawn(spec).unwrap();
+        assert_eq!(wait(&mut procs, id), (Some(0), "SHOUT".to_string()));
+    }
+
+    #[test]
+    fn killing_its_buffer_stops_a_process() {
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let mut ed = Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(UserConfig::default())));
+        let spec = ProcessSpec::shell("echo start; exec sleep 10", ProcessSink::Buffer("*out*".to_string()));
+        let id = ed.start_process(spec).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while ed.find_buffer("*out*").is_none_or(|b| b.text().is_empty()) {
+            assert!(Instant::now() < deadline, "no output");
+            ed.poll_processes();
+            thread::sleep(Duration::from_millis(10));
+        }
+        ed.switch_to_buffer("*out*");
+        ed.kill_current_buffer();
+        while ed.processes.borrow().is_running(id) {
+            assert!(Instant::now() < deadline, "process {id} was not killed");
+            ed.poll_processes();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(ed.find_buffer("*out*").is_none());
+    }
+
+    #[test]
+    fn filtered_region_undoes_in_one_step() {
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let mut ed = Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(UserConfig::default())));
+        ed.buffer.set_text_raw("one two\nthree");
+        let sink = ProcessSink::ReplaceRegion {
+            buffer: ed.buffer.file_name(),
+            start: (4, 0),
+            end: (2, 1),
+            undo_depth: ed.buffer.undo_depth(),
+        };
+        ed.finish_process("tr a-z A-Z", Some(0), sink, "TWO\nTH\n".to_string());
+        assert_eq!(ed.buffer.text(), "one TWO\nTHree");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), "one two\nthree");
+    }
+}
@@ -0,0 +1,253 @@
+// core/src/project.rs
+//
+// Projects: the directory tree a file belongs to, found by walking up to the
+// nearest directory holding one of `UserConfig::project_markers`. A crate
+// inside a Cargo workspace belongs to the workspace, so compile and grep run
+// from the workspace root.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use crate::editor::editor::Editor;
+use crate::walk::list_files;
+
+pub fn default_markers() -> Vec<String> {
+    [".git", "Cargo.toml", ".hg", ".project"].iter().map(|s| s.to_string()).collect()
+}
+
+/// Root of the project containing `dir`, if any marker is found above it.
+pub fn find_root(dir: &Path, markers: &[String]) -> Option<PathBuf> {
+    let root = dir.ancestors().find(|d| markers.iter().any(|m| d.join(m).exists()))?;
+    if root.join(".git").exists() || !root.join("Cargo.toml").is_file() {
+        return Some(root.to_path_buf());
+    }
+    // A member crate: prefer the enclosing workspace, but not past the
+    // repository boundary.
+    for up in root.ancestors().skip(1) {
+        if is_cargo_workspace(up) {
+            return Some(up.to_path_buf());
+        }
+        if up.join(".git").exists() {
+            break;
+        }
+    }
+    Some(root.to_path_buf())
+}
+
+fn is_cargo_workspace(dir: &Path) -> bool {
+    fs::read_to_string(dir.join("Cargo.toml"))
+        .is_ok_and(|text| text.lines().any(|l| l.trim() == "[workspace]"))
+}
+
+/// Display name of a project: its directory name.
+pub fn project_name(root: &Path) -> String {
+    root.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| root.display().to_string())
+}
+
+/// Files of the project, relative to `root`: what git tracks (plus
+/// untracked, non-ignored files) inside a repository, otherwise everything
+/// the walker does not ignore.
+pub fn project_files(root: &Path) -> Vec<String> {
+    git_files(root).unwrap_or_else(|| list_files(root))
+}
+
+/// Branch checked out in the git repository containing `dir`, or the
+/// short commit hash when HEAD is detached. Reads `.git/HEAD` directly, so
+/// it is cheap enough for the mode line.
+pub fn git_branch(dir: &Path) -> Option<String> {
+    let dot_git = dir.ancestors().map(|d| d.join(".git")).find(|p| p.exists())?;
+    // Worktrees and submodules have a `.git` file pointing at the real one.
+    let git_dir = if dot_git.is_file() {
+        let text = fs::read_to_string(&dot_git).ok()?;
+        let target = text.trim().strip_prefix("gitdir:")?.trim();
+        dot_git.parent()?.join(target)
+    } else {
+        dot_git
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(r) => Some(r.trim().trim_start_matches("refs/heads/").to_string()),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+fn git_files(root: &Path) -> Option<Vec<String>> {
+    let out = Command::new("git")
+        .args(["ls-files", "-z", "--cached", "--others", "--exclude-standard"])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&out.stdout);
+    Some(text.split('\0').filter(|f| !f.is_empty()).map(str::to_string).collect())
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// Project of the current buffer.
+    pub fn project_root(&self) -> Option<PathBuf> {
+        self.project_root_of(&self.default_directory())
+    }
+
+    pub fn project_root_of(&self, dir: &Path) -> Option<PathBuf> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        if let Some(hit) = self.project_roots.borrow().get(&dir) {
+            return hit.clone();
+        }
+        let root = find_root(&dir, &self.user_config.borrow().project_markers);
+        self.project_roots.borrow_mut().insert(dir, root.clone());
+        root
+    }
+
+    /// Git branch of the current buffer's directory, re-read at most every
+    /// couple of seconds.
+    pub fn git_branch(&self) -> Option<String> {
+        const TTL: Duration = Duration::from_secs(2);
+        let dir = self.default_directory();
+        if let Some((at, branch)) = self.git_branches.borrow().get(&dir)
+            && at.elapsed() < TTL {
+            return branch.clone();
+        }
+        let branch = git_branch(&dir);
+        self.git_branches.borrow_mut().insert(dir, (Instant::now(), branch.clone()));
+        branch
+    }
+
+    /// Where project-wide commands run: the project root, else the
+    /// default directory.
+    pub fn project_directory(&self) -> PathBuf {
+        self.project_root().unwrap_or_else(|| self.default_directory())
+    }
+
+    /// Projects to offer in `project-switch`: recently used ones first,
+    /// then those of open buffers.
+    pub fn known_projects(&self) -> Vec<PathBuf> {
+        let mut out = self.recent_projects.clone();
+        let dirs = std::iter::once(&self.buffer).chain(self.buffers.iter())
+            .filter_map(|b| b.file_path.as_ref()?.parent().map(Path::to_path_buf));
+        for dir in dirs {
+            if let Some(root) = self.project_root_of(&dir)
+                && !out.contains(&root) {
+                out.push(root);
+            }
+        }
+        out
+    }
+
+    pub fn remember_project(&mut self, root: &Path) {
+        self.recent_projects.retain(|p| p != root);
+        self.recent_projects.insert(0, root.to_path_buf());
+    }
+
+    /// Prompt for a file of the project at `root` and visit it.
+    pub fn project_find_file(&mut self, root: PathBuf) {
+        let files = project_files(&root);
+        if files.is_empty() {
+            self.minibuffer.message(&format!("No files in {}", root.display()));
+            return;
+        }
+        self.remember_project(&root);
+        let prompt = format!("Find file in {}: ", project_name(&root));
+        self.completing_read(&prompt, files, move |ed, file| {
+            if file.is_empty() {
+                return;
+            }
+            let path = root.join(file);
+            if let Err(e) = ed.visit_file(path.clone()) {
+                ed.minibuffer.message(&format!("Cannot open {}: {e}", path.display()));
+            }
+        });
+    }
+
+    /// Prompt for a known project (or any directory) and find a file in it.
+    pub fn project_switch(&mut self) {
+        let candidates = self.known_projects().iter().map(|p| p.display().to_string()).collect();
+        self.completing_read("Switch to project: ", candidates, |ed, dir| {
+            if dir.trim().is_empty() {
+                return;
+            }
+            let dir = ed.buffer.expand_tilde(dir.trim());
+            if !dir.is_dir() {
+                ed.minibuffer.message(&format!("Not a directory: {}", dir.display()));
+                return;
+            }
+            let root = ed.project_root_of(&dir).unwrap_or(dir);
+            ed.project_find_file(root);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory with `files` (path, contents) under it; `/` at
+    /// the end of a path makes a directory.
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("remux-project-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, text) in files {
+            let path = dir.join(path);
+            if path.to_string_lossy().ends_with('/') {
+                fs::create_dir_all(&path).unwrap();
+            } else {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, text).unwrap();
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn member_crates_belong_to_their_workspace() {
+        let dir = tree("workspace", &[
+            (".git/", ""),
+            ("Cargo.toml", "[workspace]\nmembers = [\"crates/a\"]\n"),
+            ("crates/a/Cargo.toml", "[package]\n"),
+            ("crates/a/src/", ""),
+        ]);
+        assert_eq!(find_root(&dir.join("crates/a/src"), &default_markers()), Some(dir.clone()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn crates_outside_a_workspace_are_their_own_root() {
+        let dir = tree("crate", &[
+            (".git/", ""),
+            ("Cargo.toml", "[package]\n"),
+            ("tools/gen/Cargo.toml", "[package]\n"),
+            ("tools/gen/src/", ""),
+        ]);
+        assert_eq!(find_root(&dir.join("tools/gen/src"), &default_markers()), Some(dir.join("tools/gen")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn workspaces_are_not_looked_for_past_the_repository() {
+        let dir = tree("boundary", &[
+            ("Cargo.toml", "[workspace]\n"),
+            ("repo/.git/", ""),
+            ("repo/member/Cargo.toml", "[package]\n"),
+        ]);
+        assert_eq!(find_root(&dir.join("repo/member"), &default_markers()), Some(dir.join("repo/member")));
+        assert_eq!(find_root(&dir.join("repo"), &default_markers()), Some(dir.join("repo")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn markers_are_configurable() {
+        let dir = tree("markers", &[("proj/.project", ""), ("proj/src/", "")]);
+        let markers = vec![".project".to_string()];
+        assert_eq!(find_root(&dir.join("proj/src"), &markers), Some(dir.join("proj")));
+        assert_eq!(find_root(&dir.join("proj/src"), &["no-such-marker".to_string()]), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
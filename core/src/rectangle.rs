@@ -0,0 +1,341 @@
+// core/src/rectangle.rs
+//
+// Rectangles: the block of screen columns between the mark and the cursor,
+// on every line from one to the other. Edges are display columns (see
+// `editor::layout`), so the block stays straight across tabs and wide
+// chars. A line holds the chars that *start* inside the block; a tab
+// crossing an edge is turned into spaces before the line is edited.
+
+use crate::editor::editor::Editor;
+use crate::editor::layout::{char_width, column_of, text_width};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rectangle {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    /// Exclusive.
+    pub right: usize,
+}
+
+/// Chars `a..b` of `text` starting in columns `left..right`.
+pub fn rectangle_span(text: &str, left: usize, right: usize, tab_width: usize) -> (usize, usize) {
+    let (mut a, mut b) = (None, None);
+    let mut col = 0;
+    let mut n = 0;
+    for c in text.chars() {
+        if a.is_none() && col >= left {
+            a = Some(n);
+        }
+        if col >= right {
+            b = Some(n);
+            break;
+        }
+        col += char_width(c, col, tab_width);
+        n += 1;
+    }
+    (a.unwrap_or(n), b.unwrap_or(n).max(a.unwrap_or(n)))
+}
+
+fn spaces(n: usize) -> String {
+    " ".repeat(n)
+}
+
+// ---- Editor integration ----
+impl Editor {
+    /// The rectangle with the mark and the cursor at opposite corners.
+    pub fn rectangle(&self) -> Option<Rectangle> {
+        let mark = self.buffer.mark()?;
+        let tab = self.tab_width();
+        let col = |x: usize, y: usize| column_of(&self.buffer.lines[y].text, x, tab);
+        let (c1, c2) = (col(mark.x, mark.y), col(self.buffer.cursor_x, self.buffer.cursor_y));
+        Some(Rectangle {
+            top: mark.y.min(self.buffer.cursor_y),
+            bottom: mark.y.max(self.buffer.cursor_y),
+            left: c1.min(c2),
+            right: c1.max(c2),
+        })
+    }
+
+    fn rectangle_or_complain(&mut self) -> Option<Rectangle> {
+        let rect = self.rectangle();
+        if rect.is_none() {
+            self.minibuffer.message("The mark is not set now, so there is no region");
+        }
+        rect
+    }
+
+    /// Turn a tab crossing column `col` of line `y` into spaces.
+    fn split_tab_at(&mut self, y: usize, col: usize) {
+        let tab = self.tab_width();
+        let mut start = 0;
+        for (i, c) in self.buffer.lines[y].text.chars().enumerate() {
+            let w = char_width(c, start, tab);
+            if start < col && col < start + w {
+                if c == '\t' {
+                    self.buffer.delete_range(i, y, i + 1, y);
+                    self.buffer.insert_text_at(i, y, &spaces(w));
+                }
+                return;
+            }
+            start += w;
+            if start >= col {
+                return;
+            }
+        }
+    }
+
+    /// Lines of `rect`, each padded with spaces to the rectangle's width.
+    fn extract_rectangle(&self, rect: Rectangle) -> Vec<String> {
+        let tab = self.tab_width();
+        (rect.top..=rect.bottom)
+            .map(|y| {
+                let text = &self.buffer.lines[y].text;
+                let (a, b) = rectangle_span(text, rect.left, rect.right, tab);
+                let (ca, cb) = (column_of(text, a, tab), column_of(text, b, tab));
+                // A wide char crossing the left edge leaves a gap before
+                // the first char inside.
+                let mut piece = spaces(ca.saturating_sub(rect.left));
+                let mut col = ca;
+                for c in text.chars().skip(a).take(b - a) {
+                    let w = char_width(c, col, tab);
+                    // Only the part of a tab inside the block is copied, as
+                    // killing splits it there.
+                    if c == '\t' && col + w > rect.right {
+                        piece.push_str(&spaces(rect.right - col));
+                    } else {
+                        piece.push(c);
+                    }
+                    col += w;
+                }
+                piece.push_str(&spaces(rect.right.saturating_sub(cb.max(rect.left))));
+                piece
+            })
+            .collect()
+    }
+
+    /// Replace the part of each line inside `rect` with what `f` makes of
+    /// it (the old text and its width). Lines ending left of the rectangle
+    /// are padded up to it when `pad`, and skipped otherwise.
+    fn map_rectangle(&mut self, rect: Rectangle, pad: bool, mut f: impl FnMut(&str, usize) -> String) {
+        let tab = self.tab_width();
+        let undo_from = self.buffer.undo_len();
+        for y in rect.top..=rect.bottom {
+            self.split_tab_at(y, rect.left);
+            self.split_tab_at(y, rect.right);
+            let text = &self.buffer.lines[y].text;
+            let (a, b) = rectangle_span(text, rect.left, rect.right, tab);
+            let line_width = text_width(text, tab);
+            let old: String = text.chars().skip(a).take(b - a).collect();
+            let old_width = column_of(text, b, tab) - column_of(text, a, tab);
+            let mut new = f(&old, old_width);
+            if line_width < rect.left {
+                if !pad || new.is_empty() {
+                    continue;
+                }
+                new.insert_str(0, &spaces(rect.left - line_width));
+            }
+            if new == old {
+                continue;
+            }
+            if b > a {
+                self.buffer.delete_range(a, y, b, y);
+            }
+            if !new.is_empty() {
+                self.buffer.insert_text_at(a, y, &new);
+            }
+        }
+        self.buffer.group_undo(undo_from);
+        self.finish_rectangle(rect);
+    }
+
+    /// Leave the cursor at the top left corner, without a region.
+    fn finish_rectangle(&mut self, rect: Rectangle) {
+        let tab = self.tab_width();
+        self.buffer.cursor_y = rect.top;
+        self.buffer.cursor_x = rectangle_span(&self.buffer.lines[rect.top].text, rect.left, rect.left, tab).0;
+        self.buffer.clear_mark();
+        self.buffer.visual.dirty = true;
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("rectangle");
+    }
+
+    /// `rectangle-mark-mode`: set the mark if needed and show the region as
+    /// a rectangle.
+    pub fn rectangle_mark_mode(&mut self) {
+        if self.buffer.rectangle_mark {
+            self.buffer.rectangle_mark = false;
+            return;
+        }
+        if self.buffer.mark().is_none() {
+            self.buffer.set_mark();
+        }
+        self.buffer.rectangle_mark = true;
+        self.minibuffer.message("Rectangle-Mark mode enabled");
+    }
+
+    /// `copy-rectangle`: remember the rectangle for `yank-rectangle`.
+    pub fn copy_rectangle(&mut self) {
+        let Some(rect) = self.rectangle_or_complain() else { return };
+        self.killed_rectangle = Some(self.extract_rectangle(rect));
+        self.buffer.clear_mark();
+        self.minibuffer.message("Copied rectangle");
+    }
+
+    /// `kill-rectangle`: delete the rectangle, remembering it for
+    /// `yank-rectangle`.
+    pub fn kill_rectangle(&mut self) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let Some(rect) = self.rectangle_or_complain() else { return };
+        self.killed_rectangle = Some(self.extract_rectangle(rect));
+        self.map_rectangle(rect, false, |_, _| String::new());
+    }
+
+    /// `clear-rectangle`: blank the rectangle out with spaces.
+    pub fn clear_rectangle(&mut self) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let Some(rect) = self.rectangle_or_complain() else { return };
+        self.map_rectangle(rect, false, |_, width| spaces(width));
+    }
+
+    /// `open-rectangle`: insert blanks where the rectangle is, shifting the
+    /// text right.
+    pub fn open_rectangle(&mut self) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let Some(rect) = self.rectangle_or_complain() else { return };
+        let blank = spaces(rect.right - rect.left);
+        self.map_rectangle(rect, false, |old, _| format!("{blank}{old}"));
+    }
+
+    /// `string-rectangle`: replace each line of the rectangle with `text`.
+    pub fn string_rectangle(&mut self, text: &str) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let Some(rect) = self.rectangle_or_complain() else { return };
+        self.map_rectangle(rect, true, |_, _| text.to_string());
+    }
+
+    /// `yank-rectangle`: insert the last killed rectangle with its top left
+    /// corner at the cursor, adding lines at the end of the buffer as
+    /// needed. The cursor ends up at its bottom right corner.
+    pub fn yank_rectangle(&mut self) {
+        if self.barf_if_read_only() {
+            return;
+        }
+        let Some(rect) = self.killed_rectangle.clone() else {
+            self.minibuffer.message("No rectangle to yank");
+            return;
+        };
+        let tab = self.tab_width();
+        let top = self.buffer.cursor_y;
+        let col = column_of(&self.buffer.lines[top].text, self.buffer.cursor_x, tab);
+        let mut end = (self.buffer.cursor_x, top);
+        let undo_from = self.buffer.undo_len();
+        for (i, piece) in rect.iter().enumerate() {
+            let y = top + i;
+            if y == self.buffer.lines.len() {
+                self.buffer.cursor_y = y - 1;
+                self.buffer.cursor_x = self.buffer.lines[y - 1].char_len;
+                self.buffer.insert_newline_raw();
+            }
+            self.split_tab_at(y, col);
+            let text = &self.buffer.lines[y].text;
+            let width = text_width(text, tab);
+            let mut piece = piece.clone();
+            if width < col {
+                piece.insert_str(0, &spaces(col - width));
+            }
+            let a = rectangle_span(text, col, col, tab).0;
+            self.buffer.insert_text_at(a, y, &piece);
+            end = (a + piece.chars().count(), y);
+        }
+        self.buffer.group_undo(undo_from);
+        (self.buffer.cursor_x, self.buffer.cursor_y) = end;
+        self.buffer.visual.dirty = true;
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("rectangle");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::buffer::Position;
+    use crate::command::CommandRegistry;
+    use crate::config::UserConfig;
+    use crate::editor::editor::KeyMap;
+
+    /// An editor holding `text` with the rectangle from `mark` to the
+    /// cursor at `cursor`, both `(x, y)`.
+    fn editor(text: &str, mark: (usize, usize), cursor: (usize, usize)) -> Editor {
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let mut ed = Editor::new(CommandRegistry::new(), keymap, Rc::new(RefCell::new(UserConfig::default())));
+        ed.buffer.set_text_raw(text);
+        ed.buffer.set_mark_at(Some(Position::new(mark.0, mark.1)));
+        (ed.buffer.cursor_x, ed.buffer.cursor_y) = cursor;
+        ed
+    }
+
+    #[test]
+    fn span_holds_the_chars_starting_inside() {
+        // `a` 0, tab 1..4, `b` 4, `c` 5.
+        assert_eq!(rectangle_span("a\tbc", 0, 6, 4), (0, 4));
+        // A tab crossing the left edge is left out, one crossing the right
+        // edge kept.
+        assert_eq!(rectangle_span("a\tbc", 2, 5, 4), (2, 3));
+        assert_eq!(rectangle_span("a\tbc", 0, 2, 4), (0, 2));
+        // `漢` 1..3 straddles a left edge at 2.
+        assert_eq!(rectangle_span("a漢b", 2, 4, 4), (2, 3));
+        assert_eq!(rectangle_span("a漢b", 1, 4, 4), (1, 3));
+        // Past the end of the line.
+        assert_eq!(rectangle_span("ab", 3, 5, 4), (2, 2));
+    }
+
+    #[test]
+    fn extracted_lines_fill_the_rectangle() {
+        let ed = editor("a\tbc\na漢bc\nx", (0, 0), (0, 0));
+        ed.user_config.borrow_mut().tab_width = 4;
+        // Columns 2..5 of each line.
+        let rect = Rectangle { top: 0, bottom: 2, left: 2, right: 5 };
+        assert_eq!(ed.extract_rectangle(rect), vec!["  b", " bc", "   "]);
+        // A tab crossing the right edge contributes its inside part.
+        let rect = Rectangle { top: 0, bottom: 0, left: 0, right: 2 };
+        assert_eq!(ed.extract_rectangle(rect), vec!["a "]);
+    }
+
+    #[test]
+    fn killing_across_a_tab_keeps_columns() {
+        let mut ed = editor("ab\tc\nabcdefg", (1, 0), (3, 1));
+        ed.user_config.borrow_mut().tab_width = 4;
+        ed.kill_rectangle();
+        // The tab (columns 2..4) is split at column 3.
+        assert_eq!(ed.buffer.text(), "a c\nadefg");
+        assert_eq!(ed.killed_rectangle, Some(vec!["b ".to_string(), "bc".to_string()]));
+    }
+
+    #[test]
+    fn rectangle_edits_undo_in_one_step() {
+        let text = "abcd\nefgh\nijkl";
+        let mut ed = editor(text, (1, 0), (3, 2));
+        ed.kill_rectangle();
+        assert_eq!(ed.buffer.text(), "ad\neh\nil");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), text);
+
+        ed.buffer.cursor_x = 0;
+        ed.buffer.cursor_y = 0;
+        ed.yank_rectangle();
+        assert_eq!(ed.buffer.text(), "bcabcd\nfgefgh\njkijkl");
+        ed.buffer.undo();
+        assert_eq!(ed.buffer.text(), text);
+    }
+}
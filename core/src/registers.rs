@@ -0,0 +1,243 @@
+// core/src/registers.rs
+//
+// Registers: text, numbers and positions saved under a single character,
+// as in Emacs. A position register keeps a marker in its buffer
+// (`Buffer::markers`), so it follows the text as lines are inserted or
+// deleted above it; the line and column stored with it are only used once
+// the buffer is gone and its file has to be visited again.
+//
+// The editor shows one window, so there are no window-configuration
+// registers.
+
+use std::path::PathBuf;
+use crate::buffer::Buffer;
+use crate::editor::editor::Editor;
+
+pub const REGISTERS_BUFFER: &str = "*Registers*";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Register {
+    Text(String),
+    Number(i64),
+    Position {
+        buffer: String,
+        file: Option<PathBuf>,
+        x: usize,
+        y: usize,
+    },
+}
+
+/// The key of register `c`'s marker in its buffer.
+fn marker_key(c: char) -> String {
+    format!("register {c}")
+}
+
+/// Whether `buf` is the buffer a position register was saved in: names
+/// alone can be taken again once that buffer is killed.
+fn holds_position(buf: &Buffer, buffer: &str, file: &Option<PathBuf>) -> bool {
+    buf.file_name() == buffer && buf.absolute_path() == *file
+}
+
+/// The register named by minibuffer input: its first char.
+pub fn register_name(input: &str) -> Option<char> {
+    input.chars().next()
+}
+
+// ---- Editor integration ----
+impl Editor {
+    fn position_buffer(&self, buffer: &str, file: &Option<PathBuf>) -> Option<&Buffer> {
+        std::iter::once(&self.buffer).chain(self.buffers.iter()).find(|b| holds_position(b, buffer, file))
+    }
+
+    fn position_buffer_mut(&mut self, buffer: &str, file: &Option<PathBuf>) -> Option<&mut Buffer> {
+        std::iter::once(&mut self.buffer).chain(self.buffers.iter_mut()).find(|b| holds_position(b, buffer, file))
+    }
+
+    /// Register `c`, with a position register's line and column taken from
+    /// its marker while the buffer is open.
+    pub fn register(&self, c: char) -> Option<Register> {
+        let mut reg = self.registers.get(&c)?.clone();
+        if let Register::Position { buffer, file, x, y } = &mut reg
+            && let Some(&(mx, my)) = self.position_buffer(buffer, file).and_then(|b| b.markers.get(&marker_key(c))) {
+            (*x, *y) = (mx, my);
+        }
+        Some(reg)
+    }
+
+    /// Store `reg` in register `c`. A position in an open buffer gets a
+    /// marker there.
+    pub fn set_register(&mut self, c: char, reg: Register) {
+        self.drop_register_marker(c);
+        if let Register::Position { buffer, file, x, y } = &reg
+            && let Some(buf) = self.position_buffer_mut(buffer, file) {
+            buf.markers.insert(marker_key(c), (*x, *y));
+        }
+        self.registers.insert(c, reg);
+    }
+
+    /// Copy the markers of the current buffer back into its registers,
+    /// before the buffer goes away.
+    pub(crate) fn save_register_positions(&mut self) {
+        for (c, reg) in self.registers.iter_mut() {
+            if let Register::Position { buffer, file, x, y } = reg
+                && holds_position(&self.buffer, buffer, file)
+                && let Some(&pos) = self.buffer.markers.get(&marker_key(*c)) {
+                (*x, *y) = pos;
+            }
+        }
+    }
+
+    pub fn clear_register(&mut self, c: char) {
+        self.drop_register_marker(c);
+        self.registers.remove(&c);
+    }
+
+    fn drop_register_marker(&mut self, c: char) {
+        if let Some(Register::Position { buffer, file, .. }) = self.registers.get(&c) {
+            let (buffer, file) = (buffer.clone(), file.clone());
+            if let Some(buf) = self.position_buffer_mut(&buffer, &file) {
+                buf.markers.remove(&marker_key(c));
+            }
+        }
+    }
+
+    /// `copy-to-register`: save the region's text.
+    pub fn copy_to_register(&mut self, c: char) {
+        let Some(text) = self.buffer.copy_region() else {
+            self.minibuffer.message("The mark is not set now, so there is no region");
+            return;
+        };
+        self.set_register(c, Register::Text(text));
+        self.buffer.clear_mark();
+        self.minibuffer.message(&format!("Copied region to register {c}"));
+    }
+
+    /// `insert-register`: insert a text or number register at the cursor.
+    pub fn insert_register(&mut self, c: char) {
+        let text = match self.registers.get(&c) {
+            Some(Register::Text(text)) => text.clone(),
+            Some(Register::Number(n)) => n.to_string(),
+            Some(Register::Position { .. }) => {
+                self.minibuffer.message(&format!("Register {c} holds a position; use jump-to-register"));
+                return;
+            }
+            None => {
+                self.minibuffer.message(&format!("Register {c} is empty"));
+                return;
+            }
+        };
+        if self.barf_if_read_only() {
+            return;
+        }
+        self.buffer.yank(&text);
+        self.ensure_cursor_visible();
+        self.emit_buffer_changed("insert-register");
+    }
+
+    /// `point-to-register`: save the cursor's buffer and position.
+    pub fn point_to_register(&mut self, c: char) {
+        let reg = Register::Position {
+            buffer: self.buffer.file_name(),
+            file: self.buffer.absolute_path(),
+            x: self.buffer.cursor_x,
+            y: self.buffer.cursor_y,
+        };
+        self.set_register(c, reg);
+        self.minibuffer.message(&format!("Saved position in register {c}"));
+    }
+
+    /// `jump-to-register`: go to a position register, visiting its file
+    /// again when the buffer was killed.
+    pub fn jump_to_register(&mut self, c: char) {
+        let Some(Register::Position { buffer, file, x, y }) = self.register(c) else {
+            let msg = if self.registers.contains_key(&c) { "does not hold a position" } else { "is empty" };
+            self.minibuffer.message(&format!("Register {c} {msg}"));
+            return;
+        };
+        if self.position_buffer(&buffer, &file).is_some() {
+            self.switch_to_buffer(&buffer);
+        } else if let Some(path) = file {
+            if let Err(e) = self.visit_file(path) {
+                self.minibuffer.message(&format!("Register {c}: {e}"));
+                return;
+            }
+            // Keep following the text from now on.
+            let name = self.buffer.file_name();
+            self.set_register(c, Register::Position { buffer: name, file: self.buffer.absolute_path(), x, y });
+        } else {
+            self.minibuffer.message(&format!("Register {c}: buffer {buffer} no longer exists"));
+            return;
+        }
+        self.buffer.clear_mark();
+        self.buffer.cursor_y = y.min(self.buffer.lines.len() - 1);
+        self.buffer.cursor_x = x.min(self.buffer.lines[self.buffer.cursor_y].char_len);
+        self.ensure_cursor_visible();
+    }
+
+    /// `number-to-register`.
+    pub fn number_to_register(&mut self, c: char, n: i64) {
+        self.set_register(c, Register::Number(n));
+        self.minibuffer.message(&format!("Register {c} set to {n}"));
+    }
+
+    /// `increment-register`: add `n` to a number register, or append the
+    /// region to a text register.
+    pub fn increment_register(&mut self, c: char, n: i64) {
+        match self.registers.get_mut(&c) {
+            Some(Register::Number(value)) => {
+                *value += n;
+                let value = *value;
+                self.minibuffer.message(&format!("Register {c} set to {value}"));
+            }
+            Some(Register::Text(text)) => {
+                let Some(region) = self.buffer.copy_region() else {
+                    self.minibuffer.message("The mark is not set now, so there is no region");
+                    return;
+                };
+                text.push_str(&region);
+                self.buffer.clear_mark();
+                self.minibuffer.message(&format!("Appended region to register {c}"));
+            }
+            Some(Register::Position { .. }) => {
+                self.minibuffer.message(&format!("Register {c} holds a position"));
+            }
+            None => self.minibuffer.message(&format!("Register {c} is empty")),
+        }
+    }
+
+    /// One line per register, for `list-registers`.
+    pub fn describe_registers(&self) -> String {
+        let mut names: Vec<char> = self.registers.keys().copied().collect();
+        names.sort();
+        let mut out = String::new();
+        for c in names {
+            let Some(reg) = self.register(c) else { continue };
+            let what = match reg {
+                Register::Text(text) => {
+                    let first = text.lines().next().unwrap_or("");
+                    let more = if text.lines().count() > 1 { " ..." } else { "" };
+                    format!("text: {first}{more}")
+                }
+                Register::Number(n) => format!("number: {n}"),
+                Register::Position { buffer, x, y, .. } => {
+                    format!("position: {buffer} line {}, column {}", y + 1, x)
+                }
+            };
+            out.push_str(&format!("{c}  {what}\n"));
+        }
+        out
+    }
+
+    /// `list-registers`: show the registers in a read-only buffer.
+    pub fn list_registers(&mut self) {
+        if self.registers.is_empty() {
+            self.minibuffer.message("No registers");
+            return;
+        }
+        let text = self.describe_registers();
+        let buf = self.buffer_mut_or_create(REGISTERS_BUFFER);
+        buf.set_text_raw(&text);
+        buf.read_only = true;
+        self.switch_to_buffer(REGISTERS_BUFFER);
+    }
+}
@@ -1,26 +1,175 @@
+// core/src/status.rs
+//
+// The mode line: a format of segments aligned left and right, each naming a
+// built-in value (see `BUILTIN_SEGMENTS`), a Lua-defined one, or literal
+// text, with an optional face. `build_status` evaluates the format for the
+// current buffer; the front end only lays the pieces out.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use mlua::{Function, Lua, RegistryKey};
 use crate::editor::editor::Editor;
+use crate::project::project_name;
+use crate::diagnostics::Severity;
+
+pub const BUILTIN_SEGMENTS: &[&str] = &[
+    "undo",
+    "buffer-name",
+    "modified",
+    "buffer-id",
+    "read-only",
+    "position",
+    "line",
+    "column",
+    "percent",
+    "major-mode",
+    "encoding",
+    "project",
+    "git-branch",
+    "diagnostics",
+    "cursors",
+    "macro",
+];
+
+/// Colors are names (`red`, `lightblue`, ...) or `#rrggbb`; the front end
+/// parses them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Face {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
 
 #[derive(Debug, Clone)]
-pub struct StatusInfo {
-    pub file_name: String,
-    pub modified: bool,
-    pub undo_depth: usize,
-    pub cursor_line: usize,
-    pub cursor_col: usize,
-    pub scroll_percent: String,
+pub enum SegmentKind {
+    /// A built-in or Lua-defined segment.
+    Named(String),
+    Text(String),
 }
 
-pub fn build_status(editor: &mut Editor) -> StatusInfo {
-    let buffer = &editor.buffer;
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub face: Face,
+}
 
-    StatusInfo {
-        file_name: buffer.file_name(),
-        modified: buffer.is_modified(),
-        undo_depth: buffer.undo_depth(),
-        cursor_line: buffer.cursor_y + 1,
-        cursor_col: buffer.cursor_x + 1,
-				scroll_percent: editor.scroll_indicator()       
+impl Segment {
+    pub fn named(name: &str) -> Self {
+        Self { kind: SegmentKind::Named(name.to_string()), face: Face::default() }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ModeLineFormat {
+    pub left: Vec<Segment>,
+    pub right: Vec<Segment>,
+}
 
+impl Default for ModeLineFormat {
+    fn default() -> Self {
+        let named = |names: &[&str]| names.iter().map(|n| Segment::named(n)).collect();
+        Self {
+            left: named(&["undo", "read-only", "buffer-id", "project", "diagnostics", "cursors", "macro", "percent", "position"]),
+            right: named(&["git-branch", "major-mode"]),
+        }
+    }
+}
+
+/// Segment functions defined from Lua, by name.
+pub type LuaSegments = Rc<RefCell<HashMap<String, RegistryKey>>>;
+
+/// One evaluated segment.
+#[derive(Debug, Clone)]
+pub struct StatusText {
+    pub text: String,
+    pub face: Face,
+}
+
+/// The evaluated mode line. Empty segments are left out; the front end
+/// separates the rest with a space.
+#[derive(Debug, Clone, Default)]
+pub struct StatusLine {
+    pub left: Vec<StatusText>,
+    pub right: Vec<StatusText>,
+}
+
+/// Call the Lua segment functions. Runs with the editor not borrowed, since
+/// they may call back into it; a failing function shows `?`.
+pub fn eval_lua_segments(segments: &LuaSegments, lua: &Lua) -> HashMap<String, String> {
+    let funcs: Vec<(String, Function)> = segments.borrow().iter()
+        .filter_map(|(name, key)| Some((name.clone(), lua.registry_value::<Function>(key).ok()?)))
+        .collect();
+    funcs.into_iter()
+        .map(|(name, func)| {
+            let text = match func.call::<_, Option<String>>(()) {
+                Ok(text) => text.unwrap_or_default(),
+                Err(_) => "?".to_string(),
+            };
+            (name, text)
+        })
+        .collect()
+}
+
+fn builtin_segment(editor: &Editor, name: &str) -> Option<String> {
+    let buffer = &editor.buffer;
+    let modified = if buffer.is_modified() { "*" } else { "" };
+    Some(match name {
+        "undo" => match buffer.undo_depth() {
+            0 => "U:—".to_string(),
+            n => format!("U:{}", "*".repeat(n.min(3))),
+        },
+        "buffer-name" => buffer.file_name(),
+        "modified" => modified.to_string(),
+        "buffer-id" => format!("{}{modified}", buffer.file_name()),
+        "read-only" => match (buffer.read_only, buffer.is_modified()) {
+            (false, _) => String::new(),
+            (true, false) => "%%".to_string(),
+            (true, true) => "%*".to_string(),
+        },
+        "position" => format!("({}, {})", buffer.cursor_y + 1, buffer.cursor_x + 1),
+        "line" => (buffer.cursor_y + 1).to_string(),
+        "column" => (buffer.cursor_x + 1).to_string(),
+        "percent" => format!("{:>3}", editor.scroll_indicator()),
+        "major-mode" => buffer.major_mode.clone(),
+        "encoding" => "utf-8".to_string(),
+        "project" => editor.project_root().map(|root| format!("[{}]", project_name(&root))).unwrap_or_default(),
+        "git-branch" => editor.git_branch().unwrap_or_default(),
+        "diagnostics" => {
+            let diagnostics = editor.buffer_diagnostics();
+            let count = |s: Severity| diagnostics.iter().filter(|d| d.severity == s).count();
+            let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+            if errors + warnings > 0 { format!("E:{errors} W:{warnings}") } else { String::new() }
+        }
+        "cursors" => match buffer.cursors.len() {
+            0 => String::new(),
+            n => format!("MC:{}", n + 1),
+        },
+        "macro" => if editor.defining_macro() { "Def".to_string() } else { String::new() },
+        _ => return None,
+    })
+}
+
+fn eval_segments(editor: &Editor, segments: &[Segment]) -> Vec<StatusText> {
+    segments.iter()
+        .filter_map(|seg| {
+            let text = match &seg.kind {
+                SegmentKind::Text(text) => text.clone(),
+                SegmentKind::Named(name) => builtin_segment(editor, name)
+                    .or_else(|| editor.mode_line_values.get(name).cloned())
+                    .unwrap_or_default(),
+            };
+            (!text.is_empty()).then(|| StatusText { text, face: seg.face.clone() })
+        })
+        .collect()
+}
+
+pub fn build_status(editor: &mut Editor) -> StatusLine {
+    let format = editor.user_config.borrow().mode_line.clone();
+    StatusLine {
+        left: eval_segments(editor, &format.left),
+        right: eval_segments(editor, &format.right),
+    }
+}
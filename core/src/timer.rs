@@ -0,0 +1,337 @@
+// core/src/timer.rs
+//
+// Timers and idle callbacks. The frontend's main loop polls the wheel on
+// every tick and runs whatever became due. Time comes from a `Clock`, so the
+// wheel can be driven by a `ManualClock` instead of the wall clock.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use mlua::{Lua, RegistryKey, Function, UserData, UserDataMethods};
+use crate::log::{SharedLog, log_lua_error};
+
+pub type TimerId = u64;
+
+// ---- Clocks ----
+pub trait Clock {
+    /// Monotonic time since the clock was created.
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+impl<C: Clock> Clock for Rc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+// ---- Timers ----
+enum TimerKind {
+    Once,
+    Repeat(Duration),
+}
+
+struct Timer<T> {
+    kind: TimerKind,
+    callback: Rc<T>,
+}
+
+struct IdleTimer<T> {
+    after: Duration,
+    /// Already fired during the current idle period.
+    fired: bool,
+    callback: Rc<T>,
+}
+
+/// Pending timers ordered by deadline, plus idle timers that fire once every
+/// time the user has been inactive for long enough.
+pub struct TimerWheel<T> {
+    clock: Box<dyn Clock>,
+    next_id: TimerId,
+    slots: BTreeMap<(Duration, TimerId), Timer<T>>,
+    idle: BTreeMap<TimerId, IdleTimer<T>>,
+    last_activity: Duration,
+}
+
+impl<T> Default for TimerWheel<T> {
+    fn default() -> Self {
+        Self::new(Box::new(SystemClock::default()))
+    }
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        let last_activity = clock.now();
+        Self {
+            clock,
+            next_id: 1,
+            slots: BTreeMap::new(),
+            idle: BTreeMap::new(),
+            last_activity,
+        }
+    }
+
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    fn alloc_id(&mut self) -> TimerId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn set_timeout(&mut self, delay: Duration, callback: T) -> TimerId {
+        let id = self.alloc_id();
+        let due = self.now() + delay;
+        self.slots.insert((due, id), Timer { kind: TimerKind::Once, callback: Rc::new(callback) });
+        id
+    }
+
+    pub fn set_interval(&mut self, period: Duration, callback: T) -> TimerId {
+        let id = self.alloc_id();
+        let period = period.max(Duration::from_millis(1));
+        let due = self.now() + period;
+        self.slots.insert((due, id), Timer { kind: TimerKind::Repeat(period), callback: Rc::new(callback) });
+        id
+    }
+
+    pub fn run_when_idle(&mut self, after: Duration, callback: T) -> TimerId {
+        let id = self.alloc_id();
+        self.idle.insert(id, IdleTimer { after, fired: false, callback: Rc::new(callback) });
+        id
+    }
+
+    /// Returns false if the timer already fired or never existed.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        if self.idle.remove(&id).is_some() {
+            return true;
+        }
+        let key = self.slots.keys().find(|(_, tid)| *tid == id).copied();
+        key.and_then(|k| self.slots.remove(&k)).is_some()
+    }
+
+    pub fn is_pending(&self, id: TimerId) -> bool {
+        self.idle.contains_key(&id) || self.slots.keys().any(|(_, tid)| *tid == id)
+    }
+
+    /// User input happened: restart the idle period.
+    pub fn note_activity(&mut self) {
+        self.last_activity = self.now();
+        for t in self.idle.values_mut() {
+            t.fired = false;
+        }
+    }
+
+    pub fn idle_time(&self) -> Duration {
+        self.now().saturating_sub(self.last_activity)
+    }
+
+    /// How long the main loop may sleep before something becomes due.
+    pub fn time_until_next(&self) -> Option<Duration> {
+        let now = self.now();
+        let timer = self.slots.keys().next().map(|(due, _)| due.saturating_sub(now));
+        let idle = self.idle.values()
+            .filter(|t| !t.fired)
+            .map(|t| (self.last_activity + t.after).saturating_sub(now))
+            .min();
+        match (timer, idle) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Collect every callback that is due, in deadline order. Intervals are
+    /// rescheduled, one-shot timers are dropped.
+    pub fn poll(&mut self) -> Vec<Rc<T>> {
+        let now = self.now();
+        let mut due = Vec::new();
+
+        while let Some(entry) = self.slots.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let ((deadline, id), timer) = entry.remove_entry();
+            due.push(timer.callback.clone());
+            if let TimerKind::Repeat(period) = timer.kind {
+                // Skip missed periods instead of firing them in a burst.
+                let mut next = deadline + period;
+                while next <= now {
+                    next += period;
+                }
+                self.slots.insert((next, id), timer);
+            }
+        }
+
+        let idle = now.saturating_sub(self.last_activity);
+        for t in self.idle.values_mut() {
+            if !t.fired && idle >= t.after {
+                t.fired = true;
+                due.push(t.callback.clone());
+            }
+        }
+
+        due
+    }
+}
+
+// ---- Lua glue ----
+pub type LuaTimers = Rc<RefCell<TimerWheel<RegistryKey>>>;
+
+/// Returned to Lua by `set_timeout`, `set_interval` and `run_when_idle`.
+pub struct TimerHandle {
+    pub id: TimerId,
+    pub timers: LuaTimers,
+}
+
+impl UserData for TimerHandle {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("cancel", |_, this, ()| {
+            Ok(this.timers.borrow_mut().cancel(this.id))
+        });
+        methods.add_method("pending", |_, this, ()| {
+            Ok(this.timers.borrow().is_pending(this.id))
+        });
+        methods.add_method("id", |_, this, ()| Ok(this.id));
+    }
+}
+
+/// Run every due Lua callback, logging the ones that fail. The wheel is
+/// not borrowed while callbacks run, so they may add or cancel timers
+/// themselves.
+pub fn run_due_timers(timers: &LuaTimers, lua: &Lua, log: &SharedLog) {
+    let due = timers.borrow_mut().poll();
+    for key in due {
+        if let Ok(func) = lua.registry_value::<Function>(&key) {
+            log_lua_error(log, "timer", func.call::<_, ()>(()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    /// A wheel of `&str` callbacks on a clock the test moves.
+    fn wheel() -> (TimerWheel<&'static str>, Rc<ManualClock>) {
+        let clock = Rc::new(ManualClock::default());
+        (TimerWheel::new(Box::new(clock.clone())), clock)
+    }
+
+    fn fired(wheel: &mut TimerWheel<&'static str>) -> Vec<&'static str> {
+        wheel.poll().iter().map(|cb| **cb).collect()
+    }
+
+    #[test]
+    fn timeout_fires_once() {
+        let (mut wheel, clock) = wheel();
+        let id = wheel.set_timeout(ms(100), "once");
+        clock.advance(ms(99));
+        assert!(fired(&mut wheel).is_empty());
+        assert_eq!(wheel.time_until_next(), Some(ms(1)));
+        clock.advance(ms(1));
+        assert_eq!(fired(&mut wheel), ["once"]);
+        clock.advance(ms(1000));
+        assert!(fired(&mut wheel).is_empty());
+        assert!(!wheel.is_pending(id));
+    }
+
+    #[test]
+    fn interval_skips_missed_periods() {
+        let (mut wheel, clock) = wheel();
+        wheel.set_interval(ms(10), "tick");
+        clock.advance(ms(10));
+        assert_eq!(fired(&mut wheel), ["tick"]);
+        // A long stall runs it once, not once per missed period.
+        clock.advance(ms(95));
+        assert_eq!(fired(&mut wheel), ["tick"]);
+        assert_eq!(wheel.time_until_next(), Some(ms(5)));
+        clock.advance(ms(5));
+        assert_eq!(fired(&mut wheel), ["tick"]);
+    }
+
+    #[test]
+    fn due_timers_fire_in_deadline_order() {
+        let (mut wheel, clock) = wheel();
+        wheel.set_timeout(ms(30), "late");
+        wheel.set_timeout(ms(10), "early");
+        clock.advance(ms(30));
+        assert_eq!(fired(&mut wheel), ["early", "late"]);
+    }
+
+    #[test]
+    fn idle_callbacks_fire_once_per_idle_period() {
+        let (mut wheel, clock) = wheel();
+        wheel.run_when_idle(ms(500), "idle");
+        clock.advance(ms(400));
+        wheel.note_activity();
+        clock.advance(ms(400));
+        assert!(fired(&mut wheel).is_empty());
+        clock.advance(ms(100));
+        assert_eq!(fired(&mut wheel), ["idle"]);
+        clock.advance(ms(1000));
+        assert!(fired(&mut wheel).is_empty());
+
+        wheel.note_activity();
+        assert_eq!(wheel.idle_time(), Duration::ZERO);
+        clock.advance(ms(500));
+        assert_eq!(fired(&mut wheel), ["idle"]);
+    }
+
+    #[test]
+    fn cancelled_timers_never_fire() {
+        let (mut wheel, clock) = wheel();
+        let once = wheel.set_timeout(ms(10), "once");
+        let tick = wheel.set_interval(ms(10), "tick");
+        let idle = wheel.run_when_idle(ms(10), "idle");
+        assert!(wheel.cancel(once));
+        assert!(wheel.cancel(tick));
+        assert!(wheel.cancel(idle));
+        assert!(!wheel.cancel(once));
+        clock.advance(ms(100));
+        assert!(fired(&mut wheel).is_empty());
+        assert_eq!(wheel.time_until_next(), None);
+    }
+}
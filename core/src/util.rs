@@ -0,0 +1,106 @@
+// core/src/util.rs
+//
+// Small helpers shared by several subsystems.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Broken-down UTC time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    pub fn from_unix(secs: i64) -> Self {
+        let days = secs.div_euclid(86_400);
+        let rem = secs.rem_euclid(86_400);
+
+        // Civil-from-days (Howard Hinnant's algorithm).
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u32,
+            minute: (rem % 3600 / 60) as u32,
+            second: (rem % 60) as u32,
+        }
+    }
+
+    pub fn from_system(t: SystemTime) -> Self {
+        let secs = match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        Self::from_unix(secs)
+    }
+
+    pub fn now() -> Self {
+        Self::from_system(SystemTime::now())
+    }
+
+    /// `2024-05-01 13:07`
+    pub fn format_minutes(&self) -> String {
+        format!("{:04}-{:02}-{:02} {:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute)
+    }
+
+    /// `2024-05-01 13:07:42`
+    pub fn format_seconds(&self) -> String {
+        format!("{}:{:02}", self.format_minutes(), self.second)
+    }
+
+    /// `13:07:42`
+    pub fn format_time(&self) -> String {
+        format!("{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(secs: i64) -> (i64, u32, u32) {
+        let t = DateTime::from_unix(secs);
+        (t.year, t.month, t.day)
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(date(0), (1970, 1, 1));
+        assert_eq!(date(951_782_400), (2000, 2, 29));
+        assert_eq!(date(951_868_800), (2000, 3, 1));
+        // 2100 is not a leap year.
+        assert_eq!(date(4_107_456_000), (2100, 2, 28));
+        assert_eq!(date(4_107_542_400), (2100, 3, 1));
+        assert_eq!(date(1_704_067_199), (2023, 12, 31));
+    }
+
+    #[test]
+    fn times_before_the_epoch() {
+        let t = DateTime::from_unix(-1);
+        assert_eq!(t.format_seconds(), "1969-12-31 23:59:59");
+        assert_eq!(date(-86_400 * 365), (1969, 1, 1));
+    }
+
+    #[test]
+    fn formats() {
+        let t = DateTime::from_unix(1_714_568_862);
+        assert_eq!(t.format_seconds(), "2024-05-01 13:07:42");
+        assert_eq!(t.format_minutes(), "2024-05-01 13:07");
+        assert_eq!(t.format_time(), "13:07:42");
+    }
+}
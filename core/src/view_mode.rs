@@ -0,0 +1,76 @@
+// core/src/view_mode.rs
+//
+// View mode: a buffer made for reading. Turning it on makes the buffer
+// read-only; SPC and DEL page down and up, `<` and `>` go to either end and
+// `q` leaves the mode and goes back to the previous buffer. Leaving puts the
+// read-only flag back as it was, so a listing stays protected.
+//
+// The keys are the `view` mode keymap. `lookup_key` tries it before the
+// major mode's while the mode is on, so `bind_mode("view", ...)` changes
+// them in every buffer.
+
+use crate::editor::editor::{Editor, KeyMap, Modifiers};
+
+pub const VIEW_MODE: &str = "view";
+
+pub fn view_mode_keymap() -> KeyMap {
+    let mut map = KeyMap::new();
+    for (key, cmd) in [
+        (' ', "scroll-down-command"),
+        ('\x7f', "scroll-up-command"),
+        ('<', "move-beginning-of-buffer"),
+        ('>', "move-end-of-buffer"),
+        ('q', "view-quit"),
+    ] {
+        map.bind(Modifiers::none(), key, cmd.to_string());
+    }
+    map
+}
+
+// ---- Editor integration ----
+impl Editor {
+    pub fn view_mode_enter(&mut self) {
+        if self.buffer.view_mode.is_none() {
+            self.buffer.view_mode = Some(self.buffer.read_only);
+            self.buffer.read_only = true;
+        }
+        self.minibuffer.message("View mode: SPC/DEL to page, q to quit");
+    }
+
+    pub fn view_mode_exit(&mut self) {
+        if let Some(read_only) = self.buffer.view_mode.take() {
+            self.buffer.read_only = read_only;
+        }
+    }
+
+    /// `view-mode`.
+    pub fn toggle_view_mode(&mut self) {
+        if self.buffer.view_mode.is_some() {
+            self.view_mode_exit();
+            self.minibuffer.message("View mode disabled");
+        } else {
+            self.view_mode_enter();
+        }
+    }
+
+    /// `view-quit`: leave view mode and show the most recently used other
+    /// buffer.
+    pub fn view_quit(&mut self) {
+        self.view_mode_exit();
+        if let Some(name) = self.buffers.first().map(|b| b.file_name()) {
+            self.switch_to_buffer(&name);
+        }
+    }
+
+    /// `toggle-read-only`. Making the buffer writable also ends view mode.
+    pub fn toggle_read_only(&mut self) {
+        if self.buffer.read_only {
+            self.buffer.view_mode = None;
+            self.buffer.read_only = false;
+            self.minibuffer.message("Read-only mode disabled");
+        } else {
+            self.buffer.read_only = true;
+            self.minibuffer.message("Read-only mode enabled");
+        }
+    }
+}
@@ -0,0 +1,316 @@
+// core/src/walk.rs
+//
+// Directory walking shared by grep and project file listing. Hidden entries
+// are skipped and `.gitignore` files are honoured, from the enclosing
+// repository root down, so results match what `git` or `rg` would show.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// One `.gitignore` line.
+struct IgnoreRule {
+    pattern: String,
+    negate: bool,
+    dir_only: bool,
+    /// Matched against the whole relative path instead of the file name.
+    anchored: bool,
+}
+
+struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    fn load(dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Self::parse(dir, &text))
+    }
+
+    fn parse(dir: &Path, text: &str) -> Self {
+        let rules = text.lines().filter_map(|raw| {
+            let line = raw.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (negate, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = line.trim_start_matches('/').to_string();
+            Some(IgnoreRule { pattern, negate, dir_only, anchored })
+        }).collect();
+        Self { base: dir.to_path_buf(), rules }
+    }
+}
+
+/// Last matching rule wins, across all `.gitignore` files from the
+/// repository root down to the file's directory.
+fn is_ignored(stack: &[IgnoreFile], path: &Path, is_dir: bool) -> bool {
+    let mut ignored = false;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    for file in stack {
+        let Ok(rel) = path.strip_prefix(&file.base) else { continue };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        for rule in &file.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let subject = if rule.anchored { rel.as_str() } else { name };
+            if glob_match(&rule.pattern, subject) {
+                ignored = !rule.negate;
+            }
+        }
+    }
+    ignored
+}
+
+/// Shell-style glob: `*` and `?` stop at `/`, `**` crosses directories,
+/// `[abc]` / `[a-z]` / `[!a]` match one char.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    glob_at(&p, &t)
+}
+
+fn glob_at(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => match p[2..].strip_prefix(&['/']) {
+            // `**/` matches whole directories only.
+            Some(rest) => (0..=t.len())
+                .filter(|&i| i == 0 || t[i - 1] == '/')
+                .any(|i| glob_at(rest, &t[i..])),
+            None => (0..=t.len()).any(|i| glob_at(&p[2..], &t[i..])),
+        },
+        Some('*') => {
+            for i in 0..=t.len() {
+                if glob_at(&p[1..], &t[i..]) {
+                    return true;
+                }
+                if t.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => t.first().is_some_and(|c| *c != '/') && glob_at(&p[1..], &t[1..]),
+        Some('[') => {
+            let Some(close) = p.iter().skip(1).position(|c| *c == ']').map(|i| i + 1) else {
+                return t.first() == Some(&'[') && glob_at(&p[1..], &t[1..]);
+            };
+            let Some(&c) = t.first() else { return false };
+            let class = &p[1..close];
+            let (negate, class) = match class.first() {
+                Some('!') | Some('^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut hit = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    hit |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    hit |= class[i] == c;
+                    i += 1;
+                }
+            }
+            hit != negate && glob_at(&p[close + 1..], &t[1..])
+        }
+        Some(c) => t.first() == Some(c) && glob_at(&p[1..], &t[1..]),
+    }
+}
+
+/// `.gitignore` files of the enclosing repository, from its root down to
+/// (but excluding) `dir`.
+fn ancestor_ignores(dir: &Path) -> Vec<IgnoreFile> {
+    if dir.join(".git").exists() {
+        return Vec::new();
+    }
+    let mut chain = Vec::new();
+    for anc in dir.ancestors().skip(1) {
+        chain.push(anc);
+        if anc.join(".git").exists() {
+            return chain.iter().rev().filter_map(|d| IgnoreFile::load(d)).collect();
+        }
+    }
+    // Not inside a repository: parent ignore files do not apply.
+    Vec::new()
+}
+
+/// Call `visit` for every regular file under `root`, in sorted order.
+/// Stops early when `visit` returns false or `cancel` is set.
+pub fn walk_files(root: &Path, cancel: &AtomicBool, visit: &mut dyn FnMut(&Path) -> bool) {
+    let mut stack = ancestor_ignores(root);
+    walk(root, &mut stack, cancel, visit);
+}
+
+fn walk(
+    dir: &Path,
+    stack: &mut Vec<IgnoreFile>,
+    cancel: &AtomicBool,
+    visit: &mut dyn FnMut(&Path) -> bool,
+) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else { return true };
+    let pushed = match IgnoreFile::load(dir) {
+        Some(f) => {
+            stack.push(f);
+            true
+        }
+        None => false,
+    };
+
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    let mut more = true;
+    for entry in entries {
+        if cancel.load(Ordering::Relaxed) {
+            more = false;
+            break;
+        }
+        let path = entry.path();
+        // Hidden files and directories are skipped, like ripgrep does.
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(kind) = entry.file_type() else { continue };
+        if is_ignored(stack, &path, kind.is_dir()) {
+            continue;
+        }
+        more = if kind.is_dir() {
+            walk(&path, stack, cancel, visit)
+        } else if kind.is_file() {
+            visit(&path)
+        } else {
+            true
+        };
+        if !more {
+            break;
+        }
+    }
+
+    if pushed {
+        stack.pop();
+    }
+    more
+}
+
+/// Every non-ignored file under `root`, relative to it.
+pub fn list_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    walk_files(root, &AtomicBool::new(false), &mut |path| {
+        let rel = path.strip_prefix(root).unwrap_or(path);
+        files.push(rel.to_string_lossy().into_owned());
+        true
+    });
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(gitignore: &str, path: &str, is_dir: bool) -> bool {
+        let stack = [IgnoreFile::parse(Path::new("/repo"), gitignore)];
+        is_ignored(&stack, &Path::new("/repo").join(path), is_dir)
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("?.c", "a.c"));
+        assert!(!glob_match("?", "/"));
+        assert!(glob_match("[a-c]x", "bx"));
+        assert!(!glob_match("[!a-c]x", "bx"));
+        assert!(glob_match("[^a]x", "zx"));
+        assert!(glob_match("[x", "[x"));
+    }
+
+    #[test]
+    fn glob_double_star() {
+        assert!(glob_match("**/foo", "foo"));
+        assert!(glob_match("**/foo", "a/b/foo"));
+        assert!(!glob_match("**/foo", "a/xfoo"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(!glob_match("a/**/b", "a/xb"));
+        assert!(glob_match("a/**", "a/x/y"));
+        assert!(!glob_match("a/**", "a"));
+    }
+
+    #[test]
+    fn unanchored_patterns_match_the_name_anywhere() {
+        assert!(ignored("*.o\n", "main.o", false));
+        assert!(ignored("*.o\n", "src/deep/main.o", false));
+        assert!(ignored("target\n", "sub/target", true));
+        assert!(!ignored("# *.o\n\n", "main.o", false));
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_the_base() {
+        assert!(ignored("/target\n", "target", true));
+        assert!(!ignored("/target\n", "sub/target", true));
+        assert!(ignored("doc/*.txt\n", "doc/a.txt", false));
+        assert!(!ignored("doc/*.txt\n", "doc/x/a.txt", false));
+        assert!(!ignored("doc/*.txt\n", "src/doc/a.txt", false));
+    }
+
+    #[test]
+    fn directory_only_patterns() {
+        assert!(ignored("build/\n", "build", true));
+        assert!(!ignored("build/\n", "build", false));
+        assert!(ignored("build/\n", "src/build", true));
+    }
+
+    #[test]
+    fn negation_last_rule_wins() {
+        let rules = "*.log\n!keep.log\n";
+        assert!(ignored(rules, "debug.log", false));
+        assert!(!ignored(rules, "keep.log", false));
+        assert!(ignored("!keep.log\n*.log\n", "keep.log", false));
+    }
+
+    #[test]
+    fn nested_ignore_files() {
+        let stack = [
+            IgnoreFile::parse(Path::new("/repo"), "*.tmp\n"),
+            IgnoreFile::parse(Path::new("/repo/sub"), "!a.tmp\n/local\n"),
+        ];
+        assert!(is_ignored(&stack, Path::new("/repo/sub/b.tmp"), false));
+        assert!(!is_ignored(&stack, Path::new("/repo/sub/a.tmp"), false));
+        assert!(is_ignored(&stack, Path::new("/repo/a.tmp"), false));
+        assert!(is_ignored(&stack, Path::new("/repo/sub/local"), true));
+        assert!(!is_ignored(&stack, Path::new("/repo/local"), true));
+    }
+
+    #[test]
+    fn list_files_honours_gitignore() {
+        let root = std::env::temp_dir().join(format!("remux-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in [".git", "src", "target/debug", ".hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for (file, text) in [
+            (".gitignore", "/target/\n*.log\n!keep.log\n"),
+            ("src/main.rs", ""),
+            ("src/out.log", ""),
+            ("keep.log", ""),
+            ("target/debug/remux", ""),
+            (".hidden/x", ""),
+        ] {
+            fs::write(root.join(file), text).unwrap();
+        }
+        let files = list_files(&root);
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(files, ["keep.log", "src/main.rs"]);
+    }
+}
@@ -0,0 +1,90 @@
+// headless/src/batch.rs
+//
+// `remux --batch`: open the files, run the steps in each of them (once in
+// `*scratch*` when there are none), save with `--save`, exit. Messages the
+// steps leave and every error go to standard error; `print` from Lua goes
+// to standard output. The exit status is 1 when anything failed: a file
+// that could not be opened or saved, a Lua error, an unknown command or
+// key, a command that left its prompt unanswered.
+
+use std::path::PathBuf;
+
+use remux_core::command::CommandRegistry;
+
+use crate::cli::{open_files, Options, STDIN_BUFFER};
+use crate::harness::Headless;
+
+pub fn run_batch(opts: &Options, registry: CommandRegistry) -> i32 {
+    let headless = match Headless::new(registry, opts.init_file().as_deref()) {
+        Ok(headless) => headless,
+        Err(e) => {
+            eprintln!("remux: {e}");
+            return 1;
+        }
+    };
+
+    let mut status = 0;
+    let errors = open_files(&mut headless.editor.borrow_mut(), opts);
+    for error in &errors {
+        eprintln!("remux: {error}");
+        status = 1;
+    }
+
+    let mut targets: Vec<Option<PathBuf>> = opts.files.iter().map(|f| Some(f.path.clone())).collect();
+    if targets.is_empty() {
+        targets.push(None);
+    }
+    for target in targets {
+        let label = match &target {
+            Some(path) => {
+                let shown = if path.as_os_str() == "-" {
+                    headless.editor.borrow_mut().switch_to_buffer(STDIN_BUFFER);
+                    Ok(())
+                } else {
+                    headless.editor.borrow_mut().visit_file(path.clone())
+                };
+                if shown.is_err() {
+                    continue;
+                }
+                format!("{}: ", path.display())
+            }
+            None => String::new(),
+        };
+        for step in &opts.steps {
+            let mut done = headless.run_step(step);
+            if done.is_ok() && headless.prompt_open() {
+                done = Err(format!("prompt left unanswered: {}", headless.message()));
+                headless.editor.borrow_mut().abort_minibuffer();
+            }
+            if let Some(message) = headless.take_message() {
+                eprintln!("{label}{message}");
+            }
+            if let Err(e) = done {
+                eprintln!("remux: {label}{e}");
+                status = 1;
+                break;
+            }
+            if headless.editor.borrow().should_quit {
+                break;
+            }
+        }
+        if headless.editor.borrow().should_quit {
+            break;
+        }
+    }
+
+    if opts.save {
+        match headless.save_all() {
+            Ok(written) => {
+                for path in written {
+                    eprintln!("Wrote {}", path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("remux: save failed: {e}");
+                status = 1;
+            }
+        }
+    }
+    status
+}
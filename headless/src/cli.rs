@@ -0,0 +1,238 @@
+// headless/src/cli.rs
+//
+// Command line. Files open in the order given, the first one shown;
+// `+LINE[:COL]` before a file, or `FILE:LINE[:COL]` when no file has that
+// whole name, says where its cursor starts. `-` reads standard input into
+// `*stdin*`, so output can be piped in. Steps (`--eval`, `--command`,
+// `--keys`) run in the order given: once after start-up, or with `--batch`
+// once in each file.
+
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use remux_core::config::config_path;
+use remux_core::editor::editor::Editor;
+
+use crate::harness::Step;
+
+pub const STDIN_BUFFER: &str = "*stdin*";
+
+pub const USAGE: &str = "\
+Usage: remux [OPTIONS] [[+LINE[:COL]] FILE[:LINE[:COL]]]...
+
+Options:
+  +LINE[:COL]             Start the next file at LINE (and COL)
+  -                       Read standard input into *stdin*
+  -R, --readonly          Open the files read-only
+  -c, --config PATH       Use PATH instead of ~/.config/remux/init.lua
+  -q, --no-init           Do not load any init file
+  -e, --eval LUA          Run LUA
+  -f, --command NAME[=IN] Run command NAME, answering its prompt with IN
+  -k, --keys KEYS         Press KEYS, e.g. \"C-a C-k\" (see HOOKS.md)
+      --batch             Run without the terminal: run the steps above in
+                          each file (once with no files), then exit; no init
+                          file unless --config is given
+      --save              With --batch, save the modified files at the end
+  -V, --version           Print the version
+  -h, --help              Print this help
+  --                      Treat the remaining arguments as files
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileArg {
+    /// `-` for standard input.
+    pub path: PathBuf,
+    /// 1-based.
+    pub line: Option<usize>,
+    pub col: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub files: Vec<FileArg>,
+    pub readonly: bool,
+    pub config: Option<PathBuf>,
+    pub no_init: bool,
+    pub steps: Vec<Step>,
+    pub batch: bool,
+    pub save: bool,
+}
+
+pub enum Action {
+    Run(Options),
+    Help,
+    Version,
+}
+
+/// `LINE` or `LINE:COL`.
+fn parse_position(s: &str) -> Option<(usize, Option<usize>)> {
+    match s.split_once(':') {
+        Some((line, col)) => Some((line.parse().ok()?, Some(col.parse().ok()?))),
+        None => Some((s.parse().ok()?, None)),
+    }
+}
+
+/// `FILE`, or `FILE:LINE[:COL]` when no file has the whole argument as
+/// its name. `FILE` need not exist: it is created on save.
+fn parse_file(arg: &str) -> FileArg {
+    let whole = FileArg { path: PathBuf::from(arg), line: None, col: None };
+    if whole.path.exists() {
+        return whole;
+    }
+    let mut parts = arg.rsplitn(3, ':');
+    let (last, mid, first) = (parts.next(), parts.next(), parts.next());
+    let split = match (first, mid, last) {
+        (Some(file), Some(line), Some(col)) => line.parse().ok().zip(col.parse().ok())
+            .map(|(line, col)| (file, line, Some(col))),
+        _ => None,
+    };
+    // `file:LINE` alone, or a `:COL` that was not a number.
+    let split = split.or_else(|| {
+        let (file, line) = arg.rsplit_once(':')?;
+        Some((file, line.parse().ok()?, None))
+    });
+    match split {
+        Some((file, line, col)) if !file.is_empty() => FileArg { path: PathBuf::from(file), line: Some(line), col },
+        _ => whole,
+    }
+}
+
+/// `NAME` or `NAME=INPUT`.
+fn command_step(arg: &str) -> Step {
+    match arg.split_once('=') {
+        Some((name, input)) => Step::Command { name: name.to_string(), input: Some(input.to_string()) },
+        None => Step::Command { name: arg.to_string(), input: None },
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Action, String> {
+    let mut opts = Options::default();
+    let mut jump: Option<(usize, Option<usize>)> = None;
+    let mut only_files = false;
+    let mut it = args.iter().skip(1);
+
+    while let Some(arg) = it.next() {
+        let mut value = |name: &str| it.next().cloned().ok_or(format!("{name} needs an argument"));
+        if only_files || arg == "-" || !arg.starts_with(['-', '+']) || arg.len() == 1 {
+            let mut file = if arg == "-" {
+                FileArg { path: PathBuf::from("-"), line: None, col: None }
+            } else {
+                parse_file(arg)
+            };
+            if let Some((line, col)) = jump.take() {
+                (file.line, file.col) = (Some(line), col);
+            }
+            opts.files.push(file);
+            continue;
+        }
+        if let Some(pos) = arg.strip_prefix('+') {
+            jump = Some(parse_position(pos).ok_or(format!("bad position: {arg}"))?);
+            continue;
+        }
+        match arg.as_str() {
+            "--" => only_files = true,
+            "-R" | "--readonly" => opts.readonly = true,
+            "-c" | "--config" => opts.config = Some(PathBuf::from(value(arg)?)),
+            "-q" | "--no-init" => opts.no_init = true,
+            "-e" | "--eval" => opts.steps.push(Step::Eval(value(arg)?)),
+            "-f" | "--command" => opts.steps.push(command_step(&value(arg)?)),
+            "-k" | "--keys" => opts.steps.push(Step::Keys(value(arg)?)),
+            "--batch" => opts.batch = true,
+            "--save" => opts.save = true,
+            "-V" | "--version" => return Ok(Action::Version),
+            "-h" | "--help" => return Ok(Action::Help),
+            _ => {
+                if let Some(path) = arg.strip_prefix("--config=") {
+                    opts.config = Some(PathBuf::from(path));
+                } else if let Some(code) = arg.strip_prefix("--eval=") {
+                    opts.steps.push(Step::Eval(code.to_string()));
+                } else {
+                    return Err(format!("unknown option: {arg}"));
+                }
+            }
+        }
+    }
+    if jump.is_some() {
+        return Err("+LINE must come before a file".to_string());
+    }
+    Ok(Action::Run(opts))
+}
+
+impl Options {
+    /// The init file to run: `--config`, else `init.lua` unless `-q` or
+    /// `--batch` says not to.
+    pub fn init_file(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.config {
+            return Some(path.clone());
+        }
+        if self.no_init || self.batch {
+            return None;
+        }
+        Some(config_path())
+    }
+}
+
+/// Open the files named on the command line, the first one last so that
+/// it ends up shown. Returns what could not be opened.
+pub fn open_files(ed: &mut Editor, opts: &Options) -> Vec<String> {
+    let mut errors = Vec::new();
+    for file in opts.files.iter().rev() {
+        let opened = if file.path.as_os_str() == "-" {
+            read_stdin(ed)
+        } else {
+            ed.visit_file(file.path.clone())
+        };
+        if let Err(e) = opened {
+            errors.push(format!("{}: {e}", file.path.display()));
+            continue;
+        }
+        if let Some(line) = file.line {
+            ed.goto_line(line);
+            let buf = &mut ed.buffer;
+            buf.cursor_x = file.col.unwrap_or(1).saturating_sub(1).min(buf.lines[buf.cursor_y].char_len);
+            ed.ensure_cursor_visible();
+        }
+        if opts.readonly {
+            ed.buffer.read_only = true;
+        }
+    }
+    errors
+}
+
+fn read_stdin(ed: &mut Editor) -> io::Result<()> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    ed.buffer_mut_or_create(STDIN_BUFFER).set_text_raw(text.strip_suffix('\n').unwrap_or(&text));
+    ed.switch_to_buffer(STDIN_BUFFER);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, line: Option<usize>, col: Option<usize>) -> FileArg {
+        FileArg { path: PathBuf::from(path), line, col }
+    }
+
+    #[test]
+    fn file_with_position() {
+        assert_eq!(parse_file("new.rs"), file("new.rs", None, None));
+        assert_eq!(parse_file("new.rs:12"), file("new.rs", Some(12), None));
+        assert_eq!(parse_file("new.rs:12:5"), file("new.rs", Some(12), Some(5)));
+        assert_eq!(parse_file("new.rs:12:x"), file("new.rs:12:x", None, None));
+        assert_eq!(parse_file(":3"), file(":3", None, None));
+    }
+
+    #[test]
+    fn existing_file_named_like_a_position() {
+        let dir = std::env::temp_dir().join(format!("remux-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes:3");
+        std::fs::write(&path, "").unwrap();
+        let arg = path.to_string_lossy().into_owned();
+        let parsed = parse_file(&arg);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(parsed, file(&arg, None, None));
+    }
+}
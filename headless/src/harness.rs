@@ -0,0 +1,263 @@
+// headless/src/harness.rs
+//
+// An editor with Lua but no terminal. Everything a front end would do is a
+// method: open files, run Lua, run commands (answering their prompts), type
+// keys. Lua queues its requests as events; each call processes them and
+// replays any keyboard macro it started before returning, so the editor is
+// settled when it returns.
+
+use std::cell::RefCell;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use mlua::Lua;
+
+use remux_config::lua::load_lua;
+use remux_core::{
+    command::CommandRegistry,
+    commands::builtins::register_builtins,
+    config::UserConfig,
+    editor::editor::{Editor, InputMode, KeyMap},
+    editor::events::EditorEvent,
+    kmacro,
+    minibuffer::MiniBufferMode,
+};
+
+/// One thing to do, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// A Lua chunk.
+    Eval(String),
+    /// A command, and the answer to its prompt if it asks for one.
+    Command { name: String, input: Option<String> },
+    /// Keys in Emacs notation, separated by spaces: `"C-a C-k x RET"`.
+    Keys(String),
+}
+
+pub struct Headless {
+    pub editor: Rc<RefCell<Editor>>,
+    pub lua: Lua,
+    pub lua_events: Rc<RefCell<Vec<EditorEvent>>>,
+}
+
+impl Headless {
+    /// An editor with the commands of `registry`, after running `init`.
+    pub fn new(registry: CommandRegistry, init: Option<&Path>) -> mlua::Result<Self> {
+        let lua = Lua::new();
+        let lua_events = Rc::new(RefCell::new(Vec::new()));
+        let keymap = Rc::new(RefCell::new(KeyMap::new()));
+        let user_config = Rc::new(RefCell::new(UserConfig::default()));
+        let editor = Rc::new(RefCell::new(Editor::new(registry, keymap.clone(), user_config.clone())));
+        load_lua(&lua, editor.clone(), keymap, lua_events.clone(), user_config, init)?;
+        let headless = Self { editor, lua, lua_events };
+        headless.settle();
+        Ok(headless)
+    }
+
+    /// The built-in commands and no init file.
+    pub fn with_builtins() -> mlua::Result<Self> {
+        let mut registry = CommandRegistry::new();
+        register_builtins(&mut registry);
+        Self::new(registry, None)
+    }
+
+    /// Process queued events and replay queued macros, until neither
+    /// leaves more to do.
+    fn settle(&self) {
+        let mut ed = self.editor.borrow_mut();
+        for _ in 0..100 {
+            ed.bus.extend(self.lua_events.borrow_mut().drain(..));
+            let events = ed.process_events(&self.lua);
+            ed.replay_macros();
+            if events.is_empty() && self.lua_events.borrow().is_empty() {
+                break;
+            }
+        }
+    }
+
+    pub fn open(&self, path: impl Into<PathBuf>) -> io::Result<()> {
+        let opened = self.editor.borrow_mut().visit_file(path.into());
+        self.settle();
+        opened
+    }
+
+    /// Replace the current buffer's text, for commands that need no file.
+    pub fn set_text(&self, text: &str) {
+        let mut ed = self.editor.borrow_mut();
+        ed.buffer.set_text_raw(text);
+        ed.buffer.visual.dirty = true;
+    }
+
+    pub fn text(&self) -> String {
+        self.editor.borrow().buffer.text()
+    }
+
+    /// Cursor as (x, y), 0-based.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.editor.borrow().cursor_pos()
+    }
+
+    /// What the minibuffer shows: the last message, or an open prompt.
+    pub fn message(&self) -> String {
+        self.editor.borrow().minibuffer.get().to_string()
+    }
+
+    /// Whether the minibuffer is waiting for input.
+    pub fn prompt_open(&self) -> bool {
+        self.editor.borrow().mode == InputMode::MiniBuffer
+    }
+
+    /// Take the message left by the last step, if any.
+    pub fn take_message(&self) -> Option<String> {
+        let mut ed = self.editor.borrow_mut();
+        if !matches!(ed.minibuffer.mode(), MiniBufferMode::Message { .. }) {
+            return None;
+        }
+        let text = ed.minibuffer.get().to_string();
+        ed.minibuffer.clear();
+        (!text.is_empty()).then_some(text)
+    }
+
+    pub fn eval(&self, code: &str) -> mlua::Result<()> {
+        let done = self.lua.load(code).set_name("--eval").exec();
+        self.settle();
+        done
+    }
+
+    /// Run command `name` as `M-x` would, with the prefix argument already
+    /// set by earlier keys.
+    pub fn command(&self, name: &str) -> Result<(), String> {
+        {
+            let ed = self.editor.borrow();
+            if ed.commands.get(name).is_none() && !ed.named_macros.contains_key(name) {
+                return Err(format!("unknown command: {name}"));
+            }
+        }
+        self.editor.borrow_mut().execute_named(name);
+        self.settle();
+        Ok(())
+    }
+
+    /// Submit `input` to the open minibuffer prompt.
+    pub fn answer(&self, input: &str) -> Result<(), String> {
+        {
+            if !self.prompt_open() {
+                return Err(format!("no prompt to answer with {input:?}"));
+            }
+            let mut ed = self.editor.borrow_mut();
+            ed.minibuffer.push_str(input);
+            ed.execute_minibuffer();
+        }
+        self.settle();
+        Ok(())
+    }
+
+    /// Press `keys`, as in `"C-a C-k x RET"`. They are recorded like typed
+    /// ones while a keyboard macro is being defined.
+    pub fn keys(&self, keys: &str) -> Result<(), String> {
+        for name in keys.split_whitespace() {
+            let key = kmacro::parse_key(name).ok_or_else(|| format!("unknown key: {name}"))?;
+            {
+                let mut ed = self.editor.borrow_mut();
+                ed.record_macro_key(key);
+                ed.handle_key(key);
+            }
+            self.settle();
+        }
+        Ok(())
+    }
+
+    pub fn run_step(&self, step: &Step) -> Result<(), String> {
+        match step {
+            Step::Eval(code) => self.eval(code).map_err(|e| e.to_string()),
+            Step::Command { name, input } => {
+                self.command(name)?;
+                match input {
+                    Some(input) => self.answer(input),
+                    None => Ok(()),
+                }
+            }
+            Step::Keys(keys) => self.keys(keys),
+        }
+    }
+
+    /// Save every modified buffer visiting a file. Returns the files
+    /// written, or the first failure.
+    pub fn save_all(&self) -> io::Result<Vec<PathBuf>> {
+        let mut ed = self.editor.borrow_mut();
+        let ed = &mut *ed;
+        let mut written = Vec::new();
+        for buf in std::iter::once(&mut ed.buffer).chain(ed.buffers.iter_mut()) {
+            if buf.is_modified() && let Some(path) = buf.file_path.clone() {
+                buf.save()?;
+                written.push(path);
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An editor set up by the `init.lua` that ships with Remux.
+    fn headless() -> Headless {
+        let mut registry = CommandRegistry::new();
+        register_builtins(&mut registry);
+        let init = Path::new(env!("CARGO_MANIFEST_DIR")).join("../init.lua");
+        Headless::new(registry, Some(&init)).unwrap()
+    }
+
+    #[test]
+    fn keys_edit_the_buffer() {
+        let h = headless();
+        h.keys("h i RET t h e r e DEL").unwrap();
+        assert_eq!(h.text(), "hi\nther");
+        assert_eq!(h.cursor(), (4, 1));
+        h.keys("C-a x").unwrap();
+        assert_eq!(h.text(), "hi\nxther");
+        assert!(h.keys("C-<nope>").is_err());
+    }
+
+    #[test]
+    fn command_steps_answer_their_prompt() {
+        let h = headless();
+        h.set_text("one\ntwo\nthree\n");
+        let step = Step::Command { name: "goto-line".into(), input: Some("3".into()) };
+        h.run_step(&step).unwrap();
+        assert_eq!(h.cursor(), (0, 2));
+        assert!(!h.prompt_open());
+        assert!(h.command("no-such-command").is_err());
+    }
+
+    #[test]
+    fn keyboard_macro_replays_typed_keys() {
+        let h = headless();
+        h.keys("C-x ( a b C-x )").unwrap();
+        assert_eq!(h.text(), "ab");
+        h.keys("C-x e").unwrap();
+        assert_eq!(h.text(), "abab");
+    }
+
+    #[test]
+    fn killing_a_modified_file_buffer_asks_first() {
+        let dir = std::env::temp_dir().join(format!("remux-harness-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "text").unwrap();
+        let h = headless();
+        h.open(&path).unwrap();
+        h.keys("x C-x k").unwrap();
+        assert!(h.prompt_open());
+        h.answer("n").unwrap();
+        assert_eq!(h.text(), "xtext");
+
+        h.keys("C-x k").unwrap();
+        h.answer("y").unwrap();
+        assert_ne!(h.editor.borrow().buffer.file_path.as_deref(), Some(path.as_path()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "text");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
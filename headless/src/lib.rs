@@ -0,0 +1,5 @@
+// remux-headless crate: the editor without a terminal, for `remux --batch`
+// and for driving commands from tests.
+pub mod cli;
+pub mod harness;
+pub mod batch;
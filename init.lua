@@ -73,14 +73,77 @@ bind("mod0", "s", "isearch-forward")
 bind("mod0", "r", "isearch-backward")
 bind("mod1", "x", "execute-command")
 bind("mod2", "f", "find-file")
+bind("mod2", "d", "dired")
+bind("mod2", "p", "project-find-file")
+bind("mod2", "P", "project-switch")
+bind("mod1", ".", "lsp-goto-definition")
+bind("mod1", "?", "lsp-find-references")
+bind("mod1", "/", "lsp-complete")
+bind("mod1", "n", "next-diagnostic")
+bind("mod1", "p", "previous-diagnostic")
 bind("mod1", "T", "toggle-line-wrap")
+bind("mod2", "b", "switch-to-buffer")
+bind("mod2", "k", "kill-buffer")
+bind("mod2", "(", "start-kbd-macro")
+bind("mod2", ")", "end-kbd-macro")
+bind("mod2", "e", "call-last-kbd-macro")
+bind("mod2", "x", "copy-to-register")
+bind("mod2", "g", "insert-register")
+bind("mod2", "/", "point-to-register")
+bind("mod2", "j", "jump-to-register")
+bind("mod2", "m", "bookmark-set")
+bind("mod2", "'", "bookmark-jump")
+bind("mod2", "l", "list-bookmarks")
+bind("mod2", "q", "toggle-read-only")
+bind("mod2", "?", "describe-key")
 
--- 7. Customization UX -----------------------------------------------------------------------------
+--- 7. External processes ------------------------------------------------------------------------
+bind("mod1", "!", "shell-command")
+bind("mod1", "&", "async-shell-command")
+bind("mod1", "|", "shell-command-on-region")
+bind("mod2", "`", "next-error")
+bind("mod2", "~", "previous-error")
+
+--- Compilation buffers have their own keys (RET jumps, n/p step, g reruns).
+--- Syntax: bind_mode("<major mode>", "mod", "<Char | RET | TAB | SPC>", "<Remux Function>")
+-- bind_mode("compilation", "", "q", "kill-buffer")
+-- set_compile_command("cargo test")
+-- set_grep_program("builtin")    -- auto | builtin | rg
+-- set_project_markers({ ".git", "Cargo.toml", ".hg", ".project" })
+-- set_bookmark_file("~/.local/share/remux/bookmarks.json")
+-- set_desktop_save_mode(true)    -- reopen this session's files next time
+-- set_save_place(true)           -- reopen every file where the cursor was left
+-- set_lsp_server("rust", { "rust-analyzer" })
+-- set_lsp_server("python", { "pylsp" })
+
+-- 8. Customization UX -----------------------------------------------------------------------------
 --- Border (true | false)
 add_hook("after-init-once", function(cmd)
   set_buffer_borders(false)
 end)
 
+--- Mode line segments (see HOOKS.md for the list)
+-- set_mode_line{
+--   left  = { "undo", "read-only", "buffer-id", "project", "diagnostics", "percent", "position" },
+--   right = { "git-branch", "major-mode" },
+-- }
+
+--- Line numbers: absolute | relative | hybrid | off (toggle-line-numbers switches them on and off)
+-- set_line_numbers("hybrid")
+
+--- Tabs: display width and whether indentation uses tab characters
+-- set_tab_width(8)
+-- set_indent_tabs_mode(true)
+
+--- Indentation rules per major mode (see HOOKS.md)
+-- set_indent_rule("lua", { style = "block", open = { "then", "do", "function", "{" },
+--                          close = { "end", "}" }, middle = { "else" }, comment = "--" })
+-- set_electric_indent(false)
+
+--- Brackets: insert closing pairs, highlight the matching bracket
+-- set_electric_pair(true)
+-- set_show_paren(false)
+
 add_hook("isearch-started", function()
   set_isearch_highlight(true)
 end)
@@ -107,7 +170,7 @@ end)
 --     "confirm-save-and-exit")
 -- end)
 
--- 8. Notes ----------------------------------------------------------------------------------------
+-- 9. Notes ----------------------------------------------------------------------------------------
 --[[
 
  Also Available Remux Commands for "execute-command" (mod+x):
@@ -115,6 +178,75 @@ end)
  save-buffer | Save file (buffer)
  save-buffer-as | Save file as <Enter> Name
  kill-remux | Quit (Kill Remux)
+ switch-to-buffer | Switch to (or create) a buffer by name
+ kill-buffer | Close the current buffer
+ shell-command | Run a shell command, show its output ("cmd &" runs it async)
+ async-shell-command | Run a shell command, stream output into *Async Shell Command*
+ shell-command-on-region | Pipe the region through a filter (sort, jq, ...) and replace it
+ compile | Run a build command into *compilation* (default: cargo build)
+ recompile | Run the last compile command again
+ next-error / previous-error | Jump to the next / previous file:line:col location
+ grep | Search files of the current project, results in *grep*
+ rgrep | Like grep, but asks for the directory
+ grep-cancel | Stop a running grep
+ dired | List a directory (find-file on a directory does the same)
+ project-find-file | Open a file of the current project (fuzzy completion)
+ project-switch | Pick a recent project and open a file in it
+ lsp-goto-definition | Jump to the definition of the symbol at point (language server)
+ lsp-find-references | List references of the symbol at point in *references*
+ lsp-hover | Show the type / documentation of the symbol at point
+ lsp-rename | Rename the symbol at point across the project
+ lsp-complete | Complete the symbol at point
+ lsp-restart | Restart the language server of the current buffer
+ next-diagnostic / previous-diagnostic | Jump to the next / previous problem and show it
+ toggle-line-numbers | Show or hide line numbers in the gutter
+ indent-for-tab-command | Indent to the next tab stop (TAB)
+ set-tab-width | Set the tab width of the current buffer
+ toggle-indent-tabs-mode | Indent the current buffer with tabs or spaces
+ newline-and-indent | Break the line and indent the new one (RET)
+ indent-region | Reindent every line of the region
+ electric-pair-mode | Toggle inserting closing brackets and quotes
+ show-paren-mode | Toggle highlighting the matching bracket
+ forward-sexp | Move over the next balanced expression
+ backward-sexp | Move over the previous balanced expression
+ rectangle-mark-mode | Show the region as the rectangle between mark and cursor
+ kill-rectangle | Delete the rectangle and remember it
+ copy-rectangle | Remember the rectangle
+ yank-rectangle | Insert the last rectangle at the cursor
+ string-rectangle | Replace each line of the rectangle with a string
+ open-rectangle | Shift the rectangle's text right, leaving blanks
+ clear-rectangle | Replace the rectangle with spaces
+ add-cursor-next-line | Add a cursor here and move to the next line
+ add-cursor-previous-line | Add a cursor here and move to the previous line
+ add-cursor-next-match | Add a cursor here and select the next match of the region
+ add-cursors-in-region | Put a cursor on every match of a string in the region
+ remove-extra-cursors | Go back to a single cursor
+ start-kbd-macro | Start recording a keyboard macro
+ end-kbd-macro | Stop recording it
+ call-last-kbd-macro | Replay the last keyboard macro (prefix: repeat count)
+ name-last-kbd-macro | Name the last macro, so it runs like a command
+ insert-kbd-macro | Insert Lua that defines a macro
+ apply-macro-to-region-lines | Run the last macro on each line of the region
+ copy-to-register | Save the region's text in a register
+ insert-register | Insert a text or number register at the cursor
+ point-to-register | Save the cursor's position in a register
+ jump-to-register | Go to the position saved in a register
+ number-to-register | Store the prefix argument (default 0) in a register
+ increment-register | Add the prefix argument (default 1) to a number register, or append the region to a text one
+ list-registers | Show every register in *Registers*
+ bookmark-set | Bookmark the cursor under a name (default: the file name)
+ bookmark-jump | Go to a bookmark, finding the spot again if the file changed
+ bookmark-delete | Delete a bookmark
+ list-bookmarks | Show the bookmarks in *Bookmark List*
+ desktop-save | Save the open files, positions, minibuffer histories and last kill
+ desktop-read | Restore the saved session
+ toggle-read-only | Make the buffer read-only, or writable again
+ view-mode | Read the buffer: SPC/DEL page, < and > go to either end, q quits
+ describe-key | Show the command the next key runs, and its doc, in *Help*
+ describe-command | Show a command's doc and the keys that run it
+ where-is | Say which keys run a command
+ describe-bindings | List the key bindings in effect in the buffer
+ view-echo-area-messages | Show *Messages*, every past message with its time
 
  Also there is "universal-command" -
  C-u C-f - moves cursor 4 characters forward
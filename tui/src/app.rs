@@ -25,12 +25,17 @@ use mlua::Lua;
 
 use remux_core::{
     editor::editor::{Editor, KeyMap},
+    editor::events::EditorEvent,
     config::UserConfig,
     command::CommandRegistry,
+    timer::run_due_timers,
+    process::run_process_callbacks,
+    status::eval_lua_segments,
 };
 
-use remux_config::{
-    lua::load_lua,
+use remux_headless::{
+    cli::{Options, open_files},
+    harness::Headless,
 };
 
 use crate::{
@@ -41,12 +46,22 @@ use crate::{
 use crate::hooks::isearch_highlight;
 use crate::view::selection::apply_selection;
 use crate::view::isearch::apply_isearch;
+use crate::view::compilation::apply_locations;
+use crate::view::diagnostics::apply_diagnostics;
+use crate::view::paren::apply_show_paren;
+use crate::view::cursors::apply_cursors;
+
+/// Upper bound on how long the main loop sleeps waiting for input.
+const TICK: Duration = Duration::from_millis(250);
+/// Shorter tick while child processes are streaming output or a language
+/// server owes an answer.
+const PROCESS_TICK: Duration = Duration::from_millis(30);
 
 pub struct App {
     terminal: Terminal<CrosstermBackend<std::io::Stdout>>,
     pub editor: Rc<RefCell<Editor>>,
     lua: Lua,
-    pub lua_events: Rc<RefCell<Vec<remux_core::editor::editor::EditorEvent>>>,
+    pub lua_events: Rc<RefCell<Vec<EditorEvent>>>,
     pub keymap: Rc<RefCell<KeyMap>>,
     pub user_config: Rc<RefCell<UserConfig>>,
 		pub view: View,
@@ -59,9 +74,34 @@ pub struct View {
 impl App {
     /// Init TUI + Editor
     pub fn init(
-				args: &[String],
+				opts: &Options,
 				registry: CommandRegistry,
     ) -> io::Result<Self> {
+				let headless = Headless::new(registry, opts.init_file().as_deref())
+						.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+				// Restore the last session, then open the command line's files on top
+				if headless.editor.borrow().user_config.borrow().desktop_save_mode {
+						headless.editor.borrow_mut().desktop_read();
+				}
+				let errors = open_files(&mut headless.editor.borrow_mut(), opts);
+				if let Some(first) = errors.first() {
+						headless.editor.borrow_mut().minibuffer.message(&format!("Cannot open {first}"));
+				}
+				for step in &opts.steps {
+						if let Err(e) = headless.run_step(step) {
+								headless.editor.borrow_mut().minibuffer.message(&e);
+								break;
+						}
+				}
+
+				let Headless { editor, lua, lua_events } = headless;
+				let keymap = editor.borrow().keymap.clone();
+				let user_config = editor.borrow().user_config.clone();
+				editor.borrow_mut().emit(EditorEvent::InitFinished);
+
+				// Only now take the terminal over, so that `-` can read a pipe
+				// and init errors are still readable.
 				enable_raw_mode()?;
 
 				let mut stdout = io::stdout();
@@ -69,31 +109,6 @@ impl App {
 
 				let backend = CrosstermBackend::new(stdout);
 				let terminal = Terminal::new(backend)?;
-				let lua = Lua::new();
-				let lua_events: Rc<RefCell<Vec<remux_core::editor::editor::EditorEvent>>> = Rc::new(RefCell::new(Vec::new()));
-				let keymap = Rc::new(RefCell::new(KeyMap::new()));
-				let user_config = Rc::new(RefCell::new(UserConfig::default()));
-				let editor = Rc::new(RefCell::new(Editor::new(registry, keymap.clone(), user_config.clone())));
-
-
-
-				load_lua(
-						&lua,
-						editor.clone(),
-						keymap.clone(),
-						lua_events.clone(),
-						user_config.clone(),
-				).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-
-				editor.borrow_mut().process_events(&lua);
-				
-				// открыть файл из argv
-				if args.len() > 1 {
-            let path = args[1].clone();
-						if let Err(e) = editor.borrow_mut().buffer.open_file(path.into()) {
-								eprintln!("Failed to open file: {e}");
-						}
-				}
 
 				Ok(Self {
             terminal,
@@ -112,8 +127,11 @@ impl App {
     while !self.editor.borrow().should_quit {
         self.tick()?;
     }
+    self.editor.borrow_mut().save_session();
 			 if self.editor.borrow().buffer.is_modified() {
-					 self.editor.borrow().hooks.run(&self.lua, "before-exit", "");
+					 let mut ed = self.editor.borrow_mut();
+					 ed.emit(EditorEvent::BeforeExit);
+					 ed.process_events(&self.lua);
 			 }
 
     self.shutdown()
@@ -121,18 +139,53 @@ impl App {
 
 
 fn tick(&mut self) -> io::Result<()> {
-    if event::poll(Duration::from_millis(250))? {
-        handle_input(&self.lua, &self.editor, &self.keymap, &self.user_config)?;
+    let timers = self.editor.borrow().timers.clone();
+    let mut timeout = timers.borrow()
+        .time_until_next()
+        .map_or(TICK, |next| next.min(TICK));
+    let busy = {
+        let ed = self.editor.borrow();
+        ed.processes.borrow().has_running() || ed.grep_search.is_some() || ed.lsp_busy()
+    };
+    if busy {
+        timeout = timeout.min(PROCESS_TICK);
+    }
+
+    if event::poll(timeout)? {
+        timers.borrow_mut().note_activity();
+        handle_input(&self.editor)?;
     }
+    self.editor.borrow_mut().replay_macros();
+
+    // Timer callbacks may call back into the editor, so it must not be
+    // borrowed while they run.
+    let log = self.editor.borrow().log.clone();
+    run_due_timers(&timers, &self.lua, &log);
+
+    let callbacks = self.editor.borrow_mut().poll_processes();
+    self.editor.borrow_mut().poll_grep();
+    {
+        let mut ed = self.editor.borrow_mut();
+        ed.lsp_sync();
+        ed.poll_lsp();
+    }
+    run_process_callbacks(&self.lua, callbacks, &log);
+
+    // Lua mode line segments may read the editor, so evaluate them here
+    // rather than while drawing.
+    let segments = self.editor.borrow().mode_line_segments.clone();
+    let values = eval_lua_segments(&segments, &self.lua);
+    self.editor.borrow_mut().mode_line_values = values;
 
     {
         let mut ed = self.editor.borrow_mut();
         let mut lua_events = self.lua_events.borrow_mut();
 
-        ed.event_queue.extend(lua_events.drain(..));
-        ed.process_events(&self.lua);
+        ed.emit(EditorEvent::Tick);
+        ed.bus.extend(lua_events.drain(..));
+        drop(lua_events);
 
-        let events = std::mem::take(&mut ed.event_queue);
+        let events = ed.process_events(&self.lua);
 
 				for ev in &events {
 						isearch_highlight::handle_isearch_event(&mut ed, ev);
@@ -140,6 +193,10 @@ fn tick(&mut self) -> io::Result<()> {
 
 				apply_isearch(&ed, &mut self.view.render);
 				apply_selection(&ed, &mut self.view.render);
+				apply_locations(&ed, &mut self.view.render);
+				apply_diagnostics(&ed, &mut self.view.render);
+				apply_show_paren(&ed, &mut self.view.render);
+				apply_cursors(&ed, &mut self.view.render);
     }
 
     self.draw()
@@ -150,7 +207,7 @@ fn tick(&mut self) -> io::Result<()> {
 
 				self.terminal.draw(|f| {
 						let mut ed = editor.borrow_mut();
-						render_editor(f, &mut ed, &mut self.view.render, &self.lua);
+						render_editor(f, &mut ed, &mut self.view.render);
 				})?;
 
         Ok(())
@@ -1,4 +1,4 @@
-use remux_core::editor::editor::{Editor, EditorEvent};
+use remux_core::editor::{editor::Editor, events::EditorEvent};
 
 pub fn handle_isearch_event(editor: &mut Editor, ev: &EditorEvent) {
     match ev {
@@ -4,5 +4,5 @@ pub mod render;
 pub mod hooks;
 pub mod view;
 
-pub type LuaEventQueue = std::rc::Rc<std::cell::RefCell<Vec<remux_core::editor::editor::EditorEvent>>>;
+pub type LuaEventQueue = std::rc::Rc<std::cell::RefCell<Vec<remux_core::editor::events::EditorEvent>>>;
 
@@ -1,12 +1,33 @@
 use std::env;
+use std::process::exit;
 use remux_tui::app::App;
+use remux_headless::batch::run_batch;
+use remux_headless::cli::{parse_args, Action, USAGE};
 use remux_core::command::CommandRegistry;
 use remux_core::commands::builtins::register_builtins;
 
 fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
+    let opts = match parse_args(&args) {
+        Ok(Action::Run(opts)) => opts,
+        Ok(Action::Help) => {
+            print!("{USAGE}");
+            return Ok(());
+        }
+        Ok(Action::Version) => {
+            println!("remux {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("remux: {e}\n\n{USAGE}");
+            exit(2);
+        }
+    };
     let mut registry = CommandRegistry::new();
     register_builtins(&mut registry);
-    let mut app = App::init(&args, registry)?;
+    if opts.batch {
+        exit(run_batch(&opts, registry));
+    }
+    let mut app = App::init(&opts, registry)?;
     app.run()
 }
@@ -3,10 +3,12 @@ use ratatui::{
     layout::Rect,
     widgets::{Paragraph, Clear},
     text::{Line, Span},
+    style::{Style, Color},
 };
 
-use unicode_width::{UnicodeWidthChar};
 use remux_core::editor::editor::{Editor};
+use remux_core::editor::layout::{char_width, column_of};
+use remux_core::diagnostics::Severity;
 use crate::view::RenderState;
 
 pub fn render_buffer(
@@ -17,16 +19,38 @@ pub fn render_buffer(
 ) {
     let mut lines = Vec::new();
 
+    let tab = editor.tab_width();
     for vis in editor.iter_visible_visual_lines() {
         let buf_y = vis.buffer_y;
         let line = &editor.buffer.lines[buf_y];
-
-        let slice = slice_by_cell(&line.text, vis.start_x, vis.len);
+        let (left, right) = (vis.start_col, vis.start_col + area.width as usize);
 
         let mut spans = Vec::new();
-        for (i, ch) in slice.chars().enumerate() {
-            let style = render.style_at(vis.start_x + i, buf_y).unwrap_or_default();
-            spans.push(Span::styled(ch.to_string(), style));
+        let mut col = column_of(&line.text, vis.start_x, tab);
+        for (i, ch) in line.text.chars().enumerate().skip(vis.start_x).take(vis.len) {
+            let w = char_width(ch, col, tab);
+            let (start, end) = (col.max(left), (col + w).min(right));
+            col += w;
+            if start >= end {
+                if start >= right { break; }
+                continue;
+            }
+            // Tabs, and wide chars cut by the left edge, show as blanks.
+            let text = if ch == '\t' || end - start < w {
+                " ".repeat(end - start)
+            } else {
+                ch.to_string()
+            };
+            let style = render.style_at(i, buf_y).unwrap_or_default();
+            spans.push(Span::styled(text, style));
+        }
+        // A highlight just past the end of the line (a cursor there) shows
+        // as a blank.
+        let at_end = vis.start_x + vis.len == line.char_len;
+        if at_end && col >= left && col < right {
+            if let Some(style) = render.style_at(line.char_len, buf_y) {
+                spans.push(Span::styled(" ", style));
+            }
         }
 
         lines.push(Line::from(spans));
@@ -37,41 +61,49 @@ pub fn render_buffer(
     f.render_widget(paragraph, area);
 }
 
-fn slice_by_cell(s: &str, start: usize, width: usize) -> String {
-    const TABSTOP: usize = 4;
-    let mut cur = 0;
-    let mut out = String::new();
-    for ch in s.chars() {
-        if ch == '\t' {
-            let tab_w = TABSTOP - (cur % TABSTOP);
-            if cur + tab_w <= start {
-                cur += tab_w;
-                continue;
-            }
-            let visible_start = start.saturating_sub(cur);
-            let visible_end = (start + width).saturating_sub(cur);
-            let from = visible_start.min(tab_w);
-            let to   = visible_end.min(tab_w);
+/// Signs for diagnostics (the most severe on each line) and line numbers,
+/// right-aligned. Continuation rows of wrapped lines stay blank.
+pub fn render_gutter(f: &mut Frame, editor: &Editor, area: Rect) {
+    let diagnostics = editor.buffer_diagnostics();
+    let signs = editor.sign_column_width();
+    let numbers = editor.line_number_width();
+    let mut lines = Vec::new();
+    let mut prev_y = None;
 
-            if from < to {
-                out.extend(std::iter::repeat(' ').take(to - from));
-            }
-            cur += tab_w;
-            if cur >= start + width {
-                break;
-            }
-            continue;
-        }
-        let w = UnicodeWidthChar::width(ch).unwrap_or(0);
-        if cur + w <= start {
-            cur += w;
-            continue;
+    for vis in editor.iter_visible_visual_lines() {
+        let first_row = prev_y != Some(vis.buffer_y);
+        prev_y = Some(vis.buffer_y);
+        let mut spans = Vec::new();
+
+        if signs > 0 {
+            let worst = diagnostics.iter()
+                .filter(|d| d.line == vis.buffer_y)
+                .map(|d| d.severity)
+                .min();
+            spans.push(match worst {
+                Some(Severity::Error) if first_row => Span::styled("E ", Style::default().fg(Color::Red)),
+                Some(Severity::Warning) if first_row => Span::styled("W ", Style::default().fg(Color::Yellow)),
+                Some(Severity::Info) if first_row => Span::styled("I ", Style::default().fg(Color::Blue)),
+                Some(Severity::Hint) if first_row => Span::styled("H ", Style::default().fg(Color::DarkGray)),
+                _ => Span::raw("  "),
+            });
         }
-        if cur >= start + width {
-            break;
+        if numbers > 0 {
+            if first_row {
+                let label = editor.line_number_label(vis.buffer_y);
+                let style = if vis.buffer_y == editor.buffer.cursor_y {
+                    Style::default().fg(Color::White)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                spans.push(Span::styled(format!("{:>w$} ", label, w = numbers - 1), style));
+            } else {
+                spans.push(Span::raw(" ".repeat(numbers)));
+            }
         }
-        out.push(ch);
-        cur += w;
+        lines.push(Line::from(spans));
     }
-    out
+
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines), area);
 }
@@ -4,11 +4,10 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     style::{Style, Color},
 };
-use mlua::Lua;
 use remux_core::editor::editor::Editor;
 use crate::view::RenderState;
 use crate::render::{
-    buffer::render_buffer,
+    buffer::{render_buffer, render_gutter},
     status::render_status,
     minibuffer::render_minibuffer,
 };
@@ -17,14 +16,8 @@ pub fn render_editor(
     f: &mut Frame,
     editor: &mut Editor,
 		render: &mut RenderState,
-    lua: &Lua,
 ) {
     let size = f.size();
-    let status_info = remux_core::status::build_status(editor);
-    let status_line = render_status(&status_info);
-    let status_bar = Paragraph::new(status_line)
-	.style(Style::default().bg(Color::DarkGray))
-	.block(Block::default());
 
     // ────────────────────────────────────────────────────────────
     // Layout
@@ -47,12 +40,16 @@ pub fn render_editor(
         ])
         .split(size);
 
+    let status = remux_core::status::build_status(editor);
+    let status_bar = Paragraph::new(render_status(&status, chunks[1].width as usize))
+	.style(Style::default().bg(Color::DarkGray))
+	.block(Block::default());
+
 		render_buffer_area(
 				f,
 				editor,
 				chunks[0],
 				render,
-				lua,
 		);
     f.render_widget(status_bar, chunks[1]);
     render_minibuffer(f, editor, chunks[2]);
@@ -68,10 +65,7 @@ fn render_buffer_area(
     editor: &mut Editor,
     area: Rect,
 		render: &mut RenderState,
-    lua: &Lua,
 ) {
-    editor.hooks.run_once(lua, "after-init-once", "");
-
     let show_borders = editor.user_config.borrow().buffer_borders;
     let block = if show_borders {
         Block::default().borders(Borders::ALL)
@@ -80,16 +74,19 @@ fn render_buffer_area(
     };
 
     let inner = block.inner(area);
+    let gutter = (editor.gutter_width() as u16).min(inner.width);
+    let gutter_area = Rect { width: gutter, ..inner };
+    let text_area = Rect { x: inner.x + gutter, width: inner.width - gutter, ..inner };
 
-    editor.viewport_width  = inner.width  as usize;
-    editor.viewport_height = inner.height as usize;
+    editor.viewport_width  = text_area.width  as usize;
+    editor.viewport_height = text_area.height as usize;
 
-    editor.buffer.rebuild_visual_metrics(
-        editor.viewport_width,
-        editor.wrap_mode,
-    );
+    editor.rebuild_visual_metrics();
 
-		render_buffer(f, editor, inner, render);
+    if gutter > 0 {
+        render_gutter(f, editor, gutter_area);
+    }
+		render_buffer(f, editor, text_area, render);
 }
 
 
@@ -1,15 +1,47 @@
 use ratatui::{
     Frame,
     layout::Rect,
+    text::{Line, Span},
     widgets::{Paragraph, Block, Borders},
-    style::{Style, Color},
+    style::{Style, Color, Modifier},
 };
+use unicode_width::UnicodeWidthStr;
 use remux_core::editor::editor::Editor;
 
 pub fn render_minibuffer(f: &mut Frame, editor: &Editor, area: Rect) {
     let content = editor.minibuffer.get();
+    let mut spans = vec![Span::raw(content)];
 
-    let paragraph = Paragraph::new(content)
+    // Matches of a completing prompt follow the input: `{a | b | c}`,
+    // as many as fit, starting at the selected one.
+    if let Some(c) = editor.minibuffer.completions() {
+        let mut room = (area.width as usize).saturating_sub(content.width() + 4);
+        let dim = Style::default().fg(Color::DarkGray);
+        spans.push(Span::styled(" {", dim));
+        if c.match_count() == 0 {
+            spans.push(Span::styled("No match", dim));
+        }
+        let skip = c.selected_index();
+        for (i, m) in c.matches().skip(skip).enumerate() {
+            let w = m.width() + 3;
+            if i > 0 && w > room {
+                spans.push(Span::styled(" | ...", dim));
+                break;
+            }
+            room = room.saturating_sub(w);
+            if i > 0 {
+                spans.push(Span::styled(" | ", dim));
+            }
+            let style = match i {
+                0 => Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                _ => Style::default().fg(Color::Gray),
+            };
+            spans.push(Span::styled(m.to_string(), style));
+        }
+        spans.push(Span::styled("}", dim));
+    }
+
+    let paragraph = Paragraph::new(Line::from(spans))
         .style(Style::default().fg(Color::Yellow))
         .block(Block::default().borders(Borders::NONE));
 
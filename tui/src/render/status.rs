@@ -1,20 +1,64 @@
-use remux_core::status::StatusInfo;
-pub fn render_status(info: &StatusInfo) -> String {
-    let undo = if info.undo_depth > 0 {
-        format!("U:{}", "*".repeat(info.undo_depth.min(3)))
-    } else {
-        "U:—".to_string()
-    };
+use ratatui::{
+    text::{Line, Span},
+    style::{Style, Color, Modifier},
+};
+use unicode_width::UnicodeWidthStr;
+use remux_core::status::{StatusLine, StatusText, Face};
+
+fn color(name: &Option<String>) -> Option<Color> {
+    name.as_deref().and_then(|n| n.parse::<Color>().ok())
+}
+
+fn face_style(face: &Face) -> Style {
+    let mut style = Style::default();
+    if let Some(fg) = color(&face.fg) {
+        style = style.fg(fg);
+    }
+    if let Some(bg) = color(&face.bg) {
+        style = style.bg(bg);
+    }
+    if face.bold {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if face.italic {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if face.underline {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    style
+}
+
+/// Segments separated by single spaces.
+fn segment_spans(segments: &[StatusText]) -> (Vec<Span<'static>>, usize) {
+    let mut spans = Vec::new();
+    let mut width = 0;
+    for (i, seg) in segments.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(" "));
+            width += 1;
+        }
+        width += seg.text.width();
+        spans.push(Span::styled(seg.text.clone(), face_style(&seg.face)));
+    }
+    (spans, width)
+}
 
-    let modified = if info.modified { "*" } else { "" };
+/// Left segments after a space, right segments flush against the right
+/// edge (less one space). When both do not fit, the right side is dropped.
+pub fn render_status(status: &StatusLine, width: usize) -> Line<'static> {
+    let (left, left_w) = segment_spans(&status.left);
+    let (right, right_w) = segment_spans(&status.right);
 
-    format!(
-        " {} {}{} {:>3} ({}, {}) ",
-        undo,
-        info.file_name,
-        modified,
-        info.scroll_percent,
-        info.cursor_line,
-        info.cursor_col
-    )
+    let mut spans = vec![Span::raw(" ")];
+    spans.extend(left);
+    let used = 1 + left_w;
+    if right_w > 0 && used + 1 + right_w < width {
+        spans.push(Span::raw(" ".repeat(width - used - right_w - 1)));
+        spans.extend(right);
+        spans.push(Span::raw(" "));
+    } else {
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
 }
@@ -0,0 +1,44 @@
+use remux_core::editor::editor::Editor;
+
+use crate::view::{
+    RenderState,
+    Highlight,
+    HighlightGroup,
+    HighlightPriority,
+};
+
+/// Mark the `file:line:col` references of a result buffer (`*compilation*`,
+/// grep output) so they read as jump targets. Only visible lines are marked.
+pub fn apply_locations(editor: &Editor, render: &mut RenderState) {
+    render.clear_group(HighlightGroup::ErrorLocation);
+    render.clear_group(HighlightGroup::ErrorLocationCurrent);
+
+    let Some(list) = editor.locations.get(&editor.buffer.file_name()) else {
+        return;
+    };
+
+    let mut visible = editor.iter_visible_visual_lines().map(|v| v.buffer_y);
+    let Some(first) = visible.next() else {
+        return;
+    };
+    let last = visible.last().unwrap_or(first);
+
+    for (i, loc) in list.items.iter().enumerate() {
+        if loc.y < first || loc.y > last {
+            continue;
+        }
+        let group = if list.current == Some(i) {
+            HighlightGroup::ErrorLocationCurrent
+        } else {
+            HighlightGroup::ErrorLocation
+        };
+        render.add(Highlight {
+            x: loc.x,
+            y: loc.y,
+            len: loc.len,
+            group: group.clone(),
+            priority: HighlightPriority::Normal,
+            style: Highlight::style_for(group),
+        });
+    }
+}
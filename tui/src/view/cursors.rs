@@ -0,0 +1,24 @@
+use remux_core::editor::editor::Editor;
+
+use crate::view::{
+    RenderState,
+    Highlight,
+    HighlightGroup,
+    HighlightPriority,
+};
+
+/// Draw the cursors besides the main one, which the terminal shows.
+pub fn apply_cursors(editor: &Editor, render: &mut RenderState) {
+    render.clear_group(HighlightGroup::Cursor);
+
+    for cursor in &editor.buffer.cursors {
+        render.add(Highlight {
+            x: cursor.x,
+            y: cursor.y,
+            len: 1,
+            group: HighlightGroup::Cursor,
+            priority: HighlightPriority::Critical,
+            style: Highlight::style_for(HighlightGroup::Cursor),
+        });
+    }
+}
@@ -0,0 +1,54 @@
+use remux_core::editor::editor::Editor;
+use remux_core::diagnostics::Severity;
+
+use crate::view::{
+    RenderState,
+    Highlight,
+    HighlightGroup,
+    HighlightPriority,
+};
+
+/// Underline the ranges of the current buffer's diagnostics. A range
+/// spanning lines is marked line by line; an empty one still marks a char.
+pub fn apply_diagnostics(editor: &Editor, render: &mut RenderState) {
+    render.clear_group(HighlightGroup::DiagnosticError);
+    render.clear_group(HighlightGroup::DiagnosticWarning);
+    render.clear_group(HighlightGroup::DiagnosticInfo);
+
+    let diagnostics = editor.buffer_diagnostics();
+    if diagnostics.is_empty() {
+        return;
+    }
+    let mut visible = editor.iter_visible_visual_lines().map(|v| v.buffer_y);
+    let Some(first) = visible.next() else {
+        return;
+    };
+    let last = visible.last().unwrap_or(first);
+    let lines = &editor.buffer.lines;
+
+    for d in diagnostics {
+        let group = match d.severity {
+            Severity::Error => HighlightGroup::DiagnosticError,
+            Severity::Warning => HighlightGroup::DiagnosticWarning,
+            Severity::Info | Severity::Hint => HighlightGroup::DiagnosticInfo,
+        };
+        let from = d.line.max(first);
+        let to = d.end_line.min(last);
+        if from > to {
+            continue;
+        }
+        for (y, line) in lines.iter().enumerate().take(to + 1).skip(from) {
+            let line_len = line.char_len;
+            let x = if y == d.line { d.col } else { 0 };
+            let end = if y == d.end_line { d.end_col } else { line_len };
+            render.add(Highlight {
+                x,
+                y,
+                len: end.saturating_sub(x).max(1),
+                group: group.clone(),
+                priority: HighlightPriority::Low,
+                style: Highlight::style_for(group.clone()),
+            });
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use ratatui::{
-		style::{Style, Color},
+		style::{Style, Color, Modifier},
 };
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
@@ -15,7 +15,13 @@ pub enum HighlightGroup {
     ISearch,
 		ISearchCurrent,
     Selection,
-    // Diagnostics,
+    ErrorLocation,
+    ErrorLocationCurrent,
+    DiagnosticError,
+    DiagnosticWarning,
+    DiagnosticInfo,
+    ParenMatch,
+    Cursor,
 }
 
 #[derive(Clone, Debug)]
@@ -39,6 +45,27 @@ impl Highlight {
 
             HighlightGroup::Selection =>
                 Style::default().bg(Color::White).fg(Color::Black),
+
+            HighlightGroup::ErrorLocation =>
+                Style::default().fg(Color::LightRed).add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::ErrorLocationCurrent =>
+                Style::default().bg(Color::LightRed).fg(Color::Black),
+
+            HighlightGroup::DiagnosticError =>
+                Style::default().fg(Color::Red).add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::DiagnosticWarning =>
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::DiagnosticInfo =>
+                Style::default().add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::ParenMatch =>
+                Style::default().bg(Color::Cyan).fg(Color::Black),
+
+            HighlightGroup::Cursor =>
+                Style::default().add_modifier(Modifier::REVERSED),
 						
         }
     }
@@ -53,6 +80,27 @@ impl Highlight {
 
             HighlightGroup::Selection =>
                 Style::default().bg(Color::White).fg(Color::Black),
+
+            HighlightGroup::ErrorLocation =>
+                Style::default().fg(Color::LightRed).add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::ErrorLocationCurrent =>
+                Style::default().bg(Color::LightRed).fg(Color::Black),
+
+            HighlightGroup::DiagnosticError =>
+                Style::default().fg(Color::Red).add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::DiagnosticWarning =>
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::DiagnosticInfo =>
+                Style::default().add_modifier(Modifier::UNDERLINED),
+
+            HighlightGroup::ParenMatch =>
+                Style::default().bg(Color::Cyan).fg(Color::Black),
+
+            HighlightGroup::Cursor =>
+                Style::default().add_modifier(Modifier::REVERSED),
         }
     }
 		
@@ -2,6 +2,10 @@ pub mod highlight;
 pub mod render_state;
 pub mod selection;
 pub mod isearch;
+pub mod compilation;
+pub mod diagnostics;
+pub mod paren;
+pub mod cursors;
 
 pub use highlight::*;
 pub use render_state::*;
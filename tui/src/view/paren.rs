@@ -0,0 +1,27 @@
+use remux_core::editor::editor::Editor;
+
+use crate::view::{
+    RenderState,
+    Highlight,
+    HighlightGroup,
+    HighlightPriority,
+};
+
+/// Mark the bracket at the cursor and the one matching it.
+pub fn apply_show_paren(editor: &Editor, render: &mut RenderState) {
+    render.clear_group(HighlightGroup::ParenMatch);
+
+    let Some((at, other)) = editor.matching_paren() else {
+        return;
+    };
+    for pos in [at, other] {
+        render.add(Highlight {
+            x: pos.x,
+            y: pos.y,
+            len: 1,
+            group: HighlightGroup::ParenMatch,
+            priority: HighlightPriority::Normal,
+            style: Highlight::style_for(HighlightGroup::ParenMatch),
+        });
+    }
+}
@@ -1,5 +1,6 @@
 use ratatui::style::{Style, Color};
 use remux_core::editor::editor::Editor;
+use remux_core::rectangle::rectangle_span;
 
 use crate::view::{
     RenderState,
@@ -19,6 +20,11 @@ pub fn apply_selection(editor: &Editor, render: &mut RenderState) {
         .bg(Color::White)
         .fg(Color::Black);
 
+    if editor.buffer.rectangle_mark {
+        apply_rectangle(editor, render, style);
+        return;
+    }
+
     for y in sel.start.y..=sel.end.y {
         let line = &editor.buffer.lines[y];
         let line_len = line.char_len;
@@ -40,3 +46,27 @@ pub fn apply_selection(editor: &Editor, render: &mut RenderState) {
         });
     }
 }
+
+/// The chars of each line that start inside the rectangle's columns.
+fn apply_rectangle(editor: &Editor, render: &mut RenderState, style: Style) {
+    let Some(rect) = editor.rectangle() else {
+        return;
+    };
+    let tab = editor.tab_width();
+
+    for y in rect.top..=rect.bottom {
+        let (a, b) = rectangle_span(&editor.buffer.lines[y].text, rect.left, rect.right, tab);
+        if a >= b {
+            continue;
+        }
+
+        render.add(Highlight {
+            x: a,
+            y,
+            len: b - a,
+            group: HighlightGroup::Selection,
+            priority: HighlightPriority::High,
+            style,
+        });
+    }
+}